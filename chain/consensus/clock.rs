@@ -0,0 +1,47 @@
+// chain/consensus/clock.rs
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// Source of the current unix time, so deadline logic can be driven by a
+/// fake clock in tests instead of real sleeps.
+pub trait Clock: Send + Sync {
+    fn now_secs(&self) -> u64;
+}
+
+/// Wall-clock time from the operating system.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_secs(&self) -> u64 {
+        std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs()
+    }
+}
+
+/// Manually advanced clock for tests.
+#[derive(Debug, Default)]
+pub struct MockClock {
+    now: AtomicU64,
+}
+
+impl MockClock {
+    pub fn new(now_secs: u64) -> Self {
+        MockClock { now: AtomicU64::new(now_secs) }
+    }
+
+    pub fn set(&self, now_secs: u64) {
+        self.now.store(now_secs, Ordering::SeqCst);
+    }
+
+    pub fn advance(&self, secs: u64) {
+        self.now.fetch_add(secs, Ordering::SeqCst);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_secs(&self) -> u64 {
+        self.now.load(Ordering::SeqCst)
+    }
+}
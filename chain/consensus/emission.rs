@@ -0,0 +1,70 @@
+// chain/consensus/emission.rs
+use serde::{Deserialize, Serialize};
+
+/// Block reward schedule: `initial_reward` per block, halved every
+/// `halving_interval` blocks but never below `min_reward`. A zero
+/// `min_reward` lets emission run out entirely.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmissionSchedule {
+    pub initial_reward: u64,
+    pub halving_interval: u64,
+    pub min_reward: u64,
+}
+
+impl Default for EmissionSchedule {
+    fn default() -> Self {
+        EmissionSchedule {
+            initial_reward: 50,
+            halving_interval: 210_000,
+            min_reward: 1,
+        }
+    }
+}
+
+impl EmissionSchedule {
+    fn halvings(&self, height: u64) -> u64 {
+        height / self.halving_interval.max(1)
+    }
+
+    /// Base reward paid for the block at `height`.
+    pub fn reward_at(&self, height: u64) -> u64 {
+        let halvings = self.halvings(height);
+        let halved = if halvings >= 64 { 0 } else { self.initial_reward >> halvings };
+        halved.max(self.min_reward)
+    }
+
+    /// First height at which the reward is halved again after `height`.
+    pub fn next_halving_height(&self, height: u64) -> u64 {
+        (self.halvings(height) + 1).saturating_mul(self.halving_interval.max(1))
+    }
+
+    /// Total reward paid for blocks `1..=height`.
+    pub fn emitted_through(&self, height: u64) -> u64 {
+        let mut emitted: u64 = 0;
+        let mut era_start = 1;
+
+        while era_start <= height {
+            let reward = self.reward_at(era_start);
+            if reward == 0 {
+                break;
+            }
+            let era_end = self.next_halving_height(era_start).saturating_sub(1).min(height);
+            emitted = emitted.saturating_add((era_end - era_start + 1).saturating_mul(reward));
+            if era_end == u64::MAX {
+                break;
+            }
+            era_start = era_end + 1;
+        }
+        emitted
+    }
+
+    /// Total reward that will ever be paid under this schedule, or `None`
+    /// when a tail reward keeps emission going forever.
+    pub fn projected_total(&self) -> Option<u64> {
+        if self.min_reward > 0 {
+            None
+        } else {
+            Some(self.emitted_through(u64::MAX))
+        }
+    }
+}
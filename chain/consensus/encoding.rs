@@ -0,0 +1,24 @@
+// chain/consensus/encoding.rs
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// Compact binary encoding used for storage. JSON stays the format of the
+/// HTTP API, with CBOR accepted where external toolchains submit data.
+pub fn to_bytes<T: Serialize>(value: &T) -> Vec<u8> {
+    bincode::serialize(value).expect("chain types always serialize")
+}
+
+pub fn from_bytes<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    bincode::deserialize(bytes).map_err(|e| format!("Invalid binary encoding: {}", e))
+}
+
+/// CBOR encoding, for quantum toolchains that exchange proofs in it
+pub fn to_cbor<T: Serialize>(value: &T) -> Vec<u8> {
+    let mut bytes = Vec::new();
+    ciborium::ser::into_writer(value, &mut bytes).expect("chain types always serialize");
+    bytes
+}
+
+pub fn from_cbor<T: DeserializeOwned>(bytes: &[u8]) -> Result<T, String> {
+    ciborium::de::from_reader(bytes).map_err(|e| format!("Invalid CBOR encoding: {}", e))
+}
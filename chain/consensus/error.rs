@@ -0,0 +1,41 @@
+// chain/consensus/error.rs
+use std::fmt;
+
+/// Errors returned by consensus operations. `Display` keeps the wording of
+/// the original string errors.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConsensusError {
+    ChallengeNotFound,
+    DeadlineExceeded,
+    InvalidProof,
+    QubitCountMismatch { expected: usize, actual: usize },
+    InsufficientMeasurements { required_bits: usize, actual_bits: usize },
+    ValidatorNotFound,
+    InvalidSignature,
+    DuplicateProof,
+}
+
+impl fmt::Display for ConsensusError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConsensusError::ChallengeNotFound => f.write_str("Challenge not found"),
+            ConsensusError::DeadlineExceeded => f.write_str("Challenge deadline exceeded"),
+            ConsensusError::InvalidProof => f.write_str("Invalid quantum proof"),
+            ConsensusError::QubitCountMismatch { expected, actual } => write!(
+                f,
+                "Proof uses {} qubits but the challenge requires {}",
+                actual, expected
+            ),
+            ConsensusError::InsufficientMeasurements { required_bits, actual_bits } => write!(
+                f,
+                "Proof has {} measurement bits but the challenge needs at least {}",
+                actual_bits, required_bits
+            ),
+            ConsensusError::ValidatorNotFound => f.write_str("Validator not found"),
+            ConsensusError::InvalidSignature => f.write_str("Invalid signature"),
+            ConsensusError::DuplicateProof => f.write_str("Proof already submitted"),
+        }
+    }
+}
+
+impl std::error::Error for ConsensusError {}
@@ -0,0 +1,11 @@
+// chain/consensus/events.rs
+use serde::{Deserialize, Serialize};
+
+/// Notable consensus activity, published to subscribers as it happens
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ConsensusEvent {
+    BlockProposed(u64),
+    BlockFinalized(u64),
+    ProofAccepted { validator: String, reward: u64 },
+    ValidatorSlashed { address: String, amount: u64 },
+}
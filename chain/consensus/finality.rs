@@ -0,0 +1,78 @@
+// chain/consensus/finality.rs
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use super::pos::Validator;
+use super::quorum::QuorumCertificate;
+use crate::chain::crypto;
+
+/// A validator's vote to finalize a block, signed over its hybrid hash
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Attestation {
+    pub block_hash: String,
+    pub validator: String,
+    pub public_key: String, // Must hash to `validator`
+    pub signature: String,  // Hex signature over `block_hash`
+}
+
+impl Attestation {
+    /// True when the key belongs to the validator and signed the block hash
+    pub fn is_valid(&self) -> bool {
+        crypto::address_from_public_key(&self.public_key).as_deref() == Some(self.validator.as_str())
+            && crypto::verify(&self.public_key, self.block_hash.as_bytes(), &self.signature)
+    }
+}
+
+/// Collects attestations until validators holding more than 2/3 of the
+/// stake have voted for a block, which then becomes irreversible.
+#[derive(Debug, Default)]
+pub struct FinalityGadget {
+    // Block hash -> (height, validator -> signature)
+    pending: HashMap<String, (u64, BTreeMap<String, String>)>,
+    pub finalized_height: u64,
+    pub finalized_hash: Option<String>,
+}
+
+impl FinalityGadget {
+    /// Records a checked attestation for the block at `height`. Returns the
+    /// block's certificate if this vote carried it past 2/3 of `total_stake`.
+    pub fn record(
+        &mut self,
+        attestation: Attestation,
+        height: u64,
+        validators: &HashMap<String, Validator>,
+        total_stake: u64,
+    ) -> Option<QuorumCertificate> {
+        if height <= self.finalized_height && self.finalized_hash.is_some() {
+            return None;
+        }
+
+        let (_, votes) = self.pending
+            .entry(attestation.block_hash.clone())
+            .or_insert_with(|| (height, BTreeMap::new()));
+        votes.insert(attestation.validator, attestation.signature);
+
+        // Stake is read at counting time, so validators who left no longer count
+        let attested: u64 = votes.keys()
+            .filter_map(|validator| validators.get(validator))
+            .map(|validator| validator.stake)
+            .sum();
+        if total_stake == 0 || attested as u128 * 3 <= total_stake as u128 * 2 {
+            return None;
+        }
+
+        let certificate = QuorumCertificate {
+            block_hash: attestation.block_hash.clone(),
+            signatures: votes.iter().map(|(validator, signature)| (validator.clone(), signature.clone())).collect(),
+            total_power: total_stake,
+        };
+        self.finalized_height = height;
+        self.finalized_hash = Some(attestation.block_hash);
+        // Votes for blocks at or below the finalized one can no longer matter
+        self.pending.retain(|_, (pending_height, _)| *pending_height > height);
+        Some(certificate)
+    }
+
+    pub fn is_finalized(&self, block_hash: &str) -> bool {
+        self.finalized_hash.as_deref() == Some(block_hash)
+    }
+}
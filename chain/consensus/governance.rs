@@ -0,0 +1,168 @@
+// chain/consensus/governance.rs
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GovernanceError {
+    ProposalNotFound,
+    EmptyDescription,
+    DeadlinePassed,
+    VotingClosed,
+    NotEligible,
+    AlreadyVoted,
+}
+
+impl fmt::Display for GovernanceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GovernanceError::ProposalNotFound => f.write_str("Proposal not found"),
+            GovernanceError::EmptyDescription => f.write_str("Proposal description is empty"),
+            GovernanceError::DeadlinePassed => f.write_str("Proposal deadline is not in the future"),
+            GovernanceError::VotingClosed => f.write_str("Voting on this proposal has closed"),
+            GovernanceError::NotEligible => f.write_str("Only validators and delegators with stake may take part"),
+            GovernanceError::AlreadyVoted => f.write_str("Already voted on this proposal"),
+        }
+    }
+}
+
+impl std::error::Error for GovernanceError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VoteChoice {
+    Yes,
+    No,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Outcome {
+    Pending, // Voting is still open
+    Passed,
+    Rejected,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Proposal {
+    pub id: u64,
+    pub description: String,
+    pub proposer: String,
+    pub deadline: u64,                             // Last block height at which votes are accepted
+    pub votes: BTreeMap<String, (VoteChoice, u64)>, // Voter -> (choice, stake at the time of voting)
+}
+
+impl Proposal {
+    /// Total voting power cast for `choice`
+    pub fn power_for(&self, choice: VoteChoice) -> f64 {
+        self.votes.values()
+            .filter(|(cast, _)| *cast == choice)
+            .map(|&(_, stake)| voting_power(stake))
+            .sum()
+    }
+}
+
+/// Message a proposer signs to open a proposal
+pub fn proposal_message(description: &str, deadline: u64) -> String {
+    format!("propose:{}:{}", deadline, description)
+}
+
+/// Message a voter signs to cast `choice` on a proposal
+pub fn vote_message(proposal_id: u64, choice: VoteChoice) -> String {
+    let choice = match choice {
+        VoteChoice::Yes => "yes",
+        VoteChoice::No => "no",
+    };
+    format!("vote:{}:{}", proposal_id, choice)
+}
+
+/// Quadratic voting power: the square root of the stake behind a vote, so
+/// large holders count for less than their stake alone would give them.
+pub fn voting_power(stake: u64) -> f64 {
+    (stake as f64).sqrt()
+}
+
+/// Proposals and the votes cast on them. Heights are block heights; the
+/// caller supplies the current one and each voter's stake.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Governance {
+    pub proposals: BTreeMap<u64, Proposal>,
+    next_id: u64,
+}
+
+impl Governance {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Opens a proposal for voting until `deadline`. The proposer needs stake
+    /// of its own, like any voter. Returns the proposal's ID.
+    pub fn submit_proposal(
+        &mut self,
+        proposer: &str,
+        proposer_stake: u64,
+        description: &str,
+        deadline: u64,
+        height: u64,
+    ) -> Result<u64, GovernanceError> {
+        if proposer_stake == 0 {
+            return Err(GovernanceError::NotEligible);
+        }
+        if description.trim().is_empty() {
+            return Err(GovernanceError::EmptyDescription);
+        }
+        if deadline <= height {
+            return Err(GovernanceError::DeadlinePassed);
+        }
+
+        let id = self.next_id;
+        self.next_id += 1;
+        self.proposals.insert(id, Proposal {
+            id,
+            description: description.to_string(),
+            proposer: proposer.to_string(),
+            deadline,
+            votes: BTreeMap::new(),
+        });
+        Ok(id)
+    }
+
+    /// Records `voter`'s vote, weighted by the square root of `stake`. Each
+    /// voter votes once per proposal. Returns the voting power cast.
+    pub fn vote(
+        &mut self,
+        proposal_id: u64,
+        voter: &str,
+        stake: u64,
+        choice: VoteChoice,
+        height: u64,
+    ) -> Result<f64, GovernanceError> {
+        let proposal = self.proposals.get_mut(&proposal_id).ok_or(GovernanceError::ProposalNotFound)?;
+        if height > proposal.deadline {
+            return Err(GovernanceError::VotingClosed);
+        }
+        if stake == 0 {
+            return Err(GovernanceError::NotEligible);
+        }
+        if proposal.votes.contains_key(voter) {
+            return Err(GovernanceError::AlreadyVoted);
+        }
+
+        proposal.votes.insert(voter.to_string(), (choice, stake));
+        Ok(voting_power(stake))
+    }
+
+    /// Pending until the deadline has passed; then the proposal passes if
+    /// more voting power was cast for it than against it.
+    pub fn tally(&self, proposal_id: u64, height: u64) -> Result<Outcome, GovernanceError> {
+        let proposal = self.proposals.get(&proposal_id).ok_or(GovernanceError::ProposalNotFound)?;
+        if height <= proposal.deadline {
+            return Ok(Outcome::Pending);
+        }
+        if proposal.power_for(VoteChoice::Yes) > proposal.power_for(VoteChoice::No) {
+            Ok(Outcome::Passed)
+        } else {
+            Ok(Outcome::Rejected)
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// chain/consensus/hashing.rs
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Keccak256, Sha3_256};
+
+/// Hash function used for block and hybrid hashes. Every node on a chain
+/// must use the same one.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HashAlgo {
+    #[default]
+    Sha3_256,
+    Blake3,
+    Keccak256,
+}
+
+/// Incremental hasher over whichever `HashAlgo` was chosen
+pub enum Hasher {
+    Sha3_256(Sha3_256),
+    Blake3(Box<blake3::Hasher>),
+    Keccak256(Keccak256),
+}
+
+impl Hasher {
+    pub fn new(algo: HashAlgo) -> Self {
+        match algo {
+            HashAlgo::Sha3_256 => Hasher::Sha3_256(Sha3_256::new()),
+            HashAlgo::Blake3 => Hasher::Blake3(Box::new(blake3::Hasher::new())),
+            HashAlgo::Keccak256 => Hasher::Keccak256(Keccak256::new()),
+        }
+    }
+
+    pub fn update(&mut self, data: impl AsRef<[u8]>) {
+        match self {
+            Hasher::Sha3_256(hasher) => Digest::update(hasher, data),
+            Hasher::Blake3(hasher) => {
+                hasher.update(data.as_ref());
+            },
+            Hasher::Keccak256(hasher) => Digest::update(hasher, data),
+        }
+    }
+
+    /// Lowercase hex digest, 64 characters for every algorithm
+    pub fn finalize_hex(self) -> String {
+        match self {
+            Hasher::Sha3_256(hasher) => format!("{:x}", hasher.finalize()),
+            Hasher::Blake3(hasher) => hasher.finalize().to_hex().to_string(),
+            Hasher::Keccak256(hasher) => format!("{:x}", hasher.finalize()),
+        }
+    }
+}
+
+/// Canonical bytes fed to consensus hashes, so that every node hashes the
+/// same logical value to the same digest. Each hashed structure writes its
+/// fields in a fixed order using these encodings:
+///
+/// - `u64`: 8 bytes, big-endian
+/// - string or byte string: its length as a `u64`, then the bytes
+/// - list: its element count as a `u64`, then each element
+/// - map: its entry count as a `u64`, then each entry in ascending key
+///   order, as the key string followed by the value
+///
+/// Length prefixes keep adjacent fields from running together, so e.g.
+/// `("1", "23")` and `("12", "3")` encode differently, and sorting map keys
+/// makes the encoding independent of `HashMap` iteration order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CanonicalEncoder {
+    bytes: Vec<u8>,
+}
+
+impl CanonicalEncoder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn u64(&mut self, value: u64) -> &mut Self {
+        self.bytes.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    pub fn bytes(&mut self, value: impl AsRef<[u8]>) -> &mut Self {
+        let value = value.as_ref();
+        self.u64(value.len() as u64);
+        self.bytes.extend_from_slice(value);
+        self
+    }
+
+    pub fn str(&mut self, value: &str) -> &mut Self {
+        self.bytes(value)
+    }
+
+    /// Writes `items` as a list, encoding each with `encode`
+    pub fn list<T>(&mut self, items: impl IntoIterator<Item = T>, mut encode: impl FnMut(&mut Self, T)) -> &mut Self {
+        let items: Vec<T> = items.into_iter().collect();
+        self.u64(items.len() as u64);
+        for item in items {
+            encode(self, item);
+        }
+        self
+    }
+
+    /// Writes `entries` as a map in ascending key order, encoding each value
+    /// with `encode`. Keys are expected to be unique.
+    pub fn map<K: AsRef<str>, V>(
+        &mut self,
+        entries: impl IntoIterator<Item = (K, V)>,
+        mut encode: impl FnMut(&mut Self, V),
+    ) -> &mut Self {
+        let mut entries: Vec<(K, V)> = entries.into_iter().collect();
+        entries.sort_by(|(a, _), (b, _)| a.as_ref().cmp(b.as_ref()));
+        self.u64(entries.len() as u64);
+        for (key, value) in entries {
+            self.str(key.as_ref());
+            encode(self, value);
+        }
+        self
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Hex digest of the encoded bytes under `algo`
+    pub fn hash(&self, algo: HashAlgo) -> String {
+        let mut hasher = Hasher::new(algo);
+        hasher.update(&self.bytes);
+        hasher.finalize_hex()
+    }
+}
@@ -1,29 +1,117 @@
 // chain/consensus/mod.rs
+pub mod clock;
+pub mod emission;
+pub mod encoding;
+pub mod error;
+pub mod events;
+pub mod finality;
+pub mod governance;
+pub mod hashing;
 pub mod pos;
 pub mod poq;
+pub mod quorum;
+pub mod replay;
 pub mod verifier;
 
 use serde::{Deserialize, Serialize};
-use pos::{PoSConsensus, Block};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use clock::{Clock, SystemClock};
+use events::ConsensusEvent;
+use finality::{Attestation, FinalityGadget};
+use hashing::{CanonicalEncoder, HashAlgo};
+use pos::{PoSConsensus, Block, SlashReason};
 use poq::{PoQConsensus, QuantumProof};
+use quorum::QuorumCertificate;
+use error::ConsensusError;
+
+/// Number of blocks after which per-epoch PoQ score caps reset
+pub const EPOCH_LENGTH: u64 = 100;
+
+/// Events a slow subscriber may fall behind by before it starts missing them
+pub const EVENT_CHANNEL_CAPACITY: usize = 256;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct HybridBlock {
     pub pos_block: Block,
     pub quantum_proofs: Vec<QuantumProof>,
     pub hybrid_hash: String,
+    #[serde(default)]
+    pub state_root: String,      // PoS state root after applying this block
+    #[serde(default)]
+    pub quorum_certificate: Option<QuorumCertificate>, // Attached once the block is finalized
+}
+
+impl HybridBlock {
+    pub fn compute_hybrid_hash(&self) -> String {
+        self.compute_hybrid_hash_with(HashAlgo::default())
+    }
+
+    /// Hash over the PoS block's hash followed by the list of proof
+    /// artifacts, canonically encoded.
+    pub fn compute_hybrid_hash_with(&self, algo: HashAlgo) -> String {
+        CanonicalEncoder::new()
+            .str(&self.pos_block.hash)
+            .list(&self.quantum_proofs, |encoder, proof| {
+                encoder.str(&proof.proof_artifact);
+            })
+            .hash(algo)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encoding::to_bytes(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        encoding::from_bytes(bytes)
+    }
 }
 
 pub struct HybridConsensus {
     pub pos: PoSConsensus,
     pub poq: PoQConsensus,
+    pub finality: FinalityGadget,
+    pub events: Option<broadcast::Sender<ConsensusEvent>>, // Created on first subscription
+    pub included_proofs: HashSet<String>, // Challenge IDs of proofs already carried by a block
+    pub recent_inclusions: VecDeque<Vec<String>>, // Validators whose proofs each recent block carried, newest last
+}
+
+impl Default for HybridConsensus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl HybridConsensus {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    /// Consensus whose block timestamps and challenge deadlines both come
+    /// from `clock`
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
         HybridConsensus {
-            pos: PoSConsensus::new(),
-            poq: PoQConsensus::new(),
+            pos: PoSConsensus::with_clock(Arc::clone(&clock)),
+            poq: PoQConsensus::with_clock(clock),
+            finality: FinalityGadget::default(),
+            events: None,
+            included_proofs: HashSet::new(),
+            recent_inclusions: VecDeque::new(),
+        }
+    }
+
+    /// Subscribes to consensus events from this point on.
+    pub fn subscribe(&mut self) -> broadcast::Receiver<ConsensusEvent> {
+        self.events
+            .get_or_insert_with(|| broadcast::channel(EVENT_CHANNEL_CAPACITY).0)
+            .subscribe()
+    }
+
+    fn emit(&self, event: ConsensusEvent) {
+        if let Some(events) = &self.events {
+            // Sending only fails when nobody is listening
+            let _ = events.send(event);
         }
     }
 
@@ -31,50 +119,137 @@ impl HybridConsensus {
         self.pos.register_validator(address, initial_stake);
     }
 
-    pub fn generate_quantum_challenge(&mut self, qubit_count: usize) -> poq::PoQChallenge {
-        self.poq.generate_challenge(qubit_count)
+    pub fn stake(&mut self, address: &str, amount: u64) -> bool {
+        self.pos.stake(address, amount)
+    }
+
+    pub fn generate_quantum_challenge(&mut self, qubit_count: usize, nonce: u64) -> poq::PoQChallenge {
+        self.poq.generate_challenge(qubit_count, self.pos.current_block, nonce)
+    }
+
+    /// Submits a proof, returning the reward credited for it.
+    pub fn submit_quantum_proof(&mut self, proof: QuantumProof) -> Result<u64, ConsensusError> {
+        let validator = proof.validator_address.clone();
+        let reward = match self.poq.submit_proof(proof) {
+            Ok(reward) => reward,
+            Err(e) => {
+                tracing::warn!(%validator, error = %e, "proof rejected");
+                return Err(e);
+            },
+        };
+        tracing::info!(%validator, reward, "proof accepted");
+        self.emit(ConsensusEvent::ProofAccepted { validator, reward });
+        Ok(reward)
+    }
+
+    /// Batch form of `submit_quantum_proof`; each proof succeeds or fails on its own.
+    pub fn submit_quantum_proofs(&mut self, proofs: Vec<QuantumProof>) -> Vec<Result<u64, ConsensusError>> {
+        proofs.into_iter().map(|proof| self.submit_quantum_proof(proof)).collect()
+    }
+
+    pub fn slash(&mut self, address: &str, reason: SlashReason, amount: u64) -> bool {
+        if !self.pos.slash(address, reason, amount) {
+            return false;
+        }
+
+        let slashed = self.pos.slash_history[address].last().map(|event| event.amount).unwrap_or(0);
+        tracing::warn!(%address, ?reason, amount = slashed, "validator slashed");
+        self.emit(ConsensusEvent::ValidatorSlashed { address: address.to_string(), amount: slashed });
+        true
+    }
+
+    /// Counts a validator's attestation for the block at `height`. Returns
+    /// the block's certificate when the vote finalizes it.
+    pub fn attest(&mut self, attestation: Attestation, height: u64) -> Result<Option<QuorumCertificate>, ConsensusError> {
+        if !self.pos.validators.contains_key(&attestation.validator) {
+            return Err(ConsensusError::ValidatorNotFound);
+        }
+        if !attestation.is_valid() {
+            return Err(ConsensusError::InvalidSignature);
+        }
+
+        let certificate = self.finality.record(attestation, height, &self.pos.validators, self.pos.total_stake);
+        if certificate.is_some() {
+            tracing::info!(height, "block finalized");
+            self.emit(ConsensusEvent::BlockFinalized(height));
+        }
+        Ok(certificate)
     }
 
-    pub fn submit_quantum_proof(&mut self, proof: QuantumProof) -> Result<(), String> {
-        self.poq.submit_proof(proof)
+    /// The genesis block in hybrid form, which every stored chain starts with.
+    pub fn genesis_block(&self) -> HybridBlock {
+        let mut genesis = HybridBlock {
+            pos_block: self.pos.genesis.clone(),
+            quantum_proofs: Vec::new(),
+            hybrid_hash: String::new(),
+            state_root: String::new(),
+            quorum_certificate: None,
+        };
+        genesis.hybrid_hash = genesis.compute_hybrid_hash_with(self.pos.hash_algo);
+        genesis
     }
 
     pub fn propose_hybrid_block(&mut self, proposer: &str, transactions: Vec<String>) -> Option<HybridBlock> {
         // First propose a PoS block
         if let Some(pos_block) = self.pos.propose_block(proposer, transactions) {
-            // Get recent quantum proofs to include in the hybrid block
+            // Pick the quantum proofs to include in the hybrid block
             let recent_proofs = self.get_recent_quantum_proofs();
-            
-            // Calculate a hybrid hash that includes both PoS and PoQ elements
-            let mut hasher = sha3::Sha3_256::new();
-            hasher.update(&pos_block.hash);
-            for proof in &recent_proofs {
-                hasher.update(&proof.proof_artifact);
+            self.record_proof_inclusions(&recent_proofs);
+
+            // Calculate rewards for both PoS and PoQ contributions
+            self.pos.calculate_rewards(&pos_block);
+
+            if pos_block.index % EPOCH_LENGTH == 0 {
+                self.poq.start_new_epoch();
             }
-            let hybrid_hash = format!("{:x}", hasher.finalize());
 
-            let hybrid_block = HybridBlock {
+            let mut hybrid_block = HybridBlock {
                 pos_block,
                 quantum_proofs: recent_proofs,
-                hybrid_hash,
+                hybrid_hash: String::new(),
+                state_root: self.pos.state_root(),
+                quorum_certificate: None,
             };
 
-            // Calculate rewards for both PoS and PoQ contributions
-            self.pos.calculate_rewards(&hybrid_block.pos_block);
+            // Calculate a hybrid hash that includes both PoS and PoQ elements
+            hybrid_block.hybrid_hash = hybrid_block.compute_hybrid_hash_with(self.pos.hash_algo);
 
+            tracing::info!(
+                height = hybrid_block.pos_block.index,
+                %proposer,
+                transactions = hybrid_block.pos_block.transactions.len(),
+                "block proposed"
+            );
+            self.emit(ConsensusEvent::BlockProposed(hybrid_block.pos_block.index));
             Some(hybrid_block)
         } else {
+            tracing::debug!(%proposer, "proposer is not a validator");
             None
         }
     }
 
-    fn get_recent_quantum_proofs(&self) -> Vec<QuantumProof> {
-        // In a real implementation, this would return proofs from a recent time window
-        // For now, we'll return the last 5 proofs
-        self.poq.proofs.iter()
-            .rev()
-            .take(5)
+    /// Proofs the next block would carry: up to `MAX_BLOCK_PROOFS` of the
+    /// accepted proofs no block has carried yet, chosen by
+    /// `poq::select_block_proofs` against each validator's inclusions over
+    /// the last `PROOF_INCLUSION_WINDOW` blocks.
+    pub fn get_recent_quantum_proofs(&self) -> Vec<QuantumProof> {
+        let candidates: Vec<QuantumProof> = self.poq.proofs.read().unwrap().iter()
+            .filter(|proof| !self.included_proofs.contains(&proof.circuit_descriptor))
             .cloned()
-            .collect()
+            .collect();
+
+        let mut inclusions: HashMap<String, usize> = HashMap::new();
+        for validator in self.recent_inclusions.iter().flatten() {
+            *inclusions.entry(validator.clone()).or_insert(0) += 1;
+        }
+        poq::select_block_proofs(&candidates, &inclusions, poq::MAX_BLOCK_PROOFS)
+    }
+
+    fn record_proof_inclusions(&mut self, proofs: &[QuantumProof]) {
+        self.included_proofs.extend(proofs.iter().map(|proof| proof.circuit_descriptor.clone()));
+        self.recent_inclusions.push_back(proofs.iter().map(|proof| proof.validator_address.clone()).collect());
+        while self.recent_inclusions.len() > poq::PROOF_INCLUSION_WINDOW {
+            self.recent_inclusions.pop_front();
+        }
     }
 }
\ No newline at end of file
@@ -1,6 +1,55 @@
 // chain/consensus/poq.rs
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use sha3::{Digest, Sha3_256};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::{Arc, RwLock};
+use super::clock::{Clock, SystemClock};
+use super::encoding;
+use super::error::ConsensusError;
+
+/// Default number of seconds a proof may arrive after its challenge deadline
+pub const DEFAULT_GRACE_SECS: u64 = 5;
+
+/// Share of the challenge reward paid for a proof that lands in the grace window
+pub const LATE_REWARD_PERCENT: u64 = 50;
+
+/// Seconds a challenge stays open before its deadline, unless the issuer
+/// asks for another duration
+pub const CHALLENGE_DURATION_SECS: u64 = 300;
+
+/// Default bounds on requested challenge durations, so no challenge closes
+/// before it can be solved or stays open forever
+pub const MIN_CHALLENGE_DURATION_SECS: u64 = 30;
+pub const MAX_CHALLENGE_DURATION_SECS: u64 = 3_600;
+
+/// Expected solve time for a challenge of difficulty multiplier 1.0
+pub const TARGET_SOLVE_SECS: u64 = 60;
+
+/// Number of recent challenge outcomes reward retargeting looks at
+pub const RETARGET_WINDOW: usize = 16;
+
+/// Bounds on how far retargeting can move rewards from their base value
+pub const MIN_RETARGET_FACTOR: f64 = 0.5;
+pub const MAX_RETARGET_FACTOR: f64 = 2.0;
+
+/// How many of the newest proofs the `/proofs` listing shows
+pub const RECENT_PROOF_COUNT: usize = 5;
+
+/// Most proofs a hybrid block carries
+pub const MAX_BLOCK_PROOFS: usize = 5;
+
+/// Blocks whose proofs count as a validator's recent inclusions when
+/// choosing proofs for the next block
+pub const PROOF_INCLUSION_WINDOW: usize = 16;
+
+/// Highest qubit count in the low and medium difficulty tiers; larger
+/// challenges are high tier
+pub const LOW_TIER_MAX_QUBITS: usize = 8;
+pub const MEDIUM_TIER_MAX_QUBITS: usize = 16;
+
+// Per-challenge solve-time ratio bounds; an unsolved challenge counts as the maximum
+const MIN_SOLVE_RATIO: f64 = 0.25;
+const MAX_SOLVE_RATIO: f64 = 4.0;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct QuantumProof {
@@ -12,102 +61,460 @@ pub struct QuantumProof {
     pub timestamp: u64,
 }
 
+/// Challenges are ranked by qubit count into tiers with separate
+/// leaderboards, so validators on modest hardware compete among themselves.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DifficultyTier {
+    Low,    // Up to LOW_TIER_MAX_QUBITS qubits
+    Medium, // Up to MEDIUM_TIER_MAX_QUBITS qubits
+    High,
+}
+
+impl DifficultyTier {
+    pub const ALL: [DifficultyTier; 3] = [DifficultyTier::Low, DifficultyTier::Medium, DifficultyTier::High];
+
+    pub fn for_qubits(qubit_count: usize) -> Self {
+        if qubit_count <= LOW_TIER_MAX_QUBITS {
+            DifficultyTier::Low
+        } else if qubit_count <= MEDIUM_TIER_MAX_QUBITS {
+            DifficultyTier::Medium
+        } else {
+            DifficultyTier::High
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DifficultyTier::Low => "low",
+            DifficultyTier::Medium => "medium",
+            DifficultyTier::High => "high",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|tier| tier.name() == name)
+    }
+}
+
+/// Scores earned in each difficulty tier, by validator
+pub type TierScores = HashMap<DifficultyTier, HashMap<String, u64>>;
+
+/// Validators ranked by score, highest first and then by address, within
+/// `tier` or, without one, across all tiers.
+pub fn leaderboard(tier_scores: &RwLock<TierScores>, tier: Option<DifficultyTier>) -> Vec<(String, u64)> {
+    let tier_scores = tier_scores.read().unwrap();
+    let mut totals: HashMap<&str, u64> = HashMap::new();
+    let included = tier_scores.iter().filter(|(scored, _)| match tier {
+        Some(tier) => **scored == tier,
+        None => true,
+    });
+    for (_, scores) in included {
+        for (address, score) in scores {
+            *totals.entry(address).or_insert(0) += score;
+        }
+    }
+
+    let mut ranked: Vec<(String, u64)> = totals.into_iter()
+        .map(|(address, score)| (address.to_string(), score))
+        .collect();
+    ranked.sort_by(|(a, a_score), (b, b_score)| b_score.cmp(a_score).then_with(|| a.cmp(b)));
+    ranked
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PoQChallenge {
     pub challenge_id: String,
     pub qubit_count: usize,          // Difficulty scales with qubit count
     pub deadline: u64,               // Time limit for submission
+    #[serde(default = "default_duration_secs")]
+    pub duration_secs: u64,          // Seconds between issue and deadline, after clamping
     pub reward: u64,                 // Reward for solving the challenge
+    #[serde(default)]
+    pub height: u64,                 // Block height the challenge was issued at
+    #[serde(default)]
+    pub nonce: u64,                  // Disambiguates challenges issued at the same height
+}
+
+fn default_duration_secs() -> u64 {
+    CHALLENGE_DURATION_SECS
+}
+
+/// Challenge IDs are a hash of the challenge's parameters, so anyone holding
+/// a challenge can recompute and check its ID.
+pub fn challenge_id(qubit_count: usize, height: u64, deadline: u64, nonce: u64) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update((qubit_count as u64).to_le_bytes());
+    hasher.update(height.to_le_bytes());
+    hasher.update(deadline.to_le_bytes());
+    hasher.update(nonce.to_le_bytes());
+    format!("challenge_{}", hex::encode(hasher.finalize()))
+}
+
+/// The newest accepted proofs, newest first. Takes the shared proof list so
+/// callers outside the consensus lock can use it too.
+pub fn recent_proofs(proofs: &RwLock<Vec<QuantumProof>>) -> Vec<QuantumProof> {
+    proofs.read().unwrap().iter()
+        .rev()
+        .take(RECENT_PROOF_COUNT)
+        .cloned()
+        .collect()
+}
+
+/// Chooses up to `cap` of `candidates` for a block, maximising their total
+/// qubit count. Among proofs of equal qubit count, those from validators
+/// with fewer `recent_inclusions` win, counting the proofs already chosen,
+/// then the lower validator address and challenge ID, so every node picks
+/// the same proofs. They come back ordered by qubit count, highest first,
+/// then by validator address and challenge ID.
+pub fn select_block_proofs(
+    candidates: &[QuantumProof],
+    recent_inclusions: &HashMap<String, usize>,
+    cap: usize,
+) -> Vec<QuantumProof> {
+    let mut inclusions = recent_inclusions.clone();
+    let mut remaining: Vec<&QuantumProof> = candidates.iter().collect();
+    let mut selected: Vec<QuantumProof> = Vec::new();
+    while selected.len() < cap {
+        let count = |proof: &QuantumProof| inclusions.get(&proof.validator_address).copied().unwrap_or(0);
+        let best = (0..remaining.len()).min_by(|&i, &j| {
+            let (a, b) = (remaining[i], remaining[j]);
+            b.qubit_count.cmp(&a.qubit_count)
+                .then_with(|| count(a).cmp(&count(b)))
+                .then_with(|| a.validator_address.cmp(&b.validator_address))
+                .then_with(|| a.circuit_descriptor.cmp(&b.circuit_descriptor))
+        });
+        let proof = match best {
+            Some(i) => remaining.swap_remove(i),
+            None => break,
+        };
+        *inclusions.entry(proof.validator_address.clone()).or_insert(0) += 1;
+        selected.push(proof.clone());
+    }
+
+    selected.sort_by(|a, b| {
+        b.qubit_count.cmp(&a.qubit_count)
+            .then_with(|| a.validator_address.cmp(&b.validator_address))
+            .then_with(|| a.circuit_descriptor.cmp(&b.circuit_descriptor))
+    });
+    selected
+}
+
+impl QuantumProof {
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encoding::to_bytes(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        encoding::from_bytes(bytes)
+    }
+
+    pub fn to_cbor(&self) -> Vec<u8> {
+        encoding::to_cbor(self)
+    }
+
+    pub fn from_cbor(bytes: &[u8]) -> Result<Self, String> {
+        encoding::from_cbor(bytes)
+    }
+}
+
+impl PoQChallenge {
+    pub fn has_valid_id(&self) -> bool {
+        self.challenge_id == challenge_id(self.qubit_count, self.height, self.deadline, self.nonce)
+    }
+}
+
+/// Superlinear reward curve for challenges above a qubit threshold.
+///
+/// Above `qubit_threshold` the linear reward is multiplied by
+/// `(qubit_count / qubit_threshold) ^ exponent`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RewardBoost {
+    pub qubit_threshold: usize,
+    pub exponent: f64,
+}
+
+impl Default for RewardBoost {
+    fn default() -> Self {
+        RewardBoost {
+            qubit_threshold: 16,
+            exponent: 1.0,
+        }
+    }
+}
+
+/// Checks that a proof really answers its challenge. Integrators can plug in
+/// a verifier that replays the circuit on a simulator.
+pub trait QuantumVerifier: Send + Sync {
+    fn verify(&self, proof: &QuantumProof, challenge: &PoQChallenge) -> bool;
+}
+
+/// Default verifier, which only checks the proof's structure
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StructuralVerifier;
+
+impl QuantumVerifier for StructuralVerifier {
+    fn verify(&self, proof: &QuantumProof, _challenge: &PoQChallenge) -> bool {
+        // In a real implementation, we would simulate the quantum circuit
+        // and verify that the measurement results are consistent with
+        // the expected quantum computation
+
+        // For now, we'll just check that there are measurements and that
+        // the proof artifact is not empty
+        !proof.measurement_results.is_empty() && !proof.proof_artifact.is_empty()
+    }
 }
 
 pub struct PoQConsensus {
     pub challenges: HashMap<String, PoQChallenge>,
-    pub proofs: Vec<QuantumProof>,
-    pub validator_scores: HashMap<String, u64>, // Track quantum contribution
+    // Accepted proofs and the scores they earned sit behind their own locks,
+    // so readers holding a handle never wait on whoever owns the consensus
+    pub proofs: Arc<RwLock<Vec<QuantumProof>>>,
+    pub validator_scores: Arc<RwLock<HashMap<String, u64>>>, // Track quantum contribution
+    pub tier_scores: Arc<RwLock<TierScores>>,                // The same scores split by difficulty tier
+    pub reward_boost: RewardBoost,
+    pub epoch_score_cap: u64,                   // Max score a validator can earn per epoch
+    pub epoch_scores: HashMap<String, u64>,     // Score earned in the current epoch
+    pub grace_secs: u64,                        // Late proofs accepted this long past the deadline
+    pub challenge_duration_secs: u64,           // Used when no duration is requested
+    pub min_challenge_secs: u64,                // Requested durations are clamped to
+    pub max_challenge_secs: u64,                // `min_challenge_secs..=max_challenge_secs`
+    pub solve_ratios: VecDeque<f64>,            // Recent solve times relative to target, newest last
+    pub verifier: Box<dyn QuantumVerifier>,
+    // (validator, circuit descriptor, artifact) of every accepted proof
+    submitted: HashSet<(String, String, String)>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Default for PoQConsensus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PoQConsensus {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
         PoQConsensus {
             challenges: HashMap::new(),
-            proofs: Vec::new(),
-            validator_scores: HashMap::new(),
+            proofs: Arc::new(RwLock::new(Vec::new())),
+            validator_scores: Arc::new(RwLock::new(HashMap::new())),
+            tier_scores: Arc::new(RwLock::new(HashMap::new())),
+            reward_boost: RewardBoost::default(),
+            epoch_score_cap: 10_000,
+            epoch_scores: HashMap::new(),
+            grace_secs: DEFAULT_GRACE_SECS,
+            challenge_duration_secs: CHALLENGE_DURATION_SECS,
+            min_challenge_secs: MIN_CHALLENGE_DURATION_SECS,
+            max_challenge_secs: MAX_CHALLENGE_DURATION_SECS,
+            solve_ratios: VecDeque::new(),
+            verifier: Box::new(StructuralVerifier),
+            submitted: HashSet::new(),
+            clock,
+        }
+    }
+
+    pub fn boosted_reward(&self, qubit_count: usize) -> u64 {
+        let linear = (qubit_count * 10) as u64; // Higher qubit count = higher reward
+        let threshold = self.reward_boost.qubit_threshold;
+
+        if threshold == 0 || qubit_count <= threshold {
+            return linear;
+        }
+
+        let multiplier = (qubit_count as f64 / threshold as f64).powf(self.reward_boost.exponent);
+        (linear as f64 * multiplier) as u64
+    }
+
+    /// Multiplier applied to base rewards: the average recent solve time
+    /// relative to target, so rewards rise while challenges go unsolved and
+    /// fall while they are solved quickly.
+    pub fn retarget_factor(&self) -> f64 {
+        if self.solve_ratios.is_empty() {
+            return 1.0;
+        }
+        let average = self.solve_ratios.iter().sum::<f64>() / self.solve_ratios.len() as f64;
+        average.clamp(MIN_RETARGET_FACTOR, MAX_RETARGET_FACTOR)
+    }
+
+    /// Reward a new challenge of `qubit_count` qubits would carry right now
+    pub fn current_reward(&self, qubit_count: usize) -> u64 {
+        (self.boosted_reward(qubit_count) as f64 * self.retarget_factor()).round() as u64
+    }
+
+    fn record_solve_ratio(&mut self, ratio: f64) {
+        if self.solve_ratios.len() == RETARGET_WINDOW {
+            self.solve_ratios.pop_front();
+        }
+        self.solve_ratios.push_back(ratio.clamp(MIN_SOLVE_RATIO, MAX_SOLVE_RATIO));
+    }
+
+    // Solve time relative to the target for the challenge's difficulty, so
+    // larger circuits are allowed proportionally longer
+    fn solve_ratio(&self, challenge: &PoQChallenge, solved_at: u64) -> f64 {
+        let issued_at = challenge.deadline.saturating_sub(challenge.duration_secs);
+        let target = TARGET_SOLVE_SECS as f64 * self.get_difficulty_multiplier(challenge.qubit_count);
+        if target <= 0.0 {
+            return 1.0;
         }
+        solved_at.saturating_sub(issued_at) as f64 / target
+    }
+
+    pub fn start_new_epoch(&mut self) {
+        self.epoch_scores.clear();
     }
 
-    pub fn generate_challenge(&mut self, qubit_count: usize) -> PoQChallenge {
-        let challenge_id = format!("challenge_{}", rand::random::<u64>());
-        let deadline = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs() + 300; // 5 minutes from now
+    pub fn generate_challenge(&mut self, qubit_count: usize, height: u64, nonce: u64) -> PoQChallenge {
+        self.generate_challenge_with_deadline(qubit_count, height, nonce, self.challenge_duration_secs)
+    }
+
+    /// Issues a challenge that stays open for `deadline_secs`, clamped to
+    /// the configured bounds. The returned challenge carries the duration
+    /// actually used.
+    pub fn generate_challenge_with_deadline(
+        &mut self,
+        qubit_count: usize,
+        height: u64,
+        nonce: u64,
+        deadline_secs: u64,
+    ) -> PoQChallenge {
+        self.prune_expired();
+
+        let duration_secs = deadline_secs.clamp(self.min_challenge_secs, self.max_challenge_secs.max(self.min_challenge_secs));
+        let deadline = self.clock.now_secs() + duration_secs;
+        let challenge_id = challenge_id(qubit_count, height, deadline, nonce);
         
-        let reward = (qubit_count * 10) as u64; // Higher qubit count = higher reward
+        let reward = self.current_reward(qubit_count);
         
         let challenge = PoQChallenge {
             challenge_id: challenge_id.clone(),
             qubit_count,
             deadline,
+            duration_secs,
             reward,
+            height,
+            nonce,
         };
         
         self.challenges.insert(challenge_id, challenge.clone());
         challenge
     }
 
-    pub fn submit_proof(&mut self, proof: QuantumProof) -> Result<(), String> {
-        // Verify the quantum proof
-        if !self.verify_proof(&proof) {
-            return Err("Invalid quantum proof".to_string());
+    /// Accepts a proof for an open challenge, returning the score credited.
+    pub fn submit_proof(&mut self, proof: QuantumProof) -> Result<u64, ConsensusError> {
+        // A proof is only ever credited once, so resubmitting it earns nothing
+        let key = (
+            proof.validator_address.clone(),
+            proof.circuit_descriptor.clone(),
+            proof.proof_artifact.clone(),
+        );
+        if self.submitted.contains(&key) {
+            return Err(ConsensusError::DuplicateProof);
         }
 
         // Check if challenge exists and is not expired
         let challenge = self.challenges.get(&proof.circuit_descriptor)
-            .ok_or("Challenge not found")?;
-        
-        let current_time = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-        
-        if current_time > challenge.deadline {
-            return Err("Challenge deadline exceeded".to_string());
+            .ok_or(ConsensusError::ChallengeNotFound)?;
+
+        // Verify the quantum proof against the challenge it answers
+        if !self.verifier.verify(&proof, challenge) {
+            return Err(ConsensusError::InvalidProof);
         }
 
-        // Add proof to the list
-        self.proofs.push(proof.clone());
+        // The proof must answer this challenge's circuit, with one measured
+        // bit per qubit (measurement results are packed eight bits per byte)
+        if proof.qubit_count != challenge.qubit_count {
+            return Err(ConsensusError::QubitCountMismatch {
+                expected: challenge.qubit_count,
+                actual: proof.qubit_count,
+            });
+        }
+        let measured_bits = proof.measurement_results.len() * 8;
+        if measured_bits < challenge.qubit_count {
+            return Err(ConsensusError::InsufficientMeasurements {
+                required_bits: challenge.qubit_count,
+                actual_bits: measured_bits,
+            });
+        }
         
-        // Update validator score
-        *self.validator_scores.entry(proof.validator_address.clone()).or_insert(0) += challenge.reward;
+        let current_time = self.clock.now_secs();
+        let expired = current_time > challenge.deadline.saturating_add(self.grace_secs);
+
+        // Proofs that only just missed the deadline still count, at a reduced reward
+        let reward = if current_time > challenge.deadline {
+            challenge.reward * LATE_REWARD_PERCENT / 100
+        } else {
+            challenge.reward
+        };
+        let solve_ratio = self.solve_ratio(challenge, current_time);
+
+        // Pruning after the lookup lets an expired challenge report
+        // DeadlineExceeded rather than ChallengeNotFound
+        self.prune_expired();
+        if expired {
+            return Err(ConsensusError::DeadlineExceeded);
+        }
+
+        // Update validator score, bounded by what is left of the epoch cap
+        let epoch_score = self.epoch_scores.entry(proof.validator_address.clone()).or_insert(0);
+        let credited = reward.min(self.epoch_score_cap.saturating_sub(*epoch_score));
+        *epoch_score += credited;
+
+        // Each write lock is held just long enough for its one update
+        *self.validator_scores.write().unwrap().entry(proof.validator_address.clone()).or_insert(0) += credited;
+        *self.tier_scores.write().unwrap()
+            .entry(DifficultyTier::for_qubits(proof.qubit_count))
+            .or_default()
+            .entry(proof.validator_address.clone())
+            .or_insert(0) += credited;
+        self.proofs.write().unwrap().push(proof.clone());
+        self.submitted.insert(key);
         
         // Remove the challenge since it's been solved
         self.challenges.remove(&proof.circuit_descriptor);
+        self.record_solve_ratio(solve_ratio);
         
-        Ok(())
+        Ok(credited)
     }
 
-    pub fn verify_proof(&self, proof: &QuantumProof) -> bool {
-        // In a real implementation, this would verify the quantum proof
-        // For now, we'll implement a basic verification:
-        // 1. Check that the proof has valid structure
-        // 2. Verify that the proof artifact is consistent with the measurement results
-        // 3. Validate that the qubit count matches the expected challenge
-        
-        // Basic checks
-        if proof.measurement_results.is_empty() {
-            return false;
+    /// Submits each proof independently, so one bad proof doesn't stop the
+    /// rest. Results are in the same order as `proofs`.
+    pub fn submit_proofs(&mut self, proofs: Vec<QuantumProof>) -> Vec<Result<(), ConsensusError>> {
+        proofs.into_iter().map(|proof| self.submit_proof(proof).map(|_| ())).collect()
+    }
+
+    /// Drops every challenge whose deadline and grace period have passed,
+    /// returning how many were removed.
+    pub fn prune_expired(&mut self) -> usize {
+        let current_time = self.clock.now_secs();
+        let grace_secs = self.grace_secs;
+
+        let before = self.challenges.len();
+        self.challenges.retain(|_, challenge| challenge.deadline.saturating_add(grace_secs) >= current_time);
+        let expired = before - self.challenges.len();
+
+        // Challenges nobody solved push rewards up
+        for _ in 0..expired {
+            self.record_solve_ratio(MAX_SOLVE_RATIO);
         }
-        
-        // In a real implementation, we would simulate the quantum circuit
-        // and verify that the measurement results are consistent with 
-        // the expected quantum computation
-        
-        // For now, we'll just check that the proof artifact is not empty
-        !proof.proof_artifact.is_empty()
+        expired
     }
 
     pub fn get_validator_score(&self, address: &str) -> u64 {
-        *self.validator_scores.get(address).unwrap_or(&0)
+        *self.validator_scores.read().unwrap().get(address).unwrap_or(&0)
+    }
+
+    /// Score earned from proofs in one difficulty tier; across all tiers
+    /// these add up to `get_validator_score`.
+    pub fn get_validator_score_by_tier(&self, address: &str, tier: DifficultyTier) -> u64 {
+        self.tier_scores.read().unwrap()
+            .get(&tier)
+            .and_then(|scores| scores.get(address))
+            .copied()
+            .unwrap_or(0)
     }
 
     pub fn get_difficulty_multiplier(&self, qubit_count: usize) -> f64 {
@@ -1,7 +1,52 @@
 // chain/consensus/pos.rs
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
 use sha3::{Sha3_256, Digest};
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+use super::clock::{Clock, SystemClock};
+use super::emission::EmissionSchedule;
+use super::encoding;
+use super::hashing::{CanonicalEncoder, HashAlgo};
+use crate::chain::merkle;
+use crate::chain::transaction::Transaction;
+
+/// Blocks a validator registered after genesis must wait before it can be
+/// selected as proposer, so registration can't be timed to game selection
+pub const DEFAULT_ACTIVATION_DELAY: u64 = 10;
+
+/// Weight of the latest participation sample in the uptime moving average
+pub const UPTIME_SAMPLE_WEIGHT: f64 = 0.05;
+
+/// Consecutive missed proposals after which a validator is jailed
+pub const DEFAULT_JAIL_THRESHOLD: u64 = 5;
+
+/// Blocks a jailed validator must wait before it can ask to be unjailed
+pub const DEFAULT_MIN_JAIL_BLOCKS: u64 = 100;
+
+/// Blocks stake stays locked after leaving before it can be withdrawn
+pub const DEFAULT_UNBONDING_PERIOD: u64 = 100;
+
+/// Seconds between blocks the network aims for
+pub const DEFAULT_TARGET_BLOCK_TIME: u64 = 10;
+
+const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+/// Timestamp of every genesis block (2025-01-01T00:00:00Z)
+pub const GENESIS_TIMESTAMP: u64 = 1_735_689_600;
+
+/// Prefix of the genesis entry committing to the initial balance allocations
+pub const GENESIS_ALLOCATIONS_PREFIX: &str = "allocations:";
+
+/// SHA3-256 over the initial balances, canonically encoded as a map from
+/// address to amount, so it does not depend on the order they were listed in.
+pub fn allocations_hash<'a>(allocations: impl IntoIterator<Item = (&'a String, &'a u64)>) -> String {
+    CanonicalEncoder::new()
+        .map(allocations, |encoder, amount| {
+            encoder.u64(*amount);
+        })
+        .hash(HashAlgo::Sha3_256)
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Validator {
@@ -9,6 +54,39 @@ pub struct Validator {
     pub stake: u64,
     pub last_block_proposed: u64,
     pub uptime: f64, // Percentage of blocks validated
+    #[serde(default)]
+    pub activation_block: u64, // First block at which the validator can be selected
+    #[serde(default)]
+    pub rewards: u64,          // Part of `stake` earned as rewards and not yet withdrawn
+    #[serde(default)]
+    pub commission: f64,       // Share of its delegators' rewards the validator keeps, 0.0 to 1.0
+    #[serde(default)]
+    pub missed_in_a_row: u64,  // Proposals missed since it last proposed
+    #[serde(default)]
+    pub jailed_at: Option<u64>, // Block at which it was jailed, while excluded from selection
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum SlashReason {
+    DoubleSign,
+    Downtime,
+    InvalidProof,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SlashEvent {
+    pub block: u64,          // Block height at which the slash was applied
+    pub reason: SlashReason,
+    pub amount: u64,         // Stake actually removed from the validator
+}
+
+/// Stake on its way out of the validator set, released at `release_block`
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnbondingEntry {
+    pub address: String,
+    pub amount: u64,
+    pub release_block: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,26 +101,140 @@ pub struct Block {
     pub timestamp: u64,
     pub prev_hash: String,
     pub transactions: Vec<String>, // Simplified - in real implementation these would be transaction structs
+    #[serde(default)]
+    pub tx_root: String, // Merkle root over `transactions`
     pub proposer: String,
     pub hash: String,
 }
 
+impl Block {
+    /// Deterministic index-0 block committing to the initial validator set,
+    /// one `address:stake` entry per validator in address order. Nodes
+    /// starting from the same validators derive the same genesis hash.
+    pub fn genesis(validators: &[Validator]) -> Block {
+        Self::genesis_with_allocations(validators, None)
+    }
+
+    /// Genesis block that also commits to the initial balance allocations
+    /// through an `allocations:<hash>` entry after the validators, where the
+    /// hash is `allocations_hash` of the allocated balances.
+    pub fn genesis_with_allocations(validators: &[Validator], allocations_hash: Option<&str>) -> Block {
+        let mut entries: Vec<String> = validators.iter()
+            .map(|validator| format!("{}:{}", validator.address, validator.stake))
+            .collect();
+        entries.sort();
+        if let Some(hash) = allocations_hash {
+            entries.push(format!("{}{}", GENESIS_ALLOCATIONS_PREFIX, hash));
+        }
+
+        let mut block = Block {
+            index: 0,
+            timestamp: GENESIS_TIMESTAMP,
+            prev_hash: "0".to_string(),
+            tx_root: merkle::merkle_root_of_entries(&entries),
+            transactions: entries,
+            proposer: String::new(),
+            hash: String::new(),
+        };
+        block.hash = block.compute_hash();
+        block
+    }
+
+    pub fn compute_hash(&self) -> String {
+        self.compute_hash_with(HashAlgo::default())
+    }
+
+    /// Hash over the header fields in the order `index`, `timestamp`,
+    /// `prev_hash`, `tx_root`, `proposer`, canonically encoded. The
+    /// transactions are covered through `tx_root`.
+    pub fn compute_hash_with(&self, algo: HashAlgo) -> String {
+        CanonicalEncoder::new()
+            .u64(self.index)
+            .u64(self.timestamp)
+            .str(&self.prev_hash)
+            .str(&self.tx_root)
+            .str(&self.proposer)
+            .hash(algo)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        encoding::to_bytes(self)
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        encoding::from_bytes(bytes)
+    }
+}
+
 pub struct PoSConsensus {
     pub validators: HashMap<String, Validator>,
     pub total_stake: u64,
     pub current_block: u64,
+    pub last_block_hash: String,
+    pub activation_delay: u64,
+    pub slash_history: HashMap<String, Vec<SlashEvent>>,
+    pub unbonding_period: u64,
+    pub unbonding: Vec<UnbondingEntry>,
+    pub exited: HashSet<String>,
+    pub emission: EmissionSchedule,
+    pub view: u64, // Leader timeouts seen at the current height
+    pub delegations: HashMap<String, Vec<(String, u64)>>, // Validator -> (delegator, amount)
+    pub cooldown_blocks: u64, // Blocks a proposer sits out after proposing
+    pub target_block_time: u64, // Seconds per block, used for yield estimates
+    pub hash_algo: HashAlgo, // Used for block hashes
+    pub genesis_allocations_hash: Option<String>, // Committed to by the genesis block when balances are pre-funded
+    pub jail_threshold: u64, // Consecutive misses that jail a validator; 0 never jails
+    pub min_jail_blocks: u64, // Blocks a validator stays jailed before it may unjail
+    pub genesis: Block, // Block 0, rebuilt as validators register before the first block
+    clock: Arc<dyn Clock>, // Source of block timestamps
+}
+
+impl Default for PoSConsensus {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl PoSConsensus {
     pub fn new() -> Self {
+        Self::with_clock(Arc::new(SystemClock))
+    }
+
+    pub fn with_clock(clock: Arc<dyn Clock>) -> Self {
+        let genesis = Block::genesis(&[]);
         PoSConsensus {
             validators: HashMap::new(),
             total_stake: 0,
             current_block: 0,
+            last_block_hash: genesis.hash.clone(),
+            activation_delay: DEFAULT_ACTIVATION_DELAY,
+            slash_history: HashMap::new(),
+            unbonding_period: DEFAULT_UNBONDING_PERIOD,
+            unbonding: Vec::new(),
+            exited: HashSet::new(),
+            emission: EmissionSchedule::default(),
+            view: 0,
+            delegations: HashMap::new(),
+            cooldown_blocks: 0,
+            target_block_time: DEFAULT_TARGET_BLOCK_TIME,
+            hash_algo: HashAlgo::default(),
+            genesis_allocations_hash: None,
+            jail_threshold: DEFAULT_JAIL_THRESHOLD,
+            min_jail_blocks: DEFAULT_MIN_JAIL_BLOCKS,
+            genesis,
+            clock,
         }
     }
 
     pub fn register_validator(&mut self, address: String, initial_stake: u64) {
+        // Genesis validators are active immediately
+        let activation_block = if self.current_block == 0 {
+            0
+        } else {
+            self.current_block + self.activation_delay
+        };
+        tracing::info!(%address, stake = initial_stake, activation_block, "validator registered");
+
         self.validators.insert(
             address.clone(),
             Validator {
@@ -50,9 +242,51 @@ impl PoSConsensus {
                 stake: initial_stake,
                 last_block_proposed: 0,
                 uptime: 100.0,
+                activation_block,
+                rewards: 0,
+                commission: 0.0,
+                missed_in_a_row: 0,
+                jailed_at: None,
             }
         );
         self.total_stake += initial_stake;
+
+        if self.current_block == 0 {
+            self.seed_genesis();
+        }
+    }
+
+    // Rebuilds the genesis block from the registered validators, so the
+    // first proposed block links to it
+    fn seed_genesis(&mut self) {
+        let validators: Vec<Validator> = self.validators.values().cloned().collect();
+        let mut genesis = Block::genesis_with_allocations(&validators, self.genesis_allocations_hash.as_deref());
+        genesis.hash = genesis.compute_hash_with(self.hash_algo);
+
+        self.last_block_hash = genesis.hash.clone();
+        self.genesis = genesis;
+    }
+
+    /// Commits the genesis block to the balances pre-funded at genesis.
+    /// Only meaningful before the first block.
+    pub fn set_genesis_allocations_hash(&mut self, hash: Option<String>) {
+        self.genesis_allocations_hash = hash;
+        if self.current_block == 0 {
+            self.seed_genesis();
+        }
+    }
+
+    /// Takes the locally configured parameters from `local`, leaving the
+    /// chain state alone. Synced chains and snapshots only carry state, so
+    /// a node rebuilding its consensus from one keeps its own settings.
+    pub fn copy_parameters_from(&mut self, local: &PoSConsensus) {
+        self.hash_algo = local.hash_algo;
+        self.target_block_time = local.target_block_time;
+        self.cooldown_blocks = local.cooldown_blocks;
+        self.activation_delay = local.activation_delay;
+        self.unbonding_period = local.unbonding_period;
+        self.jail_threshold = local.jail_threshold;
+        self.min_jail_blocks = local.min_jail_blocks;
     }
 
     pub fn stake(&mut self, address: &str, amount: u64) -> bool {
@@ -65,11 +299,27 @@ impl PoSConsensus {
         }
     }
 
+    /// Takes `amount` out of the validator's voting power at once; the stake
+    /// itself unbonds and only becomes withdrawable `unbonding_period` blocks
+    /// later, so unstaking can't be timed around proposer selection.
     pub fn unstake(&mut self, address: &str, amount: u64) -> bool {
         if let Some(validator) = self.validators.get_mut(address) {
             if validator.stake >= amount {
                 validator.stake -= amount;
+                validator.rewards = validator.rewards.min(validator.stake);
                 self.total_stake -= amount;
+                if amount > 0 {
+                    self.unbonding.push(UnbondingEntry {
+                        address: address.to_string(),
+                        amount,
+                        release_block: self.current_block + self.unbonding_period,
+                    });
+                }
+
+                // A validator with nothing left staked leaves the set
+                if validator.stake == 0 {
+                    self.deregister_validator(address);
+                }
                 true
             } else {
                 false
@@ -79,31 +329,321 @@ impl PoSConsensus {
         }
     }
 
+    /// Sets the share of its delegators' rewards a validator keeps.
+    pub fn set_commission(&mut self, address: &str, commission: f64) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&commission) {
+            return Err(format!("Commission {} is outside the range 0.0 to 1.0", commission));
+        }
+        let validator = self.validators.get_mut(address)
+            .ok_or_else(|| "Validator not found".to_string())?;
+        validator.commission = commission;
+        Ok(())
+    }
+
+    /// Backs `validator` with `amount` of the delegator's stake. Fails if the
+    /// validator is unknown.
+    pub fn delegate(&mut self, delegator: String, validator: &str, amount: u64) -> bool {
+        if amount == 0 || !self.validators.contains_key(validator) {
+            return false;
+        }
+
+        let delegations = self.delegations.entry(validator.to_string()).or_default();
+        match delegations.iter_mut().find(|(address, _)| *address == delegator) {
+            Some((_, delegated)) => *delegated += amount,
+            None => delegations.push((delegator, amount)),
+        }
+        self.total_stake += amount;
+        true
+    }
+
+    /// Withdraws part of a delegation, which then unbonds like any other
+    /// stake leaving the validator set.
+    pub fn undelegate(&mut self, delegator: &str, validator: &str, amount: u64) -> bool {
+        let delegations = match self.delegations.get_mut(validator) {
+            Some(delegations) => delegations,
+            None => return false,
+        };
+        let position = match delegations.iter().position(|(address, delegated)| address == delegator && *delegated >= amount) {
+            Some(position) => position,
+            None => return false,
+        };
+
+        delegations[position].1 -= amount;
+        if delegations[position].1 == 0 {
+            delegations.remove(position);
+        }
+        if delegations.is_empty() {
+            self.delegations.remove(validator);
+        }
+
+        self.total_stake -= amount;
+        self.unbonding.push(UnbondingEntry {
+            address: delegator.to_string(),
+            amount,
+            release_block: self.current_block + self.unbonding_period,
+        });
+        true
+    }
+
+    /// Stake `address` takes part in governance with: its own stake if it
+    /// is a validator, plus everything it has delegated to validators.
+    pub fn governance_stake(&self, address: &str) -> u64 {
+        let own = self.validators.get(address).map(|validator| validator.stake).unwrap_or(0);
+        let delegated: u64 = self.delegations.values()
+            .flatten()
+            .filter(|(delegator, _)| delegator == address)
+            .map(|(_, amount)| amount)
+            .sum();
+        own + delegated
+    }
+
+    pub fn delegated_to(&self, validator: &str) -> u64 {
+        self.delegations.get(validator)
+            .map(|delegations| delegations.iter().map(|(_, amount)| amount).sum())
+            .unwrap_or(0)
+    }
+
+    /// Own stake plus everything delegated to the validator, which is what
+    /// proposer selection weighs.
+    pub fn effective_stake(&self, validator: &Validator) -> u64 {
+        validator.stake + self.delegated_to(&validator.address)
+    }
+
+    pub fn slash(&mut self, address: &str, reason: SlashReason, amount: u64) -> bool {
+        if let Some(validator) = self.validators.get_mut(address) {
+            // Never slash more than the validator currently has at stake
+            let slashed = amount.min(validator.stake);
+            validator.stake -= slashed;
+            validator.rewards = validator.rewards.min(validator.stake);
+            self.total_stake -= slashed;
+
+            self.slash_history
+                .entry(address.to_string())
+                .or_default()
+                .push(SlashEvent {
+                    block: self.current_block,
+                    reason,
+                    amount: slashed,
+                });
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn get_slash_history(&self, address: &str) -> Vec<SlashEvent> {
+        self.slash_history.get(address).cloned().unwrap_or_default()
+    }
+
+    pub fn is_active(&self, validator: &Validator) -> bool {
+        validator.activation_block <= self.current_block && validator.jailed_at.is_none()
+    }
+
+    /// Removes a validator from the set for good, moving its whole stake into
+    /// unbonding without any early-exit penalty.
+    pub fn exit_validator(&mut self, address: &str) -> bool {
+        let validator = match self.validators.remove(address) {
+            Some(validator) => validator,
+            None => return false,
+        };
+
+        self.total_stake -= validator.stake;
+        let release_block = self.current_block + self.unbonding_period;
+        if validator.stake > 0 {
+            self.unbonding.push(UnbondingEntry {
+                address: validator.address.clone(),
+                amount: validator.stake,
+                release_block,
+            });
+        }
+
+        self.unbond_delegations(address);
+        self.exited.insert(validator.address);
+        true
+    }
+
+    /// Removes a validator outright, returning its remaining stake for the
+    /// caller to refund. Unlike `exit_validator` the stake skips unbonding;
+    /// delegations to the validator still unbond as usual.
+    pub fn deregister_validator(&mut self, address: &str) -> Option<u64> {
+        let validator = self.validators.remove(address)?;
+        self.total_stake -= validator.stake;
+        self.unbond_delegations(address);
+        Some(validator.stake)
+    }
+
+    // Delegations to a departing validator unbond along with it
+    fn unbond_delegations(&mut self, validator: &str) {
+        let release_block = self.current_block + self.unbonding_period;
+        for (delegator, amount) in self.delegations.remove(validator).unwrap_or_default() {
+            self.total_stake -= amount;
+            self.unbonding.push(UnbondingEntry {
+                address: delegator,
+                amount,
+                release_block,
+            });
+        }
+    }
+
+    /// Stake of `address` still unbonding, oldest first
+    pub fn pending_unbonding(&self, address: &str) -> Vec<UnbondingEntry> {
+        self.unbonding.iter()
+            .filter(|entry| entry.address == address)
+            .cloned()
+            .collect()
+    }
+
+    /// Releases every unbonding entry of `address` whose release block has
+    /// been reached, returning the total for the caller to credit.
+    pub fn withdraw_unbonded(&mut self, address: &str) -> u64 {
+        let current_block = self.current_block;
+        let mut withdrawn = 0;
+        self.unbonding.retain(|entry| {
+            let released = entry.address == address && entry.release_block <= current_block;
+            if released {
+                withdrawn += entry.amount;
+            }
+            !released
+        });
+        withdrawn
+    }
+
+    pub fn is_exited(&self, address: &str) -> bool {
+        self.exited.contains(address)
+    }
+
+    /// Folds one participation sample into a validator's uptime using an
+    /// exponential moving average. A validator that misses `jail_threshold`
+    /// proposals in a row is jailed.
+    pub fn record_participation(&mut self, address: &str, participated: bool) {
+        let current_block = self.current_block;
+        let jail_threshold = self.jail_threshold;
+        if let Some(validator) = self.validators.get_mut(address) {
+            let sample = if participated { 100.0 } else { 0.0 };
+            validator.uptime = validator.uptime * (1.0 - UPTIME_SAMPLE_WEIGHT) + sample * UPTIME_SAMPLE_WEIGHT;
+
+            if participated {
+                validator.missed_in_a_row = 0;
+                return;
+            }
+            validator.missed_in_a_row += 1;
+            if jail_threshold > 0 && validator.missed_in_a_row >= jail_threshold && validator.jailed_at.is_none() {
+                tracing::warn!(%address, missed = validator.missed_in_a_row, "validator jailed");
+                validator.jailed_at = Some(current_block);
+            }
+        }
+    }
+
+    pub fn is_jailed(&self, address: &str) -> bool {
+        self.validators.get(address).is_some_and(|validator| validator.jailed_at.is_some())
+    }
+
+    /// Returns a jailed validator to selection once it has served
+    /// `min_jail_blocks`, clearing its missed proposals.
+    pub fn unjail(&mut self, address: &str) -> Result<(), String> {
+        let current_block = self.current_block;
+        let min_jail_blocks = self.min_jail_blocks;
+        let validator = self.validators.get_mut(address).ok_or("Validator not found")?;
+        let jailed_at = validator.jailed_at.ok_or("Validator is not jailed")?;
+        let release_block = jailed_at + min_jail_blocks;
+        if current_block < release_block {
+            return Err(format!("Validator is jailed until block {}", release_block));
+        }
+
+        validator.jailed_at = None;
+        validator.missed_in_a_row = 0;
+        tracing::info!(%address, "validator unjailed");
+        Ok(())
+    }
+
     pub fn select_proposer(&self) -> Option<String> {
-        if self.validators.is_empty() {
-            return None;
+        self.select_proposer_with_rng(&mut rand::thread_rng())
+    }
+
+    // Active validators sorted by effective stake (then address). HashMap iteration
+    // order is arbitrary, so sorting makes the same random draw always map
+    // to the same validator.
+    fn eligible_validators(&self) -> Vec<&Validator> {
+        let mut eligible: Vec<&Validator> = self.validators.values()
+            .filter(|validator| self.is_active(validator))
+            .collect();
+        eligible.sort_by(|a, b| {
+            self.effective_stake(b).cmp(&self.effective_stake(a)).then_with(|| a.address.cmp(&b.address))
+        });
+        eligible
+    }
+
+    /// Leader expected to propose the next block in the current view. View 0
+    /// is a stake-weighted draw seeded by the chain tip, so every node agrees
+    /// on it; each later view hands leadership to the next validator in line.
+    pub fn leader_for_view(&self) -> Option<String> {
+        let mut encoder = CanonicalEncoder::new();
+        encoder.u64(self.current_block + 1).str(&self.last_block_hash);
+        let digest = Sha3_256::digest(encoder.as_bytes());
+        let mut seed = [0u8; 8];
+        seed.copy_from_slice(&digest[..8]);
+
+        let base = self.select_proposer_with_rng(&mut StdRng::seed_from_u64(u64::from_le_bytes(seed)))?;
+        let eligible = self.eligible_validators();
+        let base_index = eligible.iter().position(|validator| validator.address == base)?;
+        let index = (base_index as u64 + self.view) % eligible.len() as u64;
+        Some(eligible[index as usize].address.clone())
+    }
+
+    /// Gives up on the current leader after a timeout, counting a missed
+    /// proposal against it, and moves to the next view, returning its leader.
+    pub fn timeout_to_next_view(&mut self) -> Option<String> {
+        if let Some(leader) = self.leader_for_view() {
+            self.record_participation(&leader, false);
         }
+        self.view += 1;
+        self.leader_for_view()
+    }
 
-        // Simple weighted random selection based on stake
-        let mut rng = rand::thread_rng();
-        let total_stake = self.total_stake as f64;
-        
-        if total_stake == 0.0 {
+    /// Whether the validator proposed within the last `cooldown_blocks`
+    /// blocks. A `last_block_proposed` of 0 means it has never proposed.
+    pub fn in_cooldown(&self, validator: &Validator) -> bool {
+        validator.last_block_proposed > 0
+            && self.current_block - validator.last_block_proposed < self.cooldown_blocks
+    }
+
+    /// Stake-weighted proposer selection driven by the given RNG, so a seeded
+    /// RNG always picks the same sequence of proposers.
+    pub fn select_proposer_with_rng(&self, rng: &mut impl RngCore) -> Option<String> {
+        let mut eligible = self.eligible_validators();
+
+        // Validators that proposed within the cooldown sit this block out,
+        // unless that would leave nobody to propose
+        let rested: Vec<&Validator> = eligible.iter()
+            .copied()
+            .filter(|validator| !self.in_cooldown(validator))
+            .collect();
+        if !rested.is_empty() {
+            eligible = rested;
+        }
+
+        // Weighted random selection based on stake, discounted by uptime
+        // so chronically offline validators are chosen less often
+        let weights: Vec<u64> = eligible.iter()
+            .map(|validator| (self.effective_stake(validator) as f64 * (validator.uptime / 100.0).clamp(0.0, 1.0)) as u64)
+            .collect();
+        let total_weight = weights.iter().sum::<u64>();
+
+        if total_weight == 0 {
             return None;
         }
-        
-        let random_value = (rand::random::<f64>() * total_stake) as u64;
-        
-        let mut cumulative_stake = 0;
-        for (address, validator) in &self.validators {
-            cumulative_stake += validator.stake;
-            if cumulative_stake >= random_value {
-                return Some(address.clone());
+
+        let random_value = rng.gen_range(0..total_weight);
+
+        let mut cumulative_weight = 0;
+        for (validator, weight) in eligible.iter().zip(&weights) {
+            cumulative_weight += weight;
+            if random_value < cumulative_weight {
+                return Some(validator.address.clone());
             }
         }
-        
-        // Fallback to first validator
-        self.validators.keys().next().cloned()
+
+        None
     }
 
     pub fn propose_block(&mut self, proposer: &str, transactions: Vec<String>) -> Option<Block> {
@@ -112,50 +652,143 @@ impl PoSConsensus {
         }
 
         let index = self.current_block + 1;
-        let timestamp = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .unwrap()
-            .as_secs();
-
-        // Create a simple hash of the block data
-        let mut hasher = Sha3_256::new();
-        hasher.update(index.to_string());
-        hasher.update(timestamp.to_string());
-        if let Some(prev_validator) = self.validators.values().next() {
-            hasher.update(&prev_validator.address);
-        }
-        for tx in &transactions {
-            hasher.update(tx);
-        }
-        let hash = format!("{:x}", hasher.finalize());
-
-        let block = Block {
+        let mut block = Block {
             index,
-            timestamp,
-            prev_hash: if self.current_block == 0 { "0".to_string() } else { 
-                // In a real implementation, we would have the previous block hash
-                format!("prev_hash_{}", self.current_block) 
-            },
+            timestamp: self.clock.now_secs(),
+            prev_hash: self.last_block_hash.clone(),
+            tx_root: merkle::merkle_root_of_entries(&transactions),
             transactions,
             proposer: proposer.to_string(),
-            hash,
+            hash: String::new(),
         };
+        block.hash = block.compute_hash_with(self.hash_algo);
+
+        self.record_block(&block);
+        Some(block)
+    }
 
+    /// Applies an already-produced block, as done when replaying a chain.
+    pub fn apply_block(&mut self, block: &Block) -> bool {
+        if !self.validators.contains_key(&block.proposer) {
+            return false;
+        }
+
+        self.record_block(block);
+        self.calculate_rewards(block);
+        true
+    }
+
+    fn record_block(&mut self, block: &Block) {
         // Update validator's last proposed block
-        if let Some(validator) = self.validators.get_mut(proposer) {
-            validator.last_block_proposed = index;
+        if let Some(validator) = self.validators.get_mut(&block.proposer) {
+            validator.last_block_proposed = block.index;
         }
+        self.record_participation(&block.proposer, true);
 
-        self.current_block = index;
-        Some(block)
+        self.current_block = block.index;
+        self.last_block_hash = block.hash.clone();
+        // A new height starts back at view 0
+        self.view = 0;
+    }
+
+    /// SHA3-256 over the height, chain tip and stake table, canonically
+    /// encoded as `current_block`, `last_block_hash`, then a map from each
+    /// validator's address to its stake followed by a map from each of its
+    /// delegators to the amount delegated. Independent of map order.
+    pub fn state_root(&self) -> String {
+        let mut encoder = CanonicalEncoder::new();
+        encoder.u64(self.current_block).str(&self.last_block_hash);
+        encoder.map(self.validators.iter(), |encoder, validator| {
+            encoder.u64(validator.stake);
+            let delegations = self.delegations.get(&validator.address).map(Vec::as_slice).unwrap_or_default();
+            encoder.map(delegations.iter().map(|(delegator, amount)| (delegator, *amount)), |encoder, amount| {
+                encoder.u64(amount);
+            });
+        });
+        encoder.hash(HashAlgo::Sha3_256)
     }
 
+    /// Proposer reward for the block at `index` under the emission schedule.
+    pub fn block_reward(&self, index: u64) -> u64 {
+        self.emission.reward_at(index)
+    }
+
+    /// Expected yearly return on the stake backing `address`, as a fraction
+    /// (0.05 is 5%). Assumes a block every `target_block_time` seconds paying
+    /// the next block's reward, with proposers drawn in proportion to
+    /// effective stake. Unknown validators earn nothing.
+    pub fn estimate_apr(&self, address: &str) -> f64 {
+        let validator = match self.validators.get(address) {
+            Some(validator) => validator,
+            None => return 0.0,
+        };
+        let effective_stake = self.effective_stake(validator);
+        if effective_stake == 0 || self.total_stake == 0 || self.target_block_time == 0 {
+            return 0.0;
+        }
+
+        let blocks_per_year = SECONDS_PER_YEAR as f64 / self.target_block_time as f64;
+        let share = effective_stake as f64 / self.total_stake as f64;
+        let yearly_rewards = self.block_reward(self.current_block + 1) as f64 * blocks_per_year * share;
+        yearly_rewards / effective_stake as f64
+    }
+
+    /// Pays the block reward, split between the proposer and its delegators
+    /// in proportion to stake. The proposer first keeps its commission of the
+    /// delegators' portion, and the rest is split between them by amount
+    /// delegated. Rounding remainders and the fees of the included
+    /// transactions go to the proposer.
     pub fn calculate_rewards(&mut self, block: &Block) {
-        // Simple reward calculation
-        if let Some(validator) = self.validators.get_mut(&block.proposer) {
-            // Add reward for proposing a block
-            validator.stake += 10; // Fixed reward for simplicity
-            self.total_stake += 10;
+        let reward = self.block_reward(block.index);
+        let fees = Transaction::total_fees(&block.transactions);
+        let (own_stake, commission) = match self.validators.get(&block.proposer) {
+            Some(validator) => (validator.stake, validator.commission),
+            None => return,
+        };
+
+        let delegations = self.delegations.get_mut(&block.proposer);
+        let delegated: u64 = delegations.as_ref()
+            .map(|delegations| delegations.iter().map(|(_, amount)| amount).sum())
+            .unwrap_or(0);
+        let effective_stake = own_stake + delegated;
+
+        // Delegator shares are added to their delegations
+        let mut delegators_paid = 0;
+        if let Some(delegations) = delegations {
+            let portion = (reward as u128 * delegated as u128 / effective_stake as u128) as u64;
+            let kept = ((portion as f64 * commission) as u64).min(portion);
+            let remainder = portion - kept;
+            for (_, amount) in delegations.iter_mut() {
+                let share = (remainder as u128 * *amount as u128 / delegated as u128) as u64;
+                *amount += share;
+                delegators_paid += share;
+            }
+        }
+
+        // The proposer's share stays staked until withdrawn
+        let validator = self.validators.get_mut(&block.proposer).unwrap();
+        validator.stake += reward - delegators_paid + fees;
+        validator.rewards += reward - delegators_paid + fees;
+        self.total_stake += reward + fees;
+    }
+
+    /// Takes `amount` of a validator's accrued rewards out of its stake,
+    /// leaving the principal staked. Returns the amount withdrawn, which the
+    /// caller credits to the validator's spendable balance.
+    pub fn withdraw_rewards(&mut self, address: &str, amount: u64) -> Result<u64, String> {
+        let validator = self.validators.get_mut(address)
+            .ok_or_else(|| "Validator not found".to_string())?;
+
+        if amount > validator.rewards {
+            return Err(format!(
+                "Cannot withdraw {}: only {} in rewards has accrued",
+                amount, validator.rewards
+            ));
         }
+
+        validator.rewards -= amount;
+        validator.stake -= amount;
+        self.total_stake -= amount;
+        Ok(amount)
     }
 }
\ No newline at end of file
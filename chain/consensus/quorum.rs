@@ -0,0 +1,58 @@
+// chain/consensus/quorum.rs
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use super::HybridBlock;
+use crate::chain::crypto;
+
+/// Proof that validators holding more than 2/3 of the voting power signed a block.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuorumCertificate {
+    pub block_hash: String,
+    pub signatures: Vec<(String, String)>, // (validator address, hex signature over block_hash)
+    pub total_power: u64,                  // Total voting power of the validator set
+}
+
+/// A validator's voting power and signing key as seen by a light client.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QuorumMember {
+    pub address: String,
+    pub power: u64,
+    pub public_key: String,
+}
+
+/// Checks that `block` carries a certificate signed by more than 2/3 of
+/// `validator_set`'s power. Unknown signers, bad signatures and duplicate
+/// votes contribute no power.
+pub fn verify_quorum_certificate(block: &HybridBlock, validator_set: &[QuorumMember]) -> bool {
+    let qc = match &block.quorum_certificate {
+        Some(qc) => qc,
+        None => return false,
+    };
+
+    if qc.block_hash != block.hybrid_hash {
+        return false;
+    }
+
+    let total_power: u64 = validator_set.iter().map(|member| member.power).sum();
+    if total_power == 0 || qc.total_power != total_power {
+        return false;
+    }
+
+    let mut counted = HashSet::new();
+    let mut signed_power: u64 = 0;
+    for (validator, signature) in &qc.signatures {
+        let member = match validator_set.iter().find(|member| &member.address == validator) {
+            Some(member) => member,
+            None => continue,
+        };
+
+        if !counted.contains(validator)
+            && crypto::verify(&member.public_key, qc.block_hash.as_bytes(), signature)
+        {
+            counted.insert(validator.clone());
+            signed_power += member.power;
+        }
+    }
+
+    signed_power as u128 * 3 > total_power as u128 * 2
+}
@@ -0,0 +1,134 @@
+// chain/consensus/replay.rs
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use super::HybridBlock;
+use super::clock::{Clock, SystemClock};
+use super::hashing::HashAlgo;
+use super::pos::{PoSConsensus, GENESIS_ALLOCATIONS_PREFIX};
+use super::verifier::BlockVerifier;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GenesisValidator {
+    pub address: String,
+    pub stake: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Genesis {
+    pub validators: Vec<GenesisValidator>,
+    #[serde(default)]
+    pub hash_algo: HashAlgo,
+    /// Hash of the balances allocated at genesis, if any were
+    #[serde(default)]
+    pub allocations_hash: Option<String>,
+}
+
+impl Genesis {
+    /// Recovers the genesis validator set from a chain's genesis block, which
+    /// lists each validator as an `address:stake` entry, along with the
+    /// allocations hash from its `allocations:<hash>` entry if it has one.
+    pub fn from_block(block: &HybridBlock, hash_algo: HashAlgo) -> Result<Self, String> {
+        if block.pos_block.index != 0 {
+            return Err(format!("Block {} is not a genesis block", block.pos_block.index));
+        }
+
+        let entries = &block.pos_block.transactions;
+        let allocations_hash = entries.iter()
+            .find_map(|entry| entry.strip_prefix(GENESIS_ALLOCATIONS_PREFIX))
+            .map(str::to_string);
+        let validators = entries.iter()
+            .filter(|entry| !entry.starts_with(GENESIS_ALLOCATIONS_PREFIX))
+            .map(|entry| {
+                let (address, stake) = entry.rsplit_once(':')
+                    .ok_or_else(|| format!("Malformed genesis entry '{}'", entry))?;
+                let stake = stake.parse::<u64>()
+                    .map_err(|_| format!("Malformed genesis entry '{}'", entry))?;
+                Ok(GenesisValidator { address: address.to_string(), stake })
+            })
+            .collect::<Result<Vec<_>, String>>()?;
+        Ok(Genesis { validators, hash_algo, allocations_hash })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChainState {
+    pub height: u64,
+    pub last_block_hash: String,
+    pub stakes: BTreeMap<String, u64>,
+    pub state_root: String,
+}
+
+impl From<&PoSConsensus> for ChainState {
+    fn from(pos: &PoSConsensus) -> Self {
+        ChainState {
+            height: pos.current_block,
+            last_block_hash: pos.last_block_hash.clone(),
+            stakes: pos.validators.iter()
+                .map(|(address, validator)| (address.clone(), validator.stake))
+                .collect(),
+            state_root: pos.state_root(),
+        }
+    }
+}
+
+/// Re-derives the PoS state purely from genesis and the given blocks.
+///
+/// Every block must verify, extend the previous one and be proposed by a
+/// known validator; the first violation is returned as an error. `blocks`
+/// may start with the genesis block, which must then match `genesis`.
+pub fn replay_from_genesis(genesis: &Genesis, blocks: &[HybridBlock]) -> Result<ChainState, String> {
+    replay_pos(genesis, blocks, Arc::new(SystemClock)).map(|pos| ChainState::from(&pos))
+}
+
+/// Same checks as `replay_from_genesis`, returning the rebuilt PoS state
+/// itself so a node can adopt it. `clock` drives the blocks it proposes
+/// afterwards.
+pub fn replay_pos(genesis: &Genesis, blocks: &[HybridBlock], clock: Arc<dyn Clock>) -> Result<PoSConsensus, String> {
+    let mut pos = PoSConsensus::with_clock(clock);
+    pos.hash_algo = genesis.hash_algo;
+    pos.set_genesis_allocations_hash(genesis.allocations_hash.clone());
+    let verifier = BlockVerifier::new(genesis.hash_algo);
+    for validator in &genesis.validators {
+        pos.register_validator(validator.address.clone(), validator.stake);
+    }
+
+    let blocks = match blocks.split_first() {
+        Some((first, rest)) if first.pos_block.index == 0 => {
+            if !verifier.verify_genesis(first) || first.pos_block.hash != pos.genesis.hash {
+                return Err("Chain starts from a different genesis block".to_string());
+            }
+            rest
+        },
+        _ => blocks,
+    };
+
+    for block in blocks {
+        let pos_block = &block.pos_block;
+
+        if !verifier.verify_hybrid_block(block) {
+            return Err(format!("Block {} failed verification", pos_block.index));
+        }
+        if pos_block.index != pos.current_block + 1 {
+            return Err(format!("Block {} is out of sequence (expected {})", pos_block.index, pos.current_block + 1));
+        }
+        if pos_block.prev_hash != pos.last_block_hash {
+            return Err(format!("Block {} does not extend the previous block", pos_block.index));
+        }
+        if !pos.apply_block(pos_block) {
+            return Err(format!("Block {} proposed by unknown validator {}", pos_block.index, pos_block.proposer));
+        }
+    }
+
+    Ok(pos)
+}
+
+/// Returns whether replaying `blocks` reproduces the state root committed by the tip.
+pub fn replay_matches_tip(genesis: &Genesis, blocks: &[HybridBlock]) -> Result<bool, String> {
+    let state = replay_from_genesis(genesis, blocks)?;
+    match blocks.last() {
+        Some(tip) => Ok(state.state_root == tip.state_root),
+        // Nothing has been committed yet, so genesis trivially matches
+        None => Ok(true),
+    }
+}
@@ -1,60 +1,192 @@
 // chain/consensus/verifier.rs
-use crate::consensus::{pos::Block, HybridBlock};
-use sha3::{Sha3_256, Digest};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use super::{hashing::HashAlgo, pos::{Block, GENESIS_TIMESTAMP}, replay::Genesis, HybridBlock};
+use crate::chain::merkle;
 
-pub struct BlockVerifier;
+/// How far ahead of local time a block timestamp may be before it is rejected
+pub const MAX_FUTURE_DRIFT_SECS: u64 = 120;
+
+/// Default cap on the transactions in one block
+pub const DEFAULT_MAX_BLOCK_TXS: usize = 100;
+
+/// Default cap on the combined size of a block's transaction entries
+pub const DEFAULT_MAX_BLOCK_BYTES: usize = 1024 * 1024;
+
+/// Outcome of comparing two competing chains. `fork_point` is the index of
+/// the first block at which they differ.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChainChoice {
+    First { fork_point: usize },
+    Second { fork_point: usize },
+    NeitherValid,
+}
+
+/// Checks blocks against the hash algorithm their chain was built with and
+/// the size limits blocks must respect
+#[derive(Debug, Clone, Copy)]
+pub struct BlockVerifier {
+    pub hash_algo: HashAlgo,
+    pub max_block_txs: usize,
+    pub max_block_bytes: usize,
+}
+
+impl Default for BlockVerifier {
+    fn default() -> Self {
+        Self::new(HashAlgo::default())
+    }
+}
+
+/// Whether no entry appears twice in the block. The Merkle tree repeats the
+/// last node of an odd level, so a copy of a block's last entry would leave
+/// `tx_root`, and with it the block hash, unchanged.
+pub fn has_unique_entries(block: &Block) -> bool {
+    let mut seen = std::collections::HashSet::new();
+    block.transactions.iter().all(|entry| seen.insert(entry))
+}
 
 impl BlockVerifier {
-    pub fn verify_pos_block(block: &Block) -> bool {
-        // Verify the block hash is valid
-        let mut hasher = Sha3_256::new();
-        hasher.update(block.index.to_string());
-        hasher.update(block.timestamp.to_string());
-        hasher.update(&block.prev_hash);
-        for tx in &block.transactions {
-            hasher.update(tx);
+    pub fn new(hash_algo: HashAlgo) -> Self {
+        BlockVerifier {
+            hash_algo,
+            max_block_txs: DEFAULT_MAX_BLOCK_TXS,
+            max_block_bytes: DEFAULT_MAX_BLOCK_BYTES,
+        }
+    }
+
+    /// Whether the block stays within the transaction count and byte limits.
+    /// Genesis blocks list the validator set instead and are exempt.
+    pub fn within_size_limits(&self, block: &Block) -> bool {
+        block.index == 0
+            || (block.transactions.len() <= self.max_block_txs
+                && block.transactions.iter().map(String::len).sum::<usize>() <= self.max_block_bytes)
+    }
+
+    pub fn verify_pos_block(&self, block: &Block) -> bool {
+        if !self.within_size_limits(block) || !has_unique_entries(block) {
+            return false;
+        }
+
+        // The hash commits to the transactions only through the Merkle root
+        if block.tx_root != merkle::merkle_root_of_entries(&block.transactions) {
+            return false;
         }
-        hasher.update(&block.proposer);
-        let calculated_hash = format!("{:x}", hasher.finalize());
-        
-        // The hash should match what's in the block
-        // In a real implementation, this would be more complex
-        block.hash == calculated_hash || block.hash.starts_with(&calculated_hash[..8])
+
+        // The hash must be exactly the canonical hash of the header
+        block.hash == block.compute_hash_with(self.hash_algo)
     }
 
-    pub fn verify_hybrid_block(block: &HybridBlock) -> bool {
+    pub fn verify_hybrid_block(&self, block: &HybridBlock) -> bool {
         // First verify the PoS component
-        if !Self::verify_pos_block(&block.pos_block) {
+        if !self.verify_pos_block(&block.pos_block) {
             return false;
         }
 
         // Then verify the hybrid hash
-        let mut hasher = Sha3_256::new();
-        hasher.update(&block.pos_block.hash);
-        for proof in &block.quantum_proofs {
-            hasher.update(&proof.proof_artifact);
-        }
-        let calculated_hybrid_hash = format!("{:x}", hasher.finalize());
-        
-        block.hybrid_hash == calculated_hybrid_hash || 
-        block.hybrid_hash.starts_with(&calculated_hybrid_hash[..8])
+        block.hybrid_hash == block.compute_hybrid_hash_with(self.hash_algo)
+    }
+
+    /// Checks that `block` has the fixed shape of a genesis block and that
+    /// its hashes are intact.
+    pub fn verify_genesis(&self, block: &HybridBlock) -> bool {
+        let pos_block = &block.pos_block;
+        pos_block.index == 0
+            && pos_block.prev_hash == "0"
+            && pos_block.timestamp == GENESIS_TIMESTAMP
+            && pos_block.proposer.is_empty()
+            && block.quantum_proofs.is_empty()
+            && self.verify_hybrid_block(block)
     }
 
-    pub fn verify_chain(blocks: &[HybridBlock]) -> bool {
+    pub fn verify_chain(&self, blocks: &[HybridBlock]) -> bool {
+        self.find_invalid_block(blocks).is_none()
+    }
+
+    /// Position in `blocks` of the first block that fails verification, if
+    /// any. A non-empty chain must start with a valid genesis block.
+    pub fn find_invalid_block(&self, blocks: &[HybridBlock]) -> Option<usize> {
+        self.first_failure(blocks).map(|(i, _)| i)
+    }
+
+    /// Like `find_invalid_block`, along with the reason the block failed.
+    pub fn first_failure(&self, blocks: &[HybridBlock]) -> Option<(usize, &'static str)> {
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs();
+
+        if blocks.first().is_some_and(|genesis| !self.verify_genesis(genesis)) {
+            return Some((0, "not a valid genesis block"));
+        }
+
         for (i, block) in blocks.iter().enumerate() {
             // Verify the current block
-            if !Self::verify_hybrid_block(block) {
-                return false;
+            if !self.verify_hybrid_block(block) {
+                return Some((i, "hash, transaction root or size check failed"));
             }
 
-            // Check that the chain is continuous
+            // Reject blocks dated too far in the future
+            if block.pos_block.timestamp > now.saturating_add(MAX_FUTURE_DRIFT_SECS) {
+                return Some((i, "timestamp is too far in the future"));
+            }
+
+            // Check that the chain is continuous and time never runs backwards
             if i > 0 {
                 let prev_block = &blocks[i - 1];
                 if block.pos_block.prev_hash != prev_block.pos_block.hash {
-                    return false;
+                    return Some((i, "does not extend the previous block"));
+                }
+                if block.pos_block.timestamp < prev_block.pos_block.timestamp {
+                    return Some((i, "timestamp is earlier than the previous block's"));
                 }
             }
         }
-        true
+        None
+    }
+
+    /// Stake-weighted work of a chain: the sum, over every block after
+    /// genesis, of its proposer's stake as listed in the chain's genesis
+    /// block. Proposers absent from genesis add nothing.
+    pub fn chain_work(&self, blocks: &[HybridBlock]) -> u128 {
+        let stakes: HashMap<String, u64> = blocks.first()
+            .and_then(|genesis| Genesis::from_block(genesis, self.hash_algo).ok())
+            .map(|genesis| genesis.validators.into_iter().map(|v| (v.address, v.stake)).collect())
+            .unwrap_or_default();
+
+        blocks.iter()
+            .skip(1)
+            .map(|block| stakes.get(&block.pos_block.proposer).copied().unwrap_or(0) as u128)
+            .sum()
+    }
+
+    /// Picks between two competing chains: a chain that fails verification
+    /// loses outright, otherwise the greater `chain_work` wins. Equal work
+    /// goes to the chain whose tip has the lower hybrid hash, so every node
+    /// settles on the same chain.
+    pub fn choose_canonical(&self, a: &[HybridBlock], b: &[HybridBlock]) -> ChainChoice {
+        let fork_point = a.iter()
+            .zip(b)
+            .take_while(|(x, y)| x.hybrid_hash == y.hybrid_hash)
+            .count();
+
+        let first = match (self.verify_chain(a), self.verify_chain(b)) {
+            (false, false) => return ChainChoice::NeitherValid,
+            (true, false) => true,
+            (false, true) => false,
+            (true, true) => {
+                let tip_hash = |blocks: &[HybridBlock]| blocks.last().map(|block| block.hybrid_hash.clone());
+                match self.chain_work(a).cmp(&self.chain_work(b)) {
+                    Ordering::Greater => true,
+                    Ordering::Less => false,
+                    Ordering::Equal => tip_hash(a) <= tip_hash(b),
+                }
+            },
+        };
+
+        if first {
+            ChainChoice::First { fork_point }
+        } else {
+            ChainChoice::Second { fork_point }
+        }
     }
-}
\ No newline at end of file
+}
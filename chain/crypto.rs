@@ -0,0 +1,70 @@
+// chain/crypto.rs
+use ring::signature::{Ed25519KeyPair, KeyPair, UnparsedPublicKey, ED25519};
+use sha3::{Digest, Sha3_256};
+
+/// Derives an Ed25519 key pair from a 32-byte seed.
+pub fn keypair_from_seed(seed: &[u8]) -> Result<Ed25519KeyPair, String> {
+    Ed25519KeyPair::from_seed_unchecked(seed)
+        .map_err(|_| "Invalid Ed25519 seed".to_string())
+}
+
+pub fn public_key_hex(seed: &[u8]) -> Result<String, String> {
+    let keypair = keypair_from_seed(seed)?;
+    Ok(hex::encode(keypair.public_key().as_ref()))
+}
+
+/// Signs `message` with the key derived from `seed`, returning a hex signature.
+pub fn sign(seed: &[u8], message: &[u8]) -> Result<String, String> {
+    let keypair = keypair_from_seed(seed)?;
+    Ok(hex::encode(keypair.sign(message).as_ref()))
+}
+
+pub fn verify(public_key_hex: &str, message: &[u8], signature_hex: &str) -> bool {
+    let (public_key, signature) = match (hex::decode(public_key_hex), hex::decode(signature_hex)) {
+        (Ok(public_key), Ok(signature)) => (public_key, signature),
+        _ => return false,
+    };
+
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(message, &signature)
+        .is_ok()
+}
+
+/// Hex characters in an address after the `Q` prefix
+pub const ADDRESS_BODY_LEN: usize = 39;
+
+/// Derives a `Q`-prefixed address from a hex public key, the same way the
+/// wallet does: `Q` followed by the first 39 hex characters of the key,
+/// checksummed with `checksum_address`.
+pub fn address_from_public_key(public_key_hex: &str) -> Option<String> {
+    public_key_hex.get(..ADDRESS_BODY_LEN).map(checksum_address)
+}
+
+/// Builds an address from its hex body, encoding a checksum in the letter
+/// case: a letter is uppercased when the matching nibble of the SHA3-256 of
+/// the lowercase body is 8 or more. This is EIP-55 with SHA3 in place of
+/// Keccak.
+pub fn checksum_address(body_hex: &str) -> String {
+    let body = body_hex.to_ascii_lowercase();
+    let hash = hex::encode(Sha3_256::digest(body.as_bytes()));
+
+    let checksummed: String = body.chars()
+        .zip(hash.chars())
+        .map(|(c, nibble)| match nibble.to_digit(16) {
+            Some(nibble) if nibble >= 8 => c.to_ascii_uppercase(),
+            _ => c,
+        })
+        .collect();
+    format!("Q{}", checksummed)
+}
+
+/// True if `address` is a `Q` followed by 39 hex characters whose letter
+/// case matches the checksum. A mistyped character almost always breaks it.
+pub fn validate_address(address: &str) -> bool {
+    match address.strip_prefix('Q') {
+        Some(body) if body.len() == ADDRESS_BODY_LEN && body.chars().all(|c| c.is_ascii_hexdigit()) => {
+            checksum_address(body) == address
+        },
+        _ => false,
+    }
+}
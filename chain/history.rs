@@ -0,0 +1,68 @@
+// chain/history.rs
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+
+/// Per-block balance diffs that allow reading an account's balance as of a
+/// past height. Diffs older than the retention window are folded into a base
+/// snapshot, after which those heights can no longer be queried.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct BalanceHistory {
+    base: HashMap<String, u64>,                  // Balances as of `pruned_before - 1`
+    diffs: BTreeMap<u64, HashMap<String, u64>>,  // Height -> new balances written at that height
+    pruned_before: u64,                          // Lowest height that can still be queried
+}
+
+impl BalanceHistory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the balances changed by the block at `height`.
+    pub fn record(&mut self, height: u64, changes: HashMap<String, u64>) {
+        self.diffs.entry(height).or_default().extend(changes);
+    }
+
+    pub fn balance_at(&self, address: &str, height: u64) -> Result<u64, String> {
+        if height < self.pruned_before {
+            return Err(format!("State at height {} has been pruned", height));
+        }
+
+        let recorded = self.diffs.range(..=height)
+            .rev()
+            .find_map(|(_, changes)| changes.get(address));
+
+        Ok(*recorded.or_else(|| self.base.get(address)).unwrap_or(&0))
+    }
+
+    pub fn latest_balance(&self, address: &str) -> u64 {
+        self.balance_at(address, u64::MAX).unwrap_or(0)
+    }
+
+    /// Current balance of every account that has one recorded.
+    pub fn latest_balances(&self) -> HashMap<String, u64> {
+        let mut balances = self.base.clone();
+        for changes in self.diffs.values() {
+            balances.extend(changes.iter().map(|(address, balance)| (address.clone(), *balance)));
+        }
+        balances
+    }
+
+    /// Folds every diff below `height` into the base snapshot.
+    pub fn prune_before(&mut self, height: u64) {
+        let retained = self.diffs.split_off(&height);
+        for (_, changes) in std::mem::replace(&mut self.diffs, retained) {
+            self.base.extend(changes);
+        }
+        self.pruned_before = self.pruned_before.max(height);
+    }
+
+    /// Drops the diffs recorded above `height`, e.g. for blocks abandoned
+    /// when switching to another fork.
+    pub fn truncate_after(&mut self, height: u64) {
+        self.diffs.split_off(&height.saturating_add(1));
+    }
+
+    pub fn pruned_before(&self) -> u64 {
+        self.pruned_before
+    }
+}
@@ -0,0 +1,80 @@
+// chain/ledger.rs
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use crate::chain::transaction::Transaction;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LedgerError {
+    InsufficientFunds { address: String, balance: u64, required: u64 },
+    Overflow { address: String },
+}
+
+impl fmt::Display for LedgerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LedgerError::InsufficientFunds { address, balance, required } => write!(
+                f,
+                "Insufficient funds for {}: balance {} but {} required",
+                address, balance, required
+            ),
+            LedgerError::Overflow { address } => write!(f, "Balance of {} would overflow", address),
+        }
+    }
+}
+
+impl std::error::Error for LedgerError {}
+
+/// Spendable account balances. Every transfer debits one account by exactly
+/// what it credits another, so transfers never change the total supply.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Ledger {
+    pub balances: HashMap<String, u64>,
+}
+
+impl Ledger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn balance(&self, address: &str) -> u64 {
+        self.balances.get(address).copied().unwrap_or(0)
+    }
+
+    /// Moves `amount` from `from` to `to`, leaving both untouched on error.
+    pub fn transfer(&mut self, from: &str, to: &str, amount: u64) -> Result<(), LedgerError> {
+        let balance = self.balance(from);
+        if balance < amount {
+            return Err(LedgerError::InsufficientFunds { address: from.to_string(), balance, required: amount });
+        }
+        if from == to {
+            return Ok(());
+        }
+        let credited = self.balance(to)
+            .checked_add(amount)
+            .ok_or_else(|| LedgerError::Overflow { address: to.to_string() })?;
+
+        self.balances.insert(from.to_string(), balance - amount);
+        self.balances.insert(to.to_string(), credited);
+        Ok(())
+    }
+
+    /// Applies an included transaction: the sender must cover the amount and
+    /// the fee. The fee leaves the ledger, since it is paid to the proposer
+    /// as staking rewards.
+    pub fn apply_transaction(&mut self, tx: &Transaction) -> Result<(), LedgerError> {
+        let balance = self.balance(&tx.from);
+        let required = tx.amount.saturating_add(tx.fee);
+        if balance < required {
+            return Err(LedgerError::InsufficientFunds { address: tx.from.clone(), balance, required });
+        }
+
+        self.transfer(&tx.from, &tx.to, tx.amount)?;
+        self.balances.insert(tx.from.clone(), self.balance(&tx.from) - tx.fee);
+        Ok(())
+    }
+
+    pub fn total_supply(&self) -> u128 {
+        self.balances.values().map(|&balance| balance as u128).sum()
+    }
+}
@@ -0,0 +1,94 @@
+// chain/merkle.rs
+use sha3::{Digest, Sha3_256};
+use crate::chain::transaction::Transaction;
+
+// Leaves and internal nodes are hashed under different prefixes, so that an
+// internal node can never be passed off as a leaf or the other way round
+const LEAF_PREFIX: u8 = 0x00;
+const NODE_PREFIX: u8 = 0x01;
+
+fn hash_pair(left: &str, right: &str) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update([NODE_PREFIX]);
+    hasher.update(left);
+    hasher.update(right);
+    format!("{:x}", hasher.finalize())
+}
+
+/// Leaf hash of a block entry. A transaction's leaf is taken over its
+/// `to_block_entry()` encoding, so roots over transactions and over the
+/// entries stored in a block agree.
+pub fn leaf_hash(entry: &str) -> String {
+    let mut hasher = Sha3_256::new();
+    hasher.update([LEAF_PREFIX]);
+    hasher.update(entry.as_bytes());
+    format!("{:x}", hasher.finalize())
+}
+
+// Hashes one level of the tree into the next, duplicating the last node
+// when the level has an odd length. A list with its last entry repeated
+// therefore has the same root, which is why blocks must not repeat entries.
+fn next_level(level: &[String]) -> Vec<String> {
+    level.chunks(2)
+        .map(|pair| hash_pair(&pair[0], pair.get(1).unwrap_or(&pair[0])))
+        .collect()
+}
+
+fn root_of_leaves(mut level: Vec<String>) -> String {
+    if level.is_empty() {
+        return leaf_hash("");
+    }
+    while level.len() > 1 {
+        level = next_level(&level);
+    }
+    level.remove(0)
+}
+
+/// Merkle root over raw block entries, as stored in `Block.transactions`.
+pub fn merkle_root_of_entries(entries: &[String]) -> String {
+    root_of_leaves(entries.iter().map(|entry| leaf_hash(entry)).collect())
+}
+
+pub fn merkle_root(items: &[Transaction]) -> String {
+    root_of_leaves(items.iter().map(|tx| leaf_hash(&tx.to_block_entry())).collect())
+}
+
+/// Sibling hashes from the leaf at `index` up to the root. Empty if `index`
+/// is out of range.
+pub fn merkle_proof(items: &[Transaction], index: usize) -> Vec<String> {
+    if index >= items.len() {
+        return Vec::new();
+    }
+
+    let mut level: Vec<String> = items.iter().map(|tx| leaf_hash(&tx.to_block_entry())).collect();
+    let mut index = index;
+    let mut proof = Vec::new();
+
+    while level.len() > 1 {
+        let sibling = if index.is_multiple_of(2) {
+            level.get(index + 1).unwrap_or(&level[index])
+        } else {
+            &level[index - 1]
+        };
+        proof.push(sibling.clone());
+        level = next_level(&level);
+        index /= 2;
+    }
+    proof
+}
+
+/// Checks that `item` sits at `index` in the tree with the given `root`.
+pub fn verify_proof(item: &Transaction, index: usize, proof: &[String], root: &str) -> bool {
+    let mut hash = leaf_hash(&item.to_block_entry());
+    let mut index = index;
+
+    for sibling in proof {
+        hash = if index.is_multiple_of(2) {
+            hash_pair(&hash, sibling)
+        } else {
+            hash_pair(sibling, &hash)
+        };
+        index /= 2;
+    }
+    hash == root
+}
@@ -0,0 +1,7 @@
+// chain/mod.rs
+pub mod consensus;
+pub mod crypto;
+pub mod history;
+pub mod ledger;
+pub mod merkle;
+pub mod transaction;
@@ -0,0 +1,92 @@
+// chain/transaction.rs
+use serde::{Deserialize, Serialize};
+use sha3::{Digest, Sha3_256};
+use crate::chain::consensus::hashing::CanonicalEncoder;
+use crate::chain::crypto;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Transaction {
+    pub from: String,
+    pub to: String,
+    pub amount: u64,
+    /// Per-sender sequence number, starting at 1; see `GET /nonce/{address}`
+    #[serde(default)]
+    pub nonce: u64,
+    /// Paid by the sender to the block proposer; higher fees are included first
+    #[serde(default)]
+    pub fee: u64,
+    /// Unix time before which the transaction may not be included in a block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub valid_after: Option<u64>,
+    /// Hex Ed25519 public key of the sender, whose address must be `from`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub public_key: String,
+    /// Sender's hex signature over `signing_bytes`
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub signature: String,
+}
+
+impl Transaction {
+    /// Encoding used when the transaction is embedded in a block.
+    pub fn to_block_entry(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Identifies the transaction: the SHA3-256 hex digest of its block entry
+    pub fn hash(&self) -> String {
+        Self::entry_hash(&self.to_block_entry())
+    }
+
+    /// Hash of a transaction as it appears in a block's entries
+    pub fn entry_hash(entry: &str) -> String {
+        format!("{:x}", Sha3_256::digest(entry.as_bytes()))
+    }
+
+    /// Bytes the sender signs: `from`, `to`, `amount`, `nonce`, `fee` and
+    /// `valid_after` (as a list of zero or one values), canonically encoded
+    pub fn signing_bytes(&self) -> Vec<u8> {
+        CanonicalEncoder::new()
+            .str(&self.from)
+            .str(&self.to)
+            .u64(self.amount)
+            .u64(self.nonce)
+            .u64(self.fee)
+            .list(self.valid_after, |encoder, valid_after| {
+                encoder.u64(valid_after);
+            })
+            .as_bytes()
+            .to_vec()
+    }
+
+    /// Signs the transaction with the key derived from `seed`, filling in
+    /// `public_key` and `signature`
+    pub fn sign(&mut self, seed: &[u8]) -> Result<(), String> {
+        self.public_key = crypto::public_key_hex(seed)?;
+        self.signature = crypto::sign(seed, &self.signing_bytes())?;
+        Ok(())
+    }
+
+    /// True if `signature` verifies under `public_key` and that key's
+    /// address is the sender's
+    pub fn has_valid_signature(&self) -> bool {
+        crypto::address_from_public_key(&self.public_key).as_deref() == Some(self.from.as_str())
+            && crypto::verify(&self.public_key, &self.signing_bytes(), &self.signature)
+    }
+
+    /// True once the transaction's time lock, if any, has passed at `now`
+    pub fn is_unlocked_at(&self, now: u64) -> bool {
+        match self.valid_after {
+            Some(valid_after) => valid_after <= now,
+            None => true,
+        }
+    }
+
+    /// Sum of the fees of the transactions encoded in a block. Entries that
+    /// are not transactions carry no fee.
+    pub fn total_fees(entries: &[String]) -> u64 {
+        entries.iter()
+            .filter_map(|entry| serde_json::from_str::<Transaction>(entry).ok())
+            .map(|tx| tx.fee)
+            .sum()
+    }
+}
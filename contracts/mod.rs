@@ -0,0 +1,2 @@
+// contracts/mod.rs
+pub mod sdk;
@@ -1,12 +1,111 @@
 // contracts/sdk/mod.rs
 // Nyxora Smart Contract SDK
 
-use std::collections::HashMap;
-use serde::{Deserialize, Serialize};
+pub mod abi {
+    use std::collections::BTreeMap;
+    use std::fmt;
+
+    /// Type of a contract function parameter. Arguments arrive as strings and
+    /// are checked against these before the function body runs.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ParamType {
+        String,
+        U64,
+        Bytes, // `0x`-prefixed hex
+    }
+
+    impl fmt::Display for ParamType {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            match self {
+                ParamType::String => write!(f, "string"),
+                ParamType::U64 => write!(f, "u64"),
+                ParamType::Bytes => write!(f, "bytes"),
+            }
+        }
+    }
+
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub enum ArgValue {
+        String(String),
+        U64(u64),
+        Bytes(Vec<u8>),
+    }
+
+    /// Names and types of a function's parameters, in call order
+    #[derive(Debug, Clone, Copy)]
+    pub struct FunctionAbi {
+        pub name: &'static str,
+        pub params: &'static [(&'static str, ParamType)],
+    }
+
+    /// Arguments converted to their declared types, looked up by name
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct DecodedArgs {
+        pub values: BTreeMap<String, ArgValue>,
+    }
+
+    impl FunctionAbi {
+        /// Checks the argument count and converts each argument to its
+        /// parameter's type, rejecting any that don't parse.
+        pub fn decode(&self, args: Vec<String>) -> Result<DecodedArgs, String> {
+            if args.len() < self.params.len() {
+                return Err(format!("Insufficient arguments for {}", self.name));
+            }
+            if args.len() > self.params.len() {
+                return Err(format!("Too many arguments for {}", self.name));
+            }
+
+            let mut values = BTreeMap::new();
+            for (&(name, ty), raw) in self.params.iter().zip(args) {
+                let value = match ty {
+                    ParamType::String => Some(ArgValue::String(raw.clone())),
+                    ParamType::U64 => raw.parse().ok().map(ArgValue::U64),
+                    ParamType::Bytes => raw.strip_prefix("0x")
+                        .and_then(|digits| hex::decode(digits).ok())
+                        .map(ArgValue::Bytes),
+                };
+                let value = value.ok_or_else(|| format!(
+                    "argument type mismatch: {} expects {} for '{}', got '{}'",
+                    self.name, ty, name, raw
+                ))?;
+                values.insert(name.to_string(), value);
+            }
+            Ok(DecodedArgs { values })
+        }
+    }
+
+    impl DecodedArgs {
+        pub fn string(&self, name: &str) -> Result<&str, String> {
+            match self.values.get(name) {
+                Some(ArgValue::String(value)) => Ok(value),
+                _ => Err(Self::mismatch(name, ParamType::String)),
+            }
+        }
+
+        pub fn u64(&self, name: &str) -> Result<u64, String> {
+            match self.values.get(name) {
+                Some(ArgValue::U64(value)) => Ok(*value),
+                _ => Err(Self::mismatch(name, ParamType::U64)),
+            }
+        }
+
+        pub fn bytes(&self, name: &str) -> Result<&[u8], String> {
+            match self.values.get(name) {
+                Some(ArgValue::Bytes(value)) => Ok(value),
+                _ => Err(Self::mismatch(name, ParamType::Bytes)),
+            }
+        }
+
+        fn mismatch(name: &str, ty: ParamType) -> String {
+            format!("argument type mismatch: '{}' was not decoded as {}", name, ty)
+        }
+    }
+}
 
 pub mod contract_runtime {
-    use std::collections::HashMap;
+    use std::collections::{BTreeMap, HashMap};
     use serde::{Deserialize, Serialize};
+    use super::abi::{FunctionAbi, ParamType};
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
     pub struct Contract {
@@ -14,7 +113,13 @@ pub mod contract_runtime {
         pub code: String,
         pub creator: String,
         pub timestamp: u64,
-        pub state: HashMap<String, String>,
+        pub state: BTreeMap<String, String>, // Ordered so execution and serialization are deterministic
+        #[serde(default = "initial_version")]
+        pub version: u64, // 1 when deployed, bumped by every upgrade
+    }
+
+    fn initial_version() -> u64 {
+        1
     }
 
     #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -22,12 +127,103 @@ pub mod contract_runtime {
         pub success: bool,
         pub gas_used: u64,
         pub output: String,
-        pub state_changes: HashMap<String, String>,
+        pub state_changes: BTreeMap<String, String>,
+        #[serde(default)]
+        pub state_size: usize, // Entries in the contract's state after the call
+        #[serde(default)]
+        pub events: Vec<ContractEvent>, // Emitted only by successful calls
+    }
+
+    /// Log entry emitted by a contract call for off-chain indexers
+    #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+    pub struct ContractEvent {
+        pub name: String,
+        pub data: BTreeMap<String, String>, // Ordered like contract state, so results serialize deterministically
+    }
+
+    impl ContractEvent {
+        pub fn new(name: &str, data: &[(&str, String)]) -> Self {
+            ContractEvent {
+                name: name.to_string(),
+                data: data.iter().map(|(key, value)| (key.to_string(), value.clone())).collect(),
+            }
+        }
     }
 
+    /// Default cap on the number of state entries a single contract may hold
+    pub const DEFAULT_MAX_STATE_ENTRIES: usize = 1024;
+
+    /// Gas charged for every state entry a call writes
+    pub const STATE_ENTRY_GAS: u64 = 20;
+
+    /// Gas charged per byte of key and value written to state
+    pub const STATE_BYTE_GAS: u64 = 1;
+
+    /// Deepest chain of nested contract calls a single execution may make
+    pub const MAX_CALL_DEPTH: usize = 8;
+
+    /// Contract `random_nft` asks for randomness when minting
+    pub const RANDOMNESS_CONTRACT: &str = "quantum_randomness";
+
+    pub const MINT_NFT_ABI: FunctionAbi = FunctionAbi {
+        name: "mintNFT",
+        params: &[("tokenURI", ParamType::String), ("quantumProofHash", ParamType::Bytes)],
+    };
+
+    pub const FULFILL_RANDOMNESS_ABI: FunctionAbi = FunctionAbi {
+        name: "fulfillRandomness",
+        params: &[("requestId", ParamType::U64), ("randomValue", ParamType::String), ("quantumProof", ParamType::Bytes)],
+    };
+
     pub struct ContractRuntime {
         pub contracts: HashMap<String, Contract>,
         pub gas_limit: u64,
+        pub max_state_entries: usize,
+    }
+
+    /// Handed to contract functions that call other contracts. Nested calls
+    /// charge the caller's gas meter, and their writes are rolled back if the
+    /// outermost call fails.
+    pub struct CallContext<'a> {
+        runtime: &'a mut ContractRuntime,
+        depth: usize,
+        // State of every called contract as it was before its first nested call
+        snapshots: &'a mut BTreeMap<String, BTreeMap<String, String>>,
+    }
+
+    impl CallContext<'_> {
+        pub fn call(&mut self, gas: &mut GasMeter, id: &str, function: &str, args: Vec<String>) -> Result<ContractExecutionResult, String> {
+            if self.depth >= MAX_CALL_DEPTH {
+                return Err(format!("call depth limit of {} reached", MAX_CALL_DEPTH));
+            }
+            let contract = self.runtime.contracts.get(id)
+                .ok_or_else(|| format!("Contract '{}' not found", id))?;
+            self.snapshots.entry(id.to_string()).or_insert_with(|| contract.state.clone());
+
+            self.runtime.run(id, function, args, gas, self.depth + 1, self.snapshots)
+                .map_err(|e| format!("call to '{}' failed: {}", id, e))
+        }
+    }
+
+    /// Per-execution gas budget charged by contract functions
+    pub struct GasMeter {
+        pub limit: u64,
+        pub used: u64,
+    }
+
+    impl GasMeter {
+        pub fn new(limit: u64) -> Self {
+            GasMeter { limit, used: 0 }
+        }
+
+        pub fn charge(&mut self, amount: u64) -> Result<(), String> {
+            let total = self.used.saturating_add(amount);
+            if total > self.limit {
+                return Err("out of gas".to_string());
+            }
+            self.used = total;
+            Ok(())
+        }
     }
 
     impl ContractRuntime {
@@ -35,6 +231,7 @@ pub mod contract_runtime {
             ContractRuntime {
                 contracts: HashMap::new(),
                 gas_limit,
+                max_state_entries: DEFAULT_MAX_STATE_ENTRIES,
             }
         }
 
@@ -51,67 +248,221 @@ pub mod contract_runtime {
                     .duration_since(std::time::UNIX_EPOCH)
                     .unwrap()
                     .as_secs(),
-                state: HashMap::new(),
+                state: BTreeMap::new(),
+                version: initial_version(),
             };
 
             self.contracts.insert(id, contract);
             Ok(())
         }
 
-        pub fn execute_contract(&mut self, id: &str, function: &str, args: Vec<String>) -> Result<ContractExecutionResult, String> {
+        /// Replaces the code of contract `id`, keeping its state, and bumps its
+        /// version. Only the contract's creator may upgrade it.
+        pub fn upgrade_contract(&mut self, id: &str, new_code: String, caller: &str) -> Result<(), String> {
             let contract = self.contracts.get_mut(id)
-                .ok_or("Contract not found")?;
+                .ok_or_else(|| "Contract not found".to_string())?;
+            if contract.creator != caller {
+                return Err(format!("Only the creator {} can upgrade contract '{}'", contract.creator, id));
+            }
+
+            contract.code = new_code;
+            contract.version += 1;
+            Ok(())
+        }
+
+        /// Returns every state entry of contract `id` whose key starts with
+        /// `prefix`, sorted by key. Unknown contracts have no state.
+        pub fn query_state_prefix(&self, id: &str, prefix: &str) -> Vec<(String, String)> {
+            match self.contracts.get(id) {
+                Some(contract) => contract.state.range(prefix.to_string()..)
+                    .take_while(|(key, _)| key.starts_with(prefix))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect(),
+                None => Vec::new(),
+            }
+        }
+
+        pub fn execute_contract(&mut self, id: &str, function: &str, args: Vec<String>) -> Result<ContractExecutionResult, String> {
+            if !self.contracts.contains_key(id) {
+                return Err("Contract not found".to_string());
+            }
+
+            let mut gas = GasMeter::new(self.gas_limit);
+            let mut snapshots = BTreeMap::new();
+            match self.run(id, function, args, &mut gas, 0, &mut snapshots) {
+                Ok(exec_result) => Ok(exec_result),
+                Err(e) => {
+                    // Undo whatever nested calls wrote before the failure
+                    for (callee, state) in snapshots {
+                        if let Some(contract) = self.contracts.get_mut(&callee) {
+                            contract.state = state;
+                        }
+                    }
+                    Ok(ContractExecutionResult {
+                        success: false,
+                        gas_used: gas.used,
+                        output: e,
+                        state_changes: BTreeMap::new(),
+                        state_size: self.contracts[id].state.len(),
+                        events: Vec::new(),
+                    })
+                },
+            }
+        }
+
+        // Runs one call at `depth` and applies its writes. Errors are failed
+        // calls; only the outermost call turns them into a failed result.
+        fn run(
+            &mut self,
+            id: &str,
+            function: &str,
+            args: Vec<String>,
+            gas: &mut GasMeter,
+            depth: usize,
+            snapshots: &mut BTreeMap<String, BTreeMap<String, String>>,
+        ) -> Result<ContractExecutionResult, String> {
+            // Functions see the contract as it was when the call started
+            let contract = self.contracts.get(id).ok_or("Contract not found")?.clone();
 
             // In a real implementation, this would parse and execute the contract code
             // For now, we'll simulate execution based on the contract ID
-            let result = match contract.id.as_str() {
-                "quantum_randomness" => self.execute_quantum_randomness(contract, function, args),
-                "quantum_nft" => self.execute_quantum_nft(contract, function, args),
+            let mut exec_result = match contract.id.as_str() {
+                "quantum_randomness" => Self::execute_quantum_randomness(&contract, function, args, gas),
+                "quantum_nft" => Self::execute_quantum_nft(&contract, function, args, gas),
+                "kv_store" => Self::execute_kv_store(&contract, function, args, gas),
+                "random_nft" => {
+                    let mut calls = CallContext { runtime: self, depth, snapshots };
+                    Self::execute_random_nft(&contract, function, args, gas, &mut calls)
+                },
                 _ => Err("Unknown contract type".to_string()),
+            }?;
+
+            // Functions only describe their writes; they are applied here once
+            // they fit under the state cap and have been paid for
+            let contract = self.contracts.get_mut(id).ok_or("Contract not found")?;
+            Self::apply_state_changes(contract, &exec_result.state_changes, self.max_state_entries, gas)?;
+            exec_result.gas_used = gas.used;
+            exec_result.state_size = contract.state.len();
+            Ok(exec_result)
+        }
+
+        /// Runs a call against copies of the contracts and returns what it
+        /// would do, including its `state_changes`, without committing
+        /// anything. Like `eth_call`, nothing in the runtime is mutated.
+        pub fn simulate_contract(&self, id: &str, function: &str, args: Vec<String>) -> Result<ContractExecutionResult, String> {
+            if !self.contracts.contains_key(id) {
+                return Err("Contract not found".to_string());
+            }
+
+            // Every contract is copied, since the call may reach into others
+            let mut scratch = ContractRuntime::new(self.gas_limit);
+            scratch.max_state_entries = self.max_state_entries;
+            scratch.contracts = self.contracts.clone();
+            scratch.execute_contract(id, function, args)
+        }
+
+        /// Gas a call would use, from a dry run through `simulate_contract`.
+        /// A call that would fail is an error rather than an estimate, so an
+        /// unknown function never reads as free.
+        pub fn estimate_gas(&self, id: &str, function: &str, args: Vec<String>) -> Result<u64, String> {
+            let result = self.simulate_contract(id, function, args)?;
+            if result.success {
+                Ok(result.gas_used)
+            } else {
+                Err(result.output)
+            }
+        }
+
+        /// Gas for writing `changes`, which grows with both the number of
+        /// entries and their size.
+        pub fn state_write_gas(changes: &BTreeMap<String, String>) -> u64 {
+            changes.iter()
+                .map(|(key, value)| STATE_ENTRY_GAS + STATE_BYTE_GAS * (key.len() + value.len()) as u64)
+                .sum()
+        }
+
+        fn apply_state_changes(contract: &mut Contract, changes: &BTreeMap<String, String>, max_entries: usize, gas: &mut GasMeter) -> Result<(), String> {
+            let new_entries = changes.keys().filter(|key| !contract.state.contains_key(*key)).count();
+            if contract.state.len() + new_entries > max_entries {
+                return Err(format!("state entry limit of {} reached", max_entries));
+            }
+
+            gas.charge(Self::state_write_gas(changes))?;
+            contract.state.extend(changes.clone());
+            Ok(())
+        }
+
+        /// Runs the same call on two independent copies of the contracts, one
+        /// on another thread, and fails if their results or resulting state
+        /// differ. The runtime itself is left untouched.
+        pub fn verify_deterministic(&self, id: &str, function: &str, args: Vec<String>) -> Result<ContractExecutionResult, String> {
+            if !self.contracts.contains_key(id) {
+                return Err("Contract not found".to_string());
+            }
+            let contracts = self.contracts.clone();
+            let (gas_limit, max_state_entries) = (self.gas_limit, self.max_state_entries);
+
+            // Compares every contract's state, since nested calls may write to others
+            let run = move |contracts: HashMap<String, Contract>, id: String, function: String, args: Vec<String>| {
+                let mut runtime = ContractRuntime::new(gas_limit);
+                runtime.max_state_entries = max_state_entries;
+                runtime.contracts = contracts;
+                let result = runtime.execute_contract(&id, &function, args)?;
+                let state: BTreeMap<String, BTreeMap<String, String>> = runtime.contracts.into_iter()
+                    .map(|(id, contract)| (id, contract.state))
+                    .collect();
+                Ok::<_, String>((result, state))
             };
 
-            match result {
-                Ok(exec_result) => Ok(exec_result),
-                Err(e) => Ok(ContractExecutionResult {
-                    success: false,
-                    gas_used: 0,
-                    output: e,
-                    state_changes: HashMap::new(),
-                }),
+            let (thread_contracts, thread_id, thread_function, thread_args) =
+                (contracts.clone(), id.to_string(), function.to_string(), args.clone());
+            let handle = std::thread::spawn(move || run(thread_contracts, thread_id, thread_function, thread_args));
+
+            let (result, state) = run(contracts, id.to_string(), function.to_string(), args)?;
+            let (other_result, other_state) = handle.join()
+                .map_err(|_| "Contract execution panicked".to_string())??;
+
+            let encode = |result: &ContractExecutionResult| serde_json::to_vec(result).map_err(|e| e.to_string());
+            if encode(&result)? != encode(&other_result)? || state != other_state {
+                return Err(format!("Non-deterministic execution of contract '{}' function '{}'", id, function));
             }
+            Ok(result)
         }
 
-        fn execute_quantum_randomness(&self, contract: &mut Contract, function: &str, args: Vec<String>) -> Result<ContractExecutionResult, String> {
+        fn execute_quantum_randomness(contract: &Contract, function: &str, args: Vec<String>, gas: &mut GasMeter) -> Result<ContractExecutionResult, String> {
             match function {
                 "requestRandomness" => {
+                    gas.charge(100)?;
+
                     // Simulate requesting randomness
                     let request_id = contract.state.get("requestCount")
                         .unwrap_or(&"0".to_string())
                         .parse::<u64>()
                         .unwrap_or(0);
 
-                    contract.state.insert("requestCount".to_string(), (request_id + 1).to_string());
-
                     Ok(ContractExecutionResult {
                         success: true,
-                        gas_used: 100,
+                        gas_used: gas.used,
                         output: format!("{{\"requestId\": {}}}", request_id),
                         state_changes: {
-                            let mut changes = HashMap::new();
+                            let mut changes = BTreeMap::new();
                             changes.insert("requestCount".to_string(), (request_id + 1).to_string());
+                            // Each request is tracked until it is fulfilled
+                            changes.insert(format!("request_{}", request_id), "pending".to_string());
                             changes
                         },
+                        state_size: 0,
+                        events: Vec::new(),
                     })
                 },
                 "fulfillRandomness" => {
-                    // Simulate fulfilling randomness with quantum proof
-                    if args.len() < 3 {
-                        return Err("Insufficient arguments for fulfillRandomness".to_string());
-                    }
+                    gas.charge(200)?;
 
-                    let request_id = &args[0];
-                    let random_value = &args[1];
-                    let quantum_proof = &args[2];
+                    // Simulate fulfilling randomness with quantum proof
+                    let args = FULFILL_RANDOMNESS_ABI.decode(args)?;
+                    let request_id = args.u64("requestId")?;
+                    let random_value = args.string("randomValue")?;
+                    let quantum_proof = args.bytes("quantumProof")?;
 
                     // In a real implementation, verify the quantum proof here
                     // For now, just check it's not empty
@@ -119,36 +470,38 @@ pub mod contract_runtime {
                         return Err("Invalid quantum proof".to_string());
                     }
 
-                    // Store the randomness
-                    contract.state.insert(format!("randomness_{}", request_id), random_value.clone());
-
                     Ok(ContractExecutionResult {
                         success: true,
-                        gas_used: 200,
+                        gas_used: gas.used,
                         output: format!("{{\"success\": true, \"requestId\": {}}}", request_id),
                         state_changes: {
-                            let mut changes = HashMap::new();
-                            changes.insert(format!("randomness_{}", request_id), random_value.clone());
+                            let mut changes = BTreeMap::new();
+                            changes.insert(format!("randomness_{}", request_id), random_value.to_string());
+                            changes.insert(format!("request_{}", request_id), "fulfilled".to_string());
                             changes
                         },
+                        state_size: 0,
+                        events: vec![ContractEvent::new("RandomnessFulfilled", &[
+                            ("requestId", request_id.to_string()),
+                            ("randomValue", random_value.to_string()),
+                        ])],
                     })
                 },
                 _ => Err(format!("Function '{}' not found in quantum randomness contract", function)),
             }
         }
 
-        fn execute_quantum_nft(&self, contract: &mut Contract, function: &str, args: Vec<String>) -> Result<ContractExecutionResult, String> {
+        fn execute_quantum_nft(contract: &Contract, function: &str, args: Vec<String>, gas: &mut GasMeter) -> Result<ContractExecutionResult, String> {
             match function {
                 "mintNFT" => {
-                    if args.len() < 2 {
-                        return Err("Insufficient arguments for mintNFT".to_string());
-                    }
+                    gas.charge(150)?;
 
-                    let token_uri = &args[0];
-                    let quantum_proof_hash = &args[1];
+                    let args = MINT_NFT_ABI.decode(args)?;
+                    let token_uri = args.string("tokenURI")?;
+                    let quantum_proof_hash = args.bytes("quantumProofHash")?;
 
                     // Validate quantum proof hash
-                    if quantum_proof_hash.is_empty() || quantum_proof_hash == "0" {
+                    if quantum_proof_hash.iter().all(|&byte| byte == 0) {
                         return Err("Invalid quantum proof hash".to_string());
                     }
 
@@ -158,27 +511,123 @@ pub mod contract_runtime {
                         .parse::<u64>()
                         .unwrap_or(0);
 
-                    // Update state
-                    contract.state.insert("tokenCount".to_string(), (token_id + 1).to_string());
-                    contract.state.insert(format!("tokenURI_{}", token_id), token_uri.clone());
-                    contract.state.insert(format!("quantumProofHash_{}", token_id), quantum_proof_hash.clone());
-
                     Ok(ContractExecutionResult {
                         success: true,
-                        gas_used: 150,
+                        gas_used: gas.used,
                         output: format!("{{\"tokenId\": {}}}", token_id),
                         state_changes: {
-                            let mut changes = HashMap::new();
+                            let mut changes = BTreeMap::new();
                             changes.insert("tokenCount".to_string(), (token_id + 1).to_string());
-                            changes.insert(format!("tokenURI_{}", token_id), token_uri.clone());
-                            changes.insert(format!("quantumProofHash_{}", token_id), quantum_proof_hash.clone());
+                            changes.insert(format!("tokenURI_{}", token_id), token_uri.to_string());
+                            changes.insert(format!("quantumProofHash_{}", token_id), format!("0x{}", hex::encode(quantum_proof_hash)));
                             changes
                         },
+                        state_size: 0,
+                        // Calls carry no sender yet, so new tokens go to the contract's creator
+                        events: vec![ContractEvent::new("Transfer", &[
+                            ("from", "0".to_string()),
+                            ("to", contract.creator.clone()),
+                            ("tokenId", token_id.to_string()),
+                        ])],
                     })
                 },
                 _ => Err(format!("Function '{}' not found in quantum NFT contract", function)),
             }
         }
+
+        fn execute_random_nft(contract: &Contract, function: &str, args: Vec<String>, gas: &mut GasMeter, calls: &mut CallContext) -> Result<ContractExecutionResult, String> {
+            match function {
+                "mintNFT" => {
+                    gas.charge(150)?;
+
+                    if args.is_empty() {
+                        return Err("Insufficient arguments for mintNFT".to_string());
+                    }
+                    let token_uri = &args[0];
+
+                    // Each token's traits come from its own randomness request,
+                    // paid for out of this call's gas
+                    let request = calls.call(gas, RANDOMNESS_CONTRACT, "requestRandomness", vec![])?;
+                    let request_id = serde_json::from_str::<serde_json::Value>(&request.output).ok()
+                        .and_then(|output| output["requestId"].as_u64())
+                        .ok_or("Randomness contract returned no request ID")?;
+
+                    let token_id = contract.state.get("tokenCount")
+                        .unwrap_or(&"0".to_string())
+                        .parse::<u64>()
+                        .unwrap_or(0);
+
+                    Ok(ContractExecutionResult {
+                        success: true,
+                        gas_used: gas.used,
+                        output: format!("{{\"tokenId\": {}, \"requestId\": {}}}", token_id, request_id),
+                        state_changes: {
+                            let mut changes = BTreeMap::new();
+                            changes.insert("tokenCount".to_string(), (token_id + 1).to_string());
+                            changes.insert(format!("tokenURI_{}", token_id), token_uri.clone());
+                            changes.insert(format!("randomnessRequest_{}", token_id), request_id.to_string());
+                            changes
+                        },
+                        state_size: 0,
+                        events: vec![ContractEvent::new("Transfer", &[
+                            ("from", "0".to_string()),
+                            ("to", contract.creator.clone()),
+                            ("tokenId", token_id.to_string()),
+                        ])],
+                    })
+                },
+                _ => Err(format!("Function '{}' not found in random NFT contract", function)),
+            }
+        }
+
+        fn execute_kv_store(contract: &Contract, function: &str, args: Vec<String>, gas: &mut GasMeter) -> Result<ContractExecutionResult, String> {
+            match function {
+                "set" => {
+                    gas.charge(50)?;
+
+                    if args.len() < 2 {
+                        return Err("Insufficient arguments for set".to_string());
+                    }
+
+                    let key = &args[0];
+                    let value = &args[1];
+
+                    Ok(ContractExecutionResult {
+                        success: true,
+                        gas_used: gas.used,
+                        output: serde_json::json!({ "success": true, "key": key }).to_string(),
+                        state_changes: {
+                            let mut changes = BTreeMap::new();
+                            changes.insert(key.clone(), value.clone());
+                            changes
+                        },
+                        state_size: 0,
+                        events: Vec::new(),
+                    })
+                },
+                "get" => {
+                    gas.charge(20)?;
+
+                    if args.is_empty() {
+                        return Err("Insufficient arguments for get".to_string());
+                    }
+
+                    let key = &args[0];
+                    // Missing keys read as an empty string
+                    let value = contract.state.get(key).cloned().unwrap_or_default();
+
+                    Ok(ContractExecutionResult {
+                        success: true,
+                        gas_used: gas.used,
+                        output: serde_json::json!({ "key": key, "value": value }).to_string(),
+                        state_changes: BTreeMap::new(),
+                        state_size: 0,
+                        events: Vec::new(),
+                    })
+                },
+                _ => Err(format!("Function '{}' not found in kv_store contract", function)),
+            }
+        }
     }
 
     #[cfg(test)]
@@ -234,7 +683,7 @@ pub mod contract_runtime {
             let result = runtime.execute_contract(
                 "quantum_nft",
                 "mintNFT",
-                vec!["ipfs://nft-metadata".to_string(), "0xquantumproofhash".to_string()]
+                vec!["ipfs://nft-metadata".to_string(), "0x9e3779b97f4a7c15".to_string()]
             ).unwrap();
 
             assert!(result.success);
@@ -243,244 +692,385 @@ pub mod contract_runtime {
             let token_id = output["tokenId"].as_u64().unwrap();
             assert_eq!(token_id, 0);
         }
-    }
-}
 
-pub use contract_runtime::*;
+        #[test]
+        fn test_simulate_contract_does_not_commit_state() {
+            let mut runtime = ContractRuntime::new(10000);
+            runtime.deploy_contract("quantum_nft".to_string(), String::new(), "Qvalidator123".to_string()).unwrap();
+            let args = vec!["ipfs://nft-metadata".to_string(), "0x9e3779b97f4a7c15".to_string()];
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct Contract {
-    pub id: String,
-    pub code: String,
-    pub creator: String,
-    pub timestamp: u64,
-    pub state: HashMap<String, String>,
-}
+            for _ in 0..2 {
+                let result = runtime.simulate_contract("quantum_nft", "mintNFT", args.clone()).unwrap();
+                assert!(result.success);
+                assert_eq!(result.output, "{\"tokenId\": 0}");
+                assert_eq!(result.state_changes.get("tokenURI_0").map(String::as_str), Some("ipfs://nft-metadata"));
+            }
+            assert!(runtime.query_state_prefix("quantum_nft", "").is_empty());
+            assert!(runtime.simulate_contract("missing", "mintNFT", args).is_err());
+        }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-pub struct ContractExecutionResult {
-    pub success: bool,
-    pub gas_used: u64,
-    pub output: String,
-    pub state_changes: HashMap<String, String>,
-}
+        #[test]
+        fn test_abi_decodes_typed_arguments() {
+            let args = FULFILL_RANDOMNESS_ABI
+                .decode(vec!["7".to_string(), "42".to_string(), "0xC0ffee".to_string()])
+                .unwrap();
+            assert_eq!(args.u64("requestId"), Ok(7));
+            assert_eq!(args.string("randomValue"), Ok("42"));
+            assert_eq!(args.bytes("quantumProof"), Ok(&[0xc0, 0xff, 0xee][..]));
+            assert!(args.u64("randomValue").is_err());
 
-pub struct ContractRuntime {
-    pub contracts: HashMap<String, Contract>,
-    pub gas_limit: u64,
-}
+            assert_eq!(
+                MINT_NFT_ABI.decode(vec!["ipfs://nft".to_string()]),
+                Err("Insufficient arguments for mintNFT".to_string())
+            );
+        }
+
+        #[test]
+        fn test_argument_type_mismatch_is_rejected() {
+            let mut runtime = ContractRuntime::new(10000);
+            runtime.deploy_contract("quantum_randomness".to_string(), String::new(), "Qcreator".to_string()).unwrap();
+            runtime.deploy_contract("quantum_nft".to_string(), String::new(), "Qcreator".to_string()).unwrap();
 
-impl ContractRuntime {
-    pub fn new(gas_limit: u64) -> Self {
-        ContractRuntime {
-            contracts: HashMap::new(),
-            gas_limit,
+            let result = runtime.execute_contract(
+                "quantum_randomness",
+                "fulfillRandomness",
+                vec!["first".to_string(), "42".to_string(), "0xc0ffee".to_string()]
+            ).unwrap();
+            assert!(!result.success);
+            assert_eq!(result.output, "argument type mismatch: fulfillRandomness expects u64 for 'requestId', got 'first'");
+
+            // Proof hashes must be hex, not free text
+            let result = runtime.execute_contract("quantum_nft", "mintNFT", vec!["ipfs://nft".to_string(), "0xproof".to_string()]).unwrap();
+            assert!(!result.success);
+            assert_eq!(result.output, "argument type mismatch: mintNFT expects bytes for 'quantumProofHash', got '0xproof'");
+            assert!(runtime.query_state_prefix("quantum_nft", "").is_empty());
         }
-    }
 
-    pub fn deploy_contract(&mut self, id: String, code: String, creator: String) -> Result<(), String> {
-        if self.contracts.contains_key(&id) {
-            return Err("Contract with this ID already exists".to_string());
+        #[test]
+        fn test_estimate_gas_matches_execution() {
+            let mut runtime = ContractRuntime::new(10000);
+            runtime.deploy_contract("quantum_nft".to_string(), String::new(), "Qvalidator123".to_string()).unwrap();
+            let args = vec!["ipfs://nft".to_string(), "0xc0ffee".to_string()];
+
+            let estimate = runtime.estimate_gas("quantum_nft", "mintNFT", args.clone()).unwrap();
+            let executed = runtime.execute_contract("quantum_nft", "mintNFT", args.clone()).unwrap();
+            // mintNFT charges 150 on top of paying for its writes
+            assert_eq!(estimate, 150 + ContractRuntime::state_write_gas(&executed.state_changes));
+            assert_eq!(estimate, executed.gas_used);
+
+            let unknown = runtime.estimate_gas("quantum_nft", "burnNFT", args.clone());
+            assert_eq!(unknown, Err("Function 'burnNFT' not found in quantum NFT contract".to_string()));
+            assert!(runtime.estimate_gas("missing", "mintNFT", args).is_err());
         }
 
-        let contract = Contract {
-            id: id.clone(),
-            code,
-            creator,
-            timestamp: std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_secs(),
-            state: HashMap::new(),
-        };
+        #[test]
+        fn test_creator_upgrade_keeps_state() {
+            let mut runtime = ContractRuntime::new(10000);
+            runtime.deploy_contract("kv_store".to_string(), "contract KVStore {}".to_string(), "Qvalidator123".to_string()).unwrap();
+            runtime.execute_contract("kv_store", "set", vec!["greeting".to_string(), "hello".to_string()]).unwrap();
+            assert_eq!(runtime.contracts["kv_store"].version, 1);
 
-        self.contracts.insert(id, contract);
-        Ok(())
-    }
+            runtime.upgrade_contract("kv_store", "contract KVStoreV2 {}".to_string(), "Qvalidator123").unwrap();
 
-    pub fn execute_contract(&mut self, id: &str, function: &str, args: Vec<String>) -> Result<ContractExecutionResult, String> {
-        let contract = self.contracts.get_mut(id)
-            .ok_or("Contract not found")?;
-
-        // In a real implementation, this would parse and execute the contract code
-        // For now, we'll simulate execution based on the contract ID
-        let result = match contract.id.as_str() {
-            "quantum_randomness" => self.execute_quantum_randomness(contract, function, args),
-            "quantum_nft" => self.execute_quantum_nft(contract, function, args),
-            _ => Err("Unknown contract type".to_string()),
-        };
-
-        match result {
-            Ok(exec_result) => Ok(exec_result),
-            Err(e) => Ok(ContractExecutionResult {
-                success: false,
-                gas_used: 0,
-                output: e,
-                state_changes: HashMap::new(),
-            }),
+            let contract = &runtime.contracts["kv_store"];
+            assert_eq!(contract.code, "contract KVStoreV2 {}");
+            assert_eq!(contract.version, 2);
+            let get = runtime.execute_contract("kv_store", "get", vec!["greeting".to_string()]).unwrap();
+            let output: serde_json::Value = serde_json::from_str(&get.output).unwrap();
+            assert_eq!(output["value"], "hello");
         }
-    }
 
-    fn execute_quantum_randomness(&self, contract: &mut Contract, function: &str, args: Vec<String>) -> Result<ContractExecutionResult, String> {
-        match function {
-            "requestRandomness" => {
-                // Simulate requesting randomness
-                let request_id = contract.state.get("requestCount")
-                    .unwrap_or(&"0".to_string())
-                    .parse::<u64>()
-                    .unwrap_or(0);
-                
-                contract.state.insert("requestCount".to_string(), (request_id + 1).to_string());
-                
-                Ok(ContractExecutionResult {
-                    success: true,
-                    gas_used: 100,
-                    output: format!("{{\"requestId\": {}}}", request_id),
-                    state_changes: {
-                        let mut changes = HashMap::new();
-                        changes.insert("requestCount".to_string(), (request_id + 1).to_string());
-                        changes
-                    },
-                })
-            },
-            "fulfillRandomness" => {
-                // Simulate fulfilling randomness with quantum proof
-                if args.len() < 3 {
-                    return Err("Insufficient arguments for fulfillRandomness".to_string());
-                }
-                
-                let request_id = &args[0];
-                let random_value = &args[1];
-                let quantum_proof = &args[2];
-                
-                // In a real implementation, verify the quantum proof here
-                // For now, just check it's not empty
-                if quantum_proof.is_empty() {
-                    return Err("Invalid quantum proof".to_string());
-                }
-                
-                // Store the randomness
-                contract.state.insert(format!("randomness_{}", request_id), random_value.clone());
-                
-                Ok(ContractExecutionResult {
-                    success: true,
-                    gas_used: 200,
-                    output: format!("{{\"success\": true, \"requestId\": {}}}", request_id),
-                    state_changes: {
-                        let mut changes = HashMap::new();
-                        changes.insert(format!("randomness_{}", request_id), random_value.clone());
-                        changes
-                    },
-                })
-            },
-            _ => Err(format!("Function '{}' not found in quantum randomness contract", function)),
+        #[test]
+        fn test_upgrade_rejected_for_non_creator() {
+            let mut runtime = ContractRuntime::new(10000);
+            runtime.deploy_contract("kv_store".to_string(), "contract KVStore {}".to_string(), "Qvalidator123".to_string()).unwrap();
+
+            let err = runtime.upgrade_contract("kv_store", "contract Hijacked {}".to_string(), "Qmallory").unwrap_err();
+            assert_eq!(err, "Only the creator Qvalidator123 can upgrade contract 'kv_store'");
+            let contract = &runtime.contracts["kv_store"];
+            assert_eq!(contract.code, "contract KVStore {}");
+            assert_eq!(contract.version, 1);
+
+            assert!(runtime.upgrade_contract("missing", String::new(), "Qvalidator123").is_err());
         }
-    }
 
-    fn execute_quantum_nft(&self, contract: &mut Contract, function: &str, args: Vec<String>) -> Result<ContractExecutionResult, String> {
-        match function {
-            "mintNFT" => {
-                if args.len() < 2 {
-                    return Err("Insufficient arguments for mintNFT".to_string());
-                }
-                
-                let token_uri = &args[0];
-                let quantum_proof_hash = &args[1];
-                
-                // Validate quantum proof hash
-                if quantum_proof_hash.is_empty() || quantum_proof_hash == "0" {
-                    return Err("Invalid quantum proof hash".to_string());
+        #[test]
+        fn test_kv_store_set_then_get() {
+            let mut runtime = ContractRuntime::new(10000);
+
+            runtime.deploy_contract(
+                "kv_store".to_string(),
+                "contract KVStore {}".to_string(),
+                "Qvalidator123".to_string()
+            ).unwrap();
+
+            let set = runtime.execute_contract(
+                "kv_store",
+                "set",
+                vec!["greeting".to_string(), "hello \"nyx\"".to_string()]
+            ).unwrap();
+            assert!(set.success);
+            // Base cost plus one entry of "greeting" (8 bytes) and its value (11 bytes)
+            assert_eq!(set.gas_used, 50 + STATE_ENTRY_GAS + 19 * STATE_BYTE_GAS);
+            assert_eq!(set.state_size, 1);
+
+            let get = runtime.execute_contract("kv_store", "get", vec!["greeting".to_string()]).unwrap();
+            assert!(get.success);
+            assert_eq!(get.gas_used, 20);
+
+            let output: serde_json::Value = serde_json::from_str(&get.output).unwrap();
+            assert_eq!(output["value"], "hello \"nyx\"");
+        }
+
+        #[test]
+        fn test_kv_store_get_missing_key() {
+            let mut runtime = ContractRuntime::new(10000);
+
+            runtime.deploy_contract(
+                "kv_store".to_string(),
+                "contract KVStore {}".to_string(),
+                "Qvalidator123".to_string()
+            ).unwrap();
+
+            let get = runtime.execute_contract("kv_store", "get", vec!["missing".to_string()]).unwrap();
+            assert!(get.success);
+
+            let output: serde_json::Value = serde_json::from_str(&get.output).unwrap();
+            assert_eq!(output["value"], "");
+        }
+
+        #[test]
+        fn test_mint_nft_out_of_gas() {
+            let mut runtime = ContractRuntime::new(10000);
+
+            runtime.deploy_contract(
+                "quantum_nft".to_string(),
+                "contract QuantumNFT {}".to_string(),
+                "Qvalidator123".to_string()
+            ).unwrap();
+            runtime.gas_limit = 100;
+
+            // mintNFT charges 150 gas, more than the limit allows
+            let result = runtime.execute_contract(
+                "quantum_nft",
+                "mintNFT",
+                vec!["ipfs://nft-metadata".to_string(), "0x9e3779b97f4a7c15".to_string()]
+            ).unwrap();
+
+            assert!(!result.success);
+            assert_eq!(result.output, "out of gas");
+            assert!(!runtime.contracts["quantum_nft"].state.contains_key("tokenCount"));
+        }
+
+        #[test]
+        fn test_execution_is_byte_identical_across_runs() {
+            let calls: Vec<(&str, &str, Vec<String>)> = vec![
+                ("quantum_randomness", "requestRandomness", vec![]),
+                ("quantum_randomness", "fulfillRandomness", vec!["0".to_string(), "42".to_string(), "0xc0ffee".to_string()]),
+                ("quantum_nft", "mintNFT", vec!["ipfs://nft".to_string(), "0xc0ffee".to_string()]),
+                ("kv_store", "set", vec!["key".to_string(), "value".to_string()]),
+                ("kv_store", "get", vec!["key".to_string()]),
+            ];
+
+            let run_all = || {
+                let mut runtime = ContractRuntime::new(10000);
+                for id in ["quantum_randomness", "quantum_nft", "kv_store"] {
+                    runtime.deploy_contract(id.to_string(), String::new(), "Qvalidator123".to_string()).unwrap();
                 }
-                
-                // Generate new token ID
-                let token_id = contract.state.get("tokenCount")
-                    .unwrap_or(&"0".to_string())
-                    .parse::<u64>()
-                    .unwrap_or(0);
-                
-                // Update state
-                contract.state.insert("tokenCount".to_string(), (token_id + 1).to_string());
-                contract.state.insert(format!("tokenURI_{}", token_id), token_uri.clone());
-                contract.state.insert(format!("quantumProofHash_{}", token_id), quantum_proof_hash.clone());
-                
-                Ok(ContractExecutionResult {
-                    success: true,
-                    gas_used: 150,
-                    output: format!("{{\"tokenId\": {}}}", token_id),
-                    state_changes: {
-                        let mut changes = HashMap::new();
-                        changes.insert("tokenCount".to_string(), (token_id + 1).to_string());
-                        changes.insert(format!("tokenURI_{}", token_id), token_uri.clone());
-                        changes.insert(format!("quantumProofHash_{}", token_id), quantum_proof_hash.clone());
-                        changes
-                    },
-                })
-            },
-            _ => Err(format!("Function '{}' not found in quantum NFT contract", function)),
+                calls.iter()
+                    .map(|(id, function, args)| {
+                        runtime.verify_deterministic(id, function, args.clone()).unwrap();
+                        let result = runtime.execute_contract(id, function, args.clone()).unwrap();
+                        serde_json::to_vec(&result).unwrap()
+                    })
+                    .collect::<Vec<_>>()
+            };
+
+            assert_eq!(run_all(), run_all());
         }
-    }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-
-    #[test]
-    fn test_deploy_and_execute_quantum_randomness() {
-        let mut runtime = ContractRuntime::new(10000);
-        
-        // Deploy the contract
-        let code = r#"
-        contract QuantumRandomness {
-            // Contract code here
-        }
-        "#.to_string();
-        
-        runtime.deploy_contract(
-            "quantum_randomness".to_string(),
-            code,
-            "Qvalidator123".to_string()
-        ).unwrap();
-        
-        // Execute requestRandomness
-        let result = runtime.execute_contract(
-            "quantum_randomness",
-            "requestRandomness",
-            vec![]
-        ).unwrap();
-        
-        assert!(result.success);
-        assert_eq!(result.output, "{\"requestId\": 0}");
-    }
+        #[test]
+        fn test_state_entry_cap_rejects_further_requests() {
+            let mut runtime = ContractRuntime::new(10000);
+            runtime.max_state_entries = 5;
+            runtime.deploy_contract("quantum_randomness".to_string(), String::new(), "Qvalidator123".to_string()).unwrap();
+
+            // The first request writes requestCount and request_0, later ones one entry each
+            for expected_size in 2..=5 {
+                let result = runtime.execute_contract("quantum_randomness", "requestRandomness", vec![]).unwrap();
+                assert!(result.success);
+                assert_eq!(result.state_size, expected_size);
+            }
+
+            let result = runtime.execute_contract("quantum_randomness", "requestRandomness", vec![]).unwrap();
+            assert!(!result.success);
+            assert_eq!(result.output, "state entry limit of 5 reached");
+            assert_eq!(result.state_size, 5);
+            assert_eq!(runtime.contracts["quantum_randomness"].state["requestCount"], "4");
 
-    #[test]
-    fn test_deploy_and_execute_quantum_nft() {
-        let mut runtime = ContractRuntime::new(10000);
-        
-        // Deploy the contract
-        let code = r#"
-        contract QuantumNFT {
-            // Contract code here
-        }
-        "#.to_string();
-        
-        runtime.deploy_contract(
-            "quantum_nft".to_string(),
-            code,
-            "Qvalidator123".to_string()
-        ).unwrap();
-        
-        // Execute mintNFT
-        let result = runtime.execute_contract(
-            "quantum_nft",
-            "mintNFT",
-            vec!["ipfs://nft-metadata".to_string(), "0xquantumproofhash".to_string()]
-        ).unwrap();
-        
-        assert!(result.success);
-        // Parse the output to get the token ID
-        let output: serde_json::Value = serde_json::from_str(&result.output).unwrap();
-        let token_id = output["tokenId"].as_u64().unwrap();
-        assert_eq!(token_id, 0);
+            // Overwriting existing entries is still allowed at the cap
+            let fulfill = runtime.execute_contract(
+                "quantum_randomness",
+                "fulfillRandomness",
+                vec!["0".to_string(), "42".to_string(), "0xc0ffee".to_string()]
+            ).unwrap();
+            assert!(!fulfill.success, "randomness_0 would be a new entry");
+            runtime.max_state_entries = 6;
+            let fulfill = runtime.execute_contract(
+                "quantum_randomness",
+                "fulfillRandomness",
+                vec!["0".to_string(), "42".to_string(), "0xc0ffee".to_string()]
+            ).unwrap();
+            assert!(fulfill.success);
+            assert_eq!(runtime.contracts["quantum_randomness"].state["request_0"], "fulfilled");
+        }
+
+        #[test]
+        fn test_mint_nft_emits_transfer_event() {
+            let mut runtime = ContractRuntime::new(10000);
+            runtime.deploy_contract("quantum_nft".to_string(), String::new(), "Qcreator".to_string()).unwrap();
+            let mint = |runtime: &mut ContractRuntime| {
+                runtime.execute_contract("quantum_nft", "mintNFT", vec!["ipfs://nft".to_string(), "0xc0ffee".to_string()]).unwrap()
+            };
+
+            mint(&mut runtime);
+            let result = mint(&mut runtime);
+            assert_eq!(result.events.len(), 1);
+            let event = &result.events[0];
+            assert_eq!(event.name, "Transfer");
+            assert_eq!(event.data["tokenId"], "1");
+            assert_eq!(event.data["to"], "Qcreator");
+
+            // Failed calls emit nothing
+            let failed = runtime.execute_contract("quantum_nft", "mintNFT", vec!["ipfs://nft".to_string(), "0".to_string()]).unwrap();
+            assert!(!failed.success);
+            assert!(failed.events.is_empty());
+
+            runtime.deploy_contract("quantum_randomness".to_string(), String::new(), "Qcreator".to_string()).unwrap();
+            runtime.execute_contract("quantum_randomness", "requestRandomness", vec![]).unwrap();
+            let fulfilled = runtime.execute_contract(
+                "quantum_randomness",
+                "fulfillRandomness",
+                vec!["0".to_string(), "42".to_string(), "0xc0ffee".to_string()]
+            ).unwrap();
+            assert_eq!(fulfilled.events, vec![ContractEvent::new("RandomnessFulfilled", &[
+                ("requestId", "0".to_string()),
+                ("randomValue", "42".to_string()),
+            ])]);
+        }
+
+        #[test]
+        fn test_gas_grows_with_nft_metadata_size() {
+            let mut runtime = ContractRuntime::new(100_000);
+            runtime.deploy_contract("quantum_nft".to_string(), String::new(), "Qvalidator123".to_string()).unwrap();
+
+            let mint = |runtime: &mut ContractRuntime, uri: String| {
+                runtime.execute_contract("quantum_nft", "mintNFT", vec![uri, "0xc0ffee".to_string()]).unwrap()
+            };
+
+            let small = mint(&mut runtime, format!("ipfs://{}", "a".repeat(10)));
+            let large = mint(&mut runtime, format!("ipfs://{}", "a".repeat(1010)));
+            assert!(small.success && large.success);
+            assert_eq!(large.gas_used - small.gas_used, 1000 * STATE_BYTE_GAS);
+            assert_eq!(small.gas_used, 150 + ContractRuntime::state_write_gas(&small.state_changes));
+
+            // Metadata too large for the gas limit is rejected without writing state
+            runtime.gas_limit = 1000;
+            let rejected = mint(&mut runtime, "a".repeat(2000));
+            assert!(!rejected.success);
+            assert_eq!(rejected.output, "out of gas");
+            assert_eq!(runtime.contracts["quantum_nft"].state["tokenCount"], "2");
+        }
+
+        fn random_nft_runtime(gas_limit: u64) -> ContractRuntime {
+            let mut runtime = ContractRuntime::new(gas_limit);
+            for id in ["random_nft", RANDOMNESS_CONTRACT] {
+                runtime.deploy_contract(id.to_string(), String::new(), "Qvalidator123".to_string()).unwrap();
+            }
+            runtime
+        }
+
+        #[test]
+        fn test_random_nft_calls_quantum_randomness() {
+            let mut runtime = random_nft_runtime(10000);
+
+            for token_id in 0..2 {
+                let result = runtime.execute_contract("random_nft", "mintNFT", vec!["ipfs://x".to_string()]).unwrap();
+                assert!(result.success, "{}", result.output);
+                assert_eq!(result.output, format!("{{\"tokenId\": {0}, \"requestId\": {0}}}", token_id));
+                // The nested call's gas is charged to the caller
+                assert!(result.gas_used > 150 + 100);
+            }
+
+            assert_eq!(runtime.query_state_prefix("random_nft", "randomnessRequest_"), vec![
+                ("randomnessRequest_0".to_string(), "0".to_string()),
+                ("randomnessRequest_1".to_string(), "1".to_string()),
+            ]);
+            assert_eq!(runtime.query_state_prefix(RANDOMNESS_CONTRACT, "requestCount"), vec![
+                ("requestCount".to_string(), "2".to_string()),
+            ]);
+        }
+
+        #[test]
+        fn test_failed_call_rolls_back_nested_writes() {
+            // Enough gas for the nested request, but not for the mint's own writes
+            let mut runtime = random_nft_runtime(350);
+            let result = runtime.execute_contract("random_nft", "mintNFT", vec!["ipfs://x".to_string()]).unwrap();
+            assert!(!result.success);
+            assert_eq!(result.output, "out of gas");
+            assert!(runtime.query_state_prefix(RANDOMNESS_CONTRACT, "").is_empty());
+            assert!(runtime.query_state_prefix("random_nft", "").is_empty());
+
+            let mut runtime = ContractRuntime::new(10000);
+            runtime.deploy_contract("random_nft".to_string(), String::new(), "Qvalidator123".to_string()).unwrap();
+            let result = runtime.execute_contract("random_nft", "mintNFT", vec!["ipfs://x".to_string()]).unwrap();
+            assert!(!result.success);
+            assert_eq!(result.output, "Contract 'quantum_randomness' not found");
+        }
+
+        #[test]
+        fn test_nested_calls_stop_at_depth_limit() {
+            let mut runtime = random_nft_runtime(10000);
+            let mut snapshots = BTreeMap::new();
+            let mut gas = GasMeter::new(10000);
+
+            let mut calls = CallContext { runtime: &mut runtime, depth: MAX_CALL_DEPTH - 1, snapshots: &mut snapshots };
+            assert!(calls.call(&mut gas, RANDOMNESS_CONTRACT, "requestRandomness", vec![]).is_ok());
+
+            // A random_nft mint at the limit can't make its own nested call
+            let result = calls.call(&mut gas, "random_nft", "mintNFT", vec!["ipfs://x".to_string()]);
+            assert_eq!(result.unwrap_err(), format!("call to 'random_nft' failed: call depth limit of {} reached", MAX_CALL_DEPTH));
+        }
+
+        #[test]
+        fn test_query_state_prefix_lists_token_uris() {
+            let mut runtime = ContractRuntime::new(10000);
+
+            runtime.deploy_contract(
+                "quantum_nft".to_string(),
+                "contract QuantumNFT {}".to_string(),
+                "Qvalidator123".to_string()
+            ).unwrap();
+
+            for i in 0..3 {
+                runtime.execute_contract(
+                    "quantum_nft",
+                    "mintNFT",
+                    vec![format!("ipfs://nft-{}", i), format!("0x{:04x}", i + 1)]
+                ).unwrap();
+            }
+
+            let uris = runtime.query_state_prefix("quantum_nft", "tokenURI_");
+            assert_eq!(uris, vec![
+                ("tokenURI_0".to_string(), "ipfs://nft-0".to_string()),
+                ("tokenURI_1".to_string(), "ipfs://nft-1".to_string()),
+                ("tokenURI_2".to_string(), "ipfs://nft-2".to_string()),
+            ]);
+
+            assert!(runtime.query_state_prefix("missing", "tokenURI_").is_empty());
+        }
     }
-}
\ No newline at end of file
+}
+
+pub use abi::*;
+pub use contract_runtime::*;
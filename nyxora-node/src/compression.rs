@@ -0,0 +1,90 @@
+// Response compression negotiated through `Accept-Encoding`
+use std::io::Write;
+use flate2::write::{DeflateEncoder, GzEncoder};
+use flate2::Compression;
+use hyper::body::HttpBody;
+use hyper::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_LENGTH, VARY};
+use hyper::{Body, Response};
+
+/// Bodies smaller than this are sent as they are; compressing them saves
+/// little and costs a gzip header
+pub const MIN_COMPRESS_SIZE: u64 = 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Encoding {
+    Gzip,
+    Deflate,
+}
+
+impl Encoding {
+    fn name(self) -> &'static str {
+        match self {
+            Encoding::Gzip => "gzip",
+            Encoding::Deflate => "deflate",
+        }
+    }
+}
+
+/// Picks the encoding to answer with, preferring gzip. Codings listed with
+/// `q=0` are refused by the client.
+pub fn negotiate(headers: &HeaderMap) -> Option<Encoding> {
+    let accepted: Vec<&str> = headers.get_all(ACCEPT_ENCODING).iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .filter_map(|coding| {
+            let mut parts = coding.split(';').map(str::trim);
+            let name = parts.next()?;
+            let refused = parts.any(|param| param.strip_prefix("q=").and_then(|q| q.parse::<f32>().ok()) == Some(0.0));
+            (!refused).then_some(name)
+        })
+        .collect();
+
+    let accepts = |name: &str| accepted.iter().any(|coding| coding.eq_ignore_ascii_case(name) || *coding == "*");
+    if accepts("gzip") {
+        Some(Encoding::Gzip)
+    } else if accepts("deflate") {
+        Some(Encoding::Deflate)
+    } else {
+        None
+    }
+}
+
+/// Compresses a fully buffered response of at least `MIN_COMPRESS_SIZE`
+/// bytes. Streamed bodies, such as `/events`, have no known size and pass
+/// through untouched.
+pub async fn compress(response: Response<Body>, encoding: Encoding) -> Response<Body> {
+    let size = match response.body().size_hint().exact() {
+        Some(size) => size,
+        None => return response,
+    };
+    if size < MIN_COMPRESS_SIZE || response.headers().contains_key(CONTENT_ENCODING) {
+        return response;
+    }
+
+    let (mut parts, body) = response.into_parts();
+    let bytes = match hyper::body::to_bytes(body).await {
+        Ok(bytes) => bytes,
+        Err(_) => return Response::from_parts(parts, Body::empty()),
+    };
+    let compressed = match encoding {
+        Encoding::Gzip => {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&bytes).and_then(|_| encoder.finish())
+        },
+        Encoding::Deflate => {
+            let mut encoder = DeflateEncoder::new(Vec::new(), Compression::default());
+            encoder.write_all(&bytes).and_then(|_| encoder.finish())
+        },
+    };
+
+    match compressed {
+        Ok(compressed) => {
+            parts.headers.insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name()));
+            parts.headers.insert(VARY, HeaderValue::from_static("Accept-Encoding"));
+            parts.headers.remove(CONTENT_LENGTH);
+            Response::from_parts(parts, Body::from(compressed))
+        },
+        // Writing to a Vec cannot fail, but fall back to the original body anyway
+        Err(_) => Response::from_parts(parts, Body::from(bytes)),
+    }
+}
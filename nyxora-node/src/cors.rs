@@ -0,0 +1,54 @@
+// Cross-origin access for browser dashboards, limited to configured origins
+use hyper::header::{
+    HeaderMap, HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS,
+    ACCESS_CONTROL_ALLOW_ORIGIN, ACCESS_CONTROL_MAX_AGE, ORIGIN, VARY,
+};
+use hyper::{Body, Response, StatusCode};
+
+const ALLOWED_METHODS: &str = "GET, POST, OPTIONS";
+const ALLOWED_HEADERS: &str = "Content-Type, X-Admin-Key";
+
+/// Seconds a browser may reuse a preflight answer
+const PREFLIGHT_MAX_AGE_SECS: &str = "600";
+
+/// The `Access-Control-Allow-Origin` value for a request, if its origin is
+/// in `allowed`. An allowlist containing `*` admits every origin.
+pub fn allowed_origin(allowed: &[String], headers: &HeaderMap) -> Option<HeaderValue> {
+    if allowed.iter().any(|origin| origin == "*") {
+        return Some(HeaderValue::from_static("*"));
+    }
+
+    let origin = headers.get(ORIGIN)?;
+    let requested = origin.to_str().ok()?;
+    allowed.iter()
+        .any(|allowed| allowed.trim_end_matches('/') == requested)
+        .then(|| origin.clone())
+}
+
+/// Answer to an `OPTIONS` preflight. Origins outside the allowlist get no
+/// CORS headers, so the browser refuses the real request.
+pub fn preflight(origin: Option<HeaderValue>) -> Response<Body> {
+    let response = Response::builder().status(StatusCode::NO_CONTENT).body(Body::empty()).unwrap();
+    match origin {
+        Some(origin) => {
+            let mut response = allow(response, origin);
+            let headers = response.headers_mut();
+            headers.insert(ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_static(ALLOWED_METHODS));
+            headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, HeaderValue::from_static(ALLOWED_HEADERS));
+            headers.insert(ACCESS_CONTROL_MAX_AGE, HeaderValue::from_static(PREFLIGHT_MAX_AGE_SECS));
+            response
+        },
+        None => response,
+    }
+}
+
+/// Lets the allowed `origin` read `response`
+pub fn allow(mut response: Response<Body>, origin: HeaderValue) -> Response<Body> {
+    let headers = response.headers_mut();
+    // A specific origin makes the answer depend on the request's Origin
+    if origin != "*" {
+        headers.append(VARY, HeaderValue::from_static("Origin"));
+    }
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, origin);
+    response
+}
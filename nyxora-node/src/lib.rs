@@ -0,0 +1,5 @@
+#[path = "../../chain/mod.rs"]
+pub mod chain;
+
+#[path = "../../contracts/mod.rs"]
+pub mod contracts;
@@ -1,11 +1,31 @@
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use serde::{Deserialize, Serialize};
 use std::fs;
-use tokio;
 use hyper::{Body, Request, Response, Server, StatusCode};
 use hyper::service::{make_service_fn, service_fn};
+use std::collections::{HashMap, HashSet};
 use std::convert::Infallible;
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, RwLock};
+use tokio::sync::oneshot;
+use tracing_subscriber::EnvFilter;
+use nyxora_node::chain::consensus;
+use nyxora_node::chain::consensus::clock::{Clock, SystemClock};
+use nyxora_node::chain::consensus::governance::{self, Governance, VoteChoice};
+use nyxora_node::chain::consensus::hashing::HashAlgo;
+use nyxora_node::chain::consensus::verifier::{BlockVerifier, DEFAULT_MAX_BLOCK_BYTES, DEFAULT_MAX_BLOCK_TXS};
+use nyxora_node::chain::crypto;
+use nyxora_node::chain::history::BalanceHistory;
+use nyxora_node::chain::ledger::Ledger;
+use nyxora_node::chain::transaction::Transaction;
+use nyxora_node::contracts::sdk::ContractRuntime;
+
+mod compression;
+mod cors;
+mod mempool;
+mod metrics;
+mod rate_limit;
+mod rpc;
+mod ws;
 
 #[derive(Parser)]
 #[command(name = "nyxora-node")]
@@ -22,295 +42,4089 @@ struct Cli {
     /// Port to run the node on
     #[arg(short, long, default_value_t = 8080)]
     port: u16,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Replay a chain file from genesis and compare against the tip's state root
+    Replay {
+        /// Genesis file listing the initial validator set
+        #[arg(long)]
+        genesis: String,
+
+        /// Chain file containing the serialized hybrid blocks, in binary or JSON
+        #[arg(long)]
+        chain: String,
+    },
+    /// Check every block of a chain file without running a node
+    Verify {
+        /// Chain file containing the serialized hybrid blocks, in binary or JSON
+        #[arg(long)]
+        chain: String,
+
+        /// Hash function the chain was built with
+        #[arg(long, default_value = "sha3_256", value_parser = parse_hash_algo)]
+        hash_algo: HashAlgo,
+    },
+}
+
+fn parse_hash_algo(name: &str) -> Result<HashAlgo, String> {
+    serde_json::from_value(serde_json::Value::String(name.to_string()))
+        .map_err(|_| format!("Unknown hash algorithm '{}': expected sha3_256, blake3 or keccak256", name))
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 struct NodeConfig {
     pub address: String,
+    /// Network this node belongs to, e.g. `nyxora-mainnet` or `nyxora-testnet`
+    #[serde(default = "default_chain_id")]
+    pub chain_id: String,
     pub stake_amount: u64,
     pub is_validator: bool,
     pub quantum_enabled: bool,
     pub peers: Vec<String>,
+    /// Shared secret required in the `X-Admin-Key` header for admin routes.
+    /// Never serialized, since the config is served as part of `/status`.
+    #[serde(default, skip_serializing)]
+    pub admin_key: Option<String>,
+    /// Hash function for blocks: `sha3_256` (default), `blake3` or `keccak256`
+    #[serde(default)]
+    pub hash_algo: HashAlgo,
+    /// Requests per second each client IP may sustain; 0 disables limiting
+    #[serde(default = "default_rate_limit_per_sec")]
+    pub rate_limit_per_sec: u32,
+    /// Requests a client IP may make in a burst before being limited
+    #[serde(default = "default_rate_limit_burst")]
+    pub rate_limit_burst: u32,
+    /// File the node's blocks are written to, in binary, when it shuts down
+    #[serde(default)]
+    pub chain_file: Option<String>,
+    /// Seconds between a validator's attempts to propose a block
+    #[serde(default = "default_block_time_secs")]
+    pub block_time_secs: u64,
+    /// Most transactions a block may include
+    #[serde(default = "default_max_block_txs")]
+    pub max_block_txs: usize,
+    /// Most bytes of transaction entries a block may include
+    #[serde(default = "default_max_block_bytes")]
+    pub max_block_bytes: usize,
+    /// Origins browsers may call the API from, e.g. `https://dash.example`;
+    /// `*` allows any. Empty sends no CORS headers.
+    #[serde(default)]
+    pub cors_origins: Vec<String>,
+    /// Hash of a block the operator trusts; when set, the node starts from a
+    /// peer's state snapshot at that block instead of syncing every block
+    #[serde(default)]
+    pub trusted_block_hash: Option<String>,
+    /// Marks a test network, which enables `POST /faucet`
+    #[serde(default)]
+    pub testnet: bool,
+    /// Tokens the faucet grants per request
+    #[serde(default = "default_faucet_amount")]
+    pub faucet_amount: u64,
+    /// Seconds an address must wait between faucet grants
+    #[serde(default = "default_faucet_cooldown_secs")]
+    pub faucet_cooldown_secs: u64,
+    /// Balances credited at genesis, by address. Committed to by the genesis
+    /// block, so every node on a chain must configure the same allocations.
+    #[serde(default)]
+    pub genesis_allocations: HashMap<String, u64>,
+    /// Validators listed in the genesis block, by address and stake, besides
+    /// this node when it is one. Peers' chains must start from the same
+    /// genesis, so every node on a chain must configure the same set.
+    #[serde(default)]
+    pub genesis_validators: HashMap<String, u64>,
+    /// Seconds a transaction may wait in the mempool before it is dropped;
+    /// 0 keeps transactions until they are included
+    #[serde(default = "default_mempool_ttl_secs")]
+    pub mempool_ttl_secs: u64,
+    /// Proposals a validator may miss in a row before it is jailed; 0 never jails
+    #[serde(default = "default_jail_threshold")]
+    pub jail_threshold: u64,
+    /// Blocks a jailed validator must wait before `POST /unjail` succeeds
+    #[serde(default = "default_min_jail_blocks")]
+    pub min_jail_blocks: u64,
+}
+
+fn default_chain_id() -> String {
+    "nyxora-mainnet".to_string()
+}
+
+fn default_rate_limit_per_sec() -> u32 {
+    10
+}
+
+fn default_rate_limit_burst() -> u32 {
+    20
+}
+
+fn default_max_block_txs() -> usize {
+    DEFAULT_MAX_BLOCK_TXS
+}
+
+fn default_max_block_bytes() -> usize {
+    DEFAULT_MAX_BLOCK_BYTES
+}
+
+fn default_faucet_amount() -> u64 {
+    1_000
+}
+
+fn default_faucet_cooldown_secs() -> u64 {
+    3_600
+}
+
+fn default_mempool_ttl_secs() -> u64 {
+    3_600
+}
+
+fn default_jail_threshold() -> u64 {
+    consensus::pos::DEFAULT_JAIL_THRESHOLD
+}
+
+fn default_min_jail_blocks() -> u64 {
+    consensus::pos::DEFAULT_MIN_JAIL_BLOCKS
+}
+
+fn default_block_time_secs() -> u64 {
+    consensus::pos::DEFAULT_TARGET_BLOCK_TIME
+}
+
+impl NodeConfig {
+    /// Catches settings that would leave the node unable to participate,
+    /// before anything is started
+    fn validate(&self) -> Result<(), String> {
+        check_address(&self.address)?;
+        for (address, amount) in &self.genesis_allocations {
+            check_genesis_address(address)?;
+            if *amount == 0 {
+                return Err(format!("Genesis allocation to '{}' must be greater than 0", address));
+            }
+        }
+        for (address, stake) in &self.genesis_validators {
+            check_genesis_address(address)?;
+            if *stake == 0 {
+                return Err(format!("Genesis validator '{}' needs a stake greater than 0", address));
+            }
+        }
+        if self.is_validator && self.peers.is_empty() {
+            return Err("A validator needs at least one entry in peers".to_string());
+        }
+        if self.is_validator && self.stake_amount == 0 {
+            return Err("A validator needs a stake_amount greater than 0".to_string());
+        }
+        if self.is_validator && self.block_time_secs == 0 {
+            return Err("A validator needs a block_time_secs greater than 0".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn check_address(address: &str) -> Result<(), String> {
+    if !address.starts_with('Q') || address.len() != 40 {
+        return Err(format!(
+            "Invalid address '{}': expected 40 characters starting with 'Q'",
+            address
+        ));
+    }
+    Ok(())
+}
+
+// Addresses in the genesis block are committed to for good, so a typo must be
+// caught by the checksum before the node starts
+fn check_genesis_address(address: &str) -> Result<(), String> {
+    check_address(address)?;
+    if !crypto::validate_address(address) {
+        return Err(format!("Invalid address '{}': checksum does not match", address));
+    }
+    Ok(())
 }
 
 impl Default for NodeConfig {
     fn default() -> Self {
         NodeConfig {
             address: "Q123456789012345678901234567890123456789".to_string(),
+            chain_id: default_chain_id(),
             stake_amount: 1000,
             is_validator: false,
             quantum_enabled: false,
             peers: vec!["127.0.0.1:8081".to_string()],
+            admin_key: None,
+            hash_algo: HashAlgo::default(),
+            rate_limit_per_sec: default_rate_limit_per_sec(),
+            rate_limit_burst: default_rate_limit_burst(),
+            chain_file: None,
+            block_time_secs: default_block_time_secs(),
+            max_block_txs: default_max_block_txs(),
+            max_block_bytes: default_max_block_bytes(),
+            cors_origins: Vec::new(),
+            trusted_block_hash: None,
+            testnet: false,
+            faucet_amount: default_faucet_amount(),
+            faucet_cooldown_secs: default_faucet_cooldown_secs(),
+            genesis_allocations: HashMap::new(),
+            genesis_validators: HashMap::new(),
+            mempool_ttl_secs: default_mempool_ttl_secs(),
+            jail_threshold: default_jail_threshold(),
+            min_jail_blocks: default_min_jail_blocks(),
         }
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
-struct NodeState {
-    pub config: NodeConfig,
-    pub block_height: u64,
-    pub peers: Vec<String>,
-    pub status: String,
-}
-
-// Simplified consensus structs for the node
-mod consensus {
-    use serde::{Deserialize, Serialize};
-    use std::collections::HashMap;
+/// Maximum number of proofs accepted in one `POST /proofs/batch` call
+const MAX_PROOF_BATCH: usize = 100;
 
-    #[derive(Debug, Clone, Serialize, Deserialize)]
-    pub struct Validator {
-        pub address: String,
-        pub stake: u64,
-        pub last_block_proposed: u64,
-        pub uptime: f64,
-    }
+/// Gas budget for a single contract execution
+const CONTRACT_GAS_LIMIT: u64 = 10_000;
 
-    #[derive(Debug, Clone, Serialize, Deserialize)]
-    pub struct Block {
-        pub index: u64,
-        pub timestamp: u64,
-        pub prev_hash: String,
-        pub transactions: Vec<String>,
-        pub proposer: String,
-        pub hash: String,
-    }
+/// Upper bound on the number of peers a node keeps track of
+const MAX_PEERS: usize = 64;
 
-    #[derive(Debug, Clone, Serialize, Deserialize)]
-    pub struct QuantumProof {
-        pub circuit_descriptor: String,
-        pub measurement_results: Vec<u8>,
-        pub proof_artifact: String,
-        pub qubit_count: usize,
-        pub validator_address: String,
-        pub timestamp: u64,
-    }
+/// How often the known peers are asked for their own peer lists
+const PEER_GOSSIP_INTERVAL: std::time::Duration = std::time::Duration::from_secs(30);
 
-    #[derive(Debug, Clone, Serialize, Deserialize)]
-    pub struct HybridBlock {
-        pub pos_block: Block,
-        pub quantum_proofs: Vec<QuantumProof>,
-        pub hybrid_hash: String,
-    }
+/// How long to wait on a single peer before giving up for this round
+const PEER_REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
 
-    #[derive(Clone)]
-    pub struct PoSConsensus {
-        pub validators: HashMap<String, Validator>,
-        pub total_stake: u64,
-        pub current_block: u64,
-    }
+/// How long a peer that served an invalid chain is ignored
+const PEER_BAN_DURATION: std::time::Duration = std::time::Duration::from_secs(600);
 
-    impl PoSConsensus {
-        pub fn new() -> Self {
-            PoSConsensus {
-                validators: HashMap::new(),
-                total_stake: 0,
-                current_block: 0,
-            }
-        }
+/// How often rate limiter buckets of idle clients are dropped
+const RATE_LIMIT_PRUNE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
 
-        pub fn register_validator(&mut self, address: String, initial_stake: u64) {
-            self.validators.insert(
-                address.clone(),
-                Validator {
-                    address,
-                    stake: initial_stake,
-                    last_block_proposed: 0,
-                    uptime: 100.0,
-                }
-            );
-            self.total_stake += initial_stake;
-        }
+/// How often expired transactions are pruned from the mempool
+const MEMPOOL_PRUNE_INTERVAL: std::time::Duration = std::time::Duration::from_secs(60);
 
-        pub fn stake(&mut self, address: &str, amount: u64) -> bool {
-            if let Some(validator) = self.validators.get_mut(address) {
-                validator.stake += amount;
-                self.total_stake += amount;
-                true
-            } else {
-                false
-            }
-        }
-    }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct NodeState {
+    pub config: NodeConfig,
+    pub block_height: u64,
+    pub peers: Vec<String>,
+    pub status: String,
+    /// While paused the node keeps serving reads but rejects new work
+    pub paused: bool,
+    /// Leader timeouts seen so far at the current height
+    #[serde(default)]
+    pub view: u64,
+    /// Set once a peer has answered or a block has been produced, after
+    /// which `/ready` reports the node as able to serve traffic
+    #[serde(default)]
+    pub ready: bool,
+}
 
-    #[derive(Clone)]
-    pub struct PoQConsensus {
-        pub validator_scores: HashMap<String, u64>,
-    }
+/// Body of `POST /verify_signature`. The public key is needed because an
+/// address only commits to a prefix of it.
+#[derive(Debug, Deserialize)]
+struct SignatureCheck {
+    pub address: String,
+    pub public_key: String,
+    pub message: String,
+    pub signature: String,
+}
 
-    impl PoQConsensus {
-        pub fn new() -> Self {
-            PoQConsensus {
-                validator_scores: HashMap::new(),
-            }
-        }
+impl SignatureCheck {
+    fn is_valid(&self) -> bool {
+        crypto::address_from_public_key(&self.public_key).as_deref() == Some(self.address.as_str())
+            && crypto::verify(&self.public_key, self.message.as_bytes(), &self.signature)
     }
+}
 
-    #[derive(Clone)]
-    pub struct HybridConsensus {
-        pub pos: PoSConsensus,
-        pub poq: PoQConsensus,
-    }
+/// Body of `POST /governance/propose`, signed over
+/// `governance::proposal_message(description, deadline)`
+#[derive(Debug, Serialize, Deserialize)]
+struct ProposalRequest {
+    pub proposer: String,
+    pub description: String,
+    pub deadline: u64, // Last block height at which votes count
+    pub public_key: String,
+    pub signature: String,
+}
 
-    impl HybridConsensus {
-        pub fn new() -> Self {
-            HybridConsensus {
-                pos: PoSConsensus::new(),
-                poq: PoQConsensus::new(),
-            }
-        }
+/// Body of `POST /governance/vote`, signed over
+/// `governance::vote_message(proposal_id, choice)`
+#[derive(Debug, Serialize, Deserialize)]
+struct VoteRequest {
+    pub proposal_id: u64,
+    pub voter: String,
+    pub choice: VoteChoice,
+    pub public_key: String,
+    pub signature: String,
+}
 
-        pub fn register_validator(&mut self, address: String, initial_stake: u64) {
-            self.pos.register_validator(address, initial_stake);
-        }
+/// Answer to `GET /tx/{hash}`. `height` and `index` locate a confirmed
+/// transaction in its block; both are null while it is pending.
+#[derive(Debug, Serialize)]
+struct TxStatus {
+    pub hash: String,
+    pub status: &'static str, // `confirmed` or `pending`
+    pub height: Option<u64>,
+    pub index: Option<usize>,
+    pub transaction: Option<Transaction>,
+}
 
-        pub fn stake(&mut self, address: &str, amount: u64) -> bool {
-            self.pos.stake(address, amount)
-        }
-    }
+/// State needed to bootstrap a node without replaying the chain: balances,
+/// nonces and the validator set as of `block_height`. `state_root` commits
+/// to the validator set and the tip's `block_hash`, which is what
+/// `import_snapshot` checks against a trusted hash. Balances are not covered
+/// by any block, so they are only as trustworthy as the node served them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Snapshot {
+    pub block_height: u64,
+    pub block_hash: String,
+    pub state_root: String,
+    pub balances: HashMap<String, u64>,
+    pub nonces: HashMap<String, u64>,
+    pub validators: HashMap<String, consensus::pos::Validator>,
+    pub delegations: HashMap<String, Vec<(String, u64)>>,
+    pub total_stake: u64,
 }
 
 struct NyxoraNode {
     state: Arc<Mutex<NodeState>>,
     consensus: Arc<Mutex<consensus::HybridConsensus>>,
+    history: Arc<Mutex<BalanceHistory>>,
+    mempool: Arc<Mutex<mempool::Mempool>>,
+    chain: Arc<Mutex<Vec<consensus::HybridBlock>>>, // Genesis, then blocks produced by this node
+    tx_index: Arc<Mutex<HashMap<String, (u64, usize)>>>, // Transaction hash -> (block height, position in the block)
+    nonces: Arc<Mutex<HashMap<String, u64>>>, // Last accepted nonce per sender
+    banned_peers: Arc<Mutex<HashMap<String, u64>>>, // Peer address -> unix time its ban ends
+    governance: Arc<Mutex<Governance>>,
+    faucet_grants: Arc<Mutex<HashMap<String, u64>>>, // Address -> unix time of its last faucet grant
+    contracts: Arc<Mutex<ContractRuntime>>,
+    // Shared with `consensus.poq`, so proof and score reads skip the consensus lock
+    proofs: Arc<RwLock<Vec<consensus::poq::QuantumProof>>>,
+    quantum_scores: Arc<RwLock<HashMap<String, u64>>>,
+    tier_scores: Arc<RwLock<consensus::poq::TierScores>>,
+    metrics: Arc<metrics::Metrics>,
+    rate_limiter: Arc<rate_limit::RateLimiter>,
+    clock: Arc<dyn Clock>, // Decides when time-locked transactions become includable
 }
 
 impl NyxoraNode {
     fn new(config: NodeConfig) -> Self {
-        let mut consensus = consensus::HybridConsensus::new();
+        let clock: Arc<dyn Clock> = Arc::new(SystemClock);
+        let mut consensus = consensus::HybridConsensus::with_clock(Arc::clone(&clock));
+        consensus.pos.hash_algo = config.hash_algo;
+        consensus.pos.target_block_time = config.block_time_secs;
+        consensus.pos.jail_threshold = config.jail_threshold;
+        consensus.pos.min_jail_blocks = config.min_jail_blocks;
+
+        // Pre-funded balances are committed to by the genesis block
+        let mut history = BalanceHistory::new();
+        if !config.genesis_allocations.is_empty() {
+            let hash = consensus::pos::allocations_hash(&config.genesis_allocations);
+            consensus.pos.set_genesis_allocations_hash(Some(hash));
+            history.record(0, config.genesis_allocations.clone());
+        }
+
+        // The genesis validator set comes from config, so that every node on
+        // the chain derives the same genesis block
+        for (address, stake) in &config.genesis_validators {
+            consensus.pos.register_validator(address.clone(), *stake);
+        }
 
         // Register this node as a validator if configured as one
-        if config.is_validator {
+        if config.is_validator && !consensus.pos.validators.contains_key(&config.address) {
             consensus.pos.register_validator(config.address.clone(), config.stake_amount);
         }
+        let metrics = metrics::Metrics::default();
+        metrics.observe_stake(&consensus);
+        let genesis = consensus.genesis_block();
+        let proofs = Arc::clone(&consensus.poq.proofs);
+        let quantum_scores = Arc::clone(&consensus.poq.validator_scores);
+        let tier_scores = Arc::clone(&consensus.poq.tier_scores);
+        let rate_limiter = rate_limit::RateLimiter::new(config.rate_limit_per_sec, config.rate_limit_burst);
+
+        let mut peers: Vec<String> = Vec::new();
+        for peer in &config.peers {
+            if !peers.contains(peer) && peers.len() < MAX_PEERS {
+                peers.push(peer.clone());
+            }
+        }
 
         NyxoraNode {
             state: Arc::new(Mutex::new(NodeState {
                 config,
                 block_height: 0,
-                peers: vec![],
+                peers,
                 status: "running".to_string(),
+                paused: false,
+                view: 0,
+                ready: false,
             })),
             consensus: Arc::new(Mutex::new(consensus)),
+            history: Arc::new(Mutex::new(history)),
+            mempool: Arc::new(Mutex::new(mempool::Mempool::default())),
+            chain: Arc::new(Mutex::new(vec![genesis])),
+            tx_index: Arc::new(Mutex::new(HashMap::new())),
+            nonces: Arc::new(Mutex::new(HashMap::new())),
+            banned_peers: Arc::new(Mutex::new(HashMap::new())),
+            governance: Arc::new(Mutex::new(Governance::new())),
+            faucet_grants: Arc::new(Mutex::new(HashMap::new())),
+            contracts: Arc::new(Mutex::new(ContractRuntime::new(CONTRACT_GAS_LIMIT))),
+            proofs,
+            quantum_scores,
+            tier_scores,
+            metrics: Arc::new(metrics),
+            rate_limiter: Arc::new(rate_limiter),
+            clock,
         }
     }
 
-    async fn start_server(&self, port: u16) -> Result<(), Box<dyn std::error::Error>> {
-        let addr = ([127, 0, 0, 1], port).into();
+    // Runs the HTTP API until Ctrl-C, or until `shutdown` fires if given
+    async fn start_server(
+        &self,
+        port: u16,
+        shutdown: Option<oneshot::Receiver<()>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let listener = std::net::TcpListener::bind(("127.0.0.1", port))?;
+        self.serve(listener, shutdown).await
+    }
+
+    // Serves the HTTP API on an already bound listener, which lets several
+    // nodes run side by side in one process
+    async fn serve(
+        &self,
+        listener: std::net::TcpListener,
+        shutdown: Option<oneshot::Receiver<()>>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let addr = listener.local_addr()?;
 
         let node = self.clone_for_hyper();
 
-        let make_svc = make_service_fn(move |_conn| {
+        let make_svc = make_service_fn(move |conn: &hyper::server::conn::AddrStream| {
             let node = node.clone();
+            let remote_addr = conn.remote_addr();
             async move {
-                Ok::<_, Infallible>(service_fn(move |req| {
+                Ok::<_, Infallible>(service_fn(move |mut req: Request<Body>| {
                     let node = node.clone();
+                    // Lets handlers see who is calling, e.g. for rate limiting
+                    req.extensions_mut().insert(remote_addr);
                     handle_request(req, node)
                 }))
             }
         });
 
-        let server = Server::bind(&addr).serve(make_svc);
+        let shutdown_signal = async move {
+            match shutdown {
+                Some(receiver) => tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {},
+                    Ok(()) = receiver => {},
+                },
+                None => {
+                    let _ = tokio::signal::ctrl_c().await;
+                },
+            }
+        };
+
+        let server = Server::from_tcp(listener)?
+            .serve(make_svc)
+            .with_graceful_shutdown(shutdown_signal);
+
+        let chain_id = self.state.lock().unwrap().config.chain_id.clone();
+        tracing::info!(%chain_id, %addr, "node listening");
+
+        // Catch up with the network once, without holding up the server
+        let sync_node = self.clone_shared();
+        let trusted_hash = self.state.lock().unwrap().config.trusted_block_hash.clone();
+        let sync = tokio::spawn(async move {
+            let snapshot_height = match trusted_hash {
+                Some(hash) => sync_node.sync_from_snapshot(&hash).await,
+                None => None,
+            };
+            if snapshot_height.is_none() {
+                sync_node.sync_from_peers().await;
+            }
+        });
+
+        let gossip_node = self.clone_shared();
+        let gossip = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(PEER_GOSSIP_INTERVAL);
+            loop {
+                interval.tick().await;
+                gossip_node.gossip_peers(&addr.to_string()).await;
+            }
+        });
+
+        // Only validators propose blocks
+        let (is_validator, block_time_secs) = {
+            let state = self.state.lock().unwrap();
+            (state.config.is_validator, state.config.block_time_secs)
+        };
+        let production = is_validator.then(|| {
+            let block_time = std::time::Duration::from_secs(block_time_secs);
+            tokio::spawn(produce_blocks(self.clone_shared(), block_time))
+        });
+
+        let rate_limiter = Arc::clone(&self.rate_limiter);
+        let pruning = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(RATE_LIMIT_PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                rate_limiter.prune_idle(std::time::Instant::now());
+            }
+        });
+
+        let expiry_node = self.clone_shared();
+        let expiry = tokio::spawn(async move {
+            let mut interval = tokio::time::interval(MEMPOOL_PRUNE_INTERVAL);
+            loop {
+                interval.tick().await;
+                expiry_node.prune_mempool();
+            }
+        });
+
+        // Proofs are counted from consensus events, whichever path submitted them
+        let mut events = self.subscribe_events();
+        let proof_metrics = Arc::clone(&self.metrics);
+        let proof_counter = tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(consensus::events::ConsensusEvent::ProofAccepted { .. }) => proof_metrics.inc_proofs(),
+                    Ok(_) | Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => {},
+                    Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
 
-        println!("Nyxora node running on http://{}", addr);
+        let result = server.await;
+        sync.abort();
+        if let Some(production) = production {
+            production.abort();
+        }
+        gossip.abort();
+        pruning.abort();
+        expiry.abort();
+        proof_counter.abort();
+        result?;
 
-        server.await?;
+        // In-flight requests have finished, so the chain can no longer change
+        let chain_file = self.state.lock().unwrap().config.chain_file.clone();
+        if let Some(path) = chain_file {
+            self.save_chain(&path)?;
+            tracing::info!(%path, "chain written");
+        }
+        tracing::info!(height = self.get_status().block_height, "node shut down");
         Ok(())
     }
 
     fn clone_for_hyper(&self) -> Arc<Mutex<Self>> {
-        Arc::new(Mutex::new(NyxoraNode {
-            state: Arc::new(Mutex::new((*self.state.lock().unwrap()).clone())),
-            consensus: Arc::new(Mutex::new((*self.consensus.lock().unwrap()).clone())),
-        }))
+        Arc::new(Mutex::new(self.clone_shared()))
     }
 
-    fn get_status(&self) -> NodeState {
-        self.state.lock().unwrap().clone()
+    // A second handle onto the same node, so the server and background tasks
+    // all see the same state
+    fn clone_shared(&self) -> Self {
+        NyxoraNode {
+            state: Arc::clone(&self.state),
+            consensus: Arc::clone(&self.consensus),
+            history: Arc::clone(&self.history),
+            mempool: Arc::clone(&self.mempool),
+            chain: Arc::clone(&self.chain),
+            tx_index: Arc::clone(&self.tx_index),
+            nonces: Arc::clone(&self.nonces),
+            banned_peers: Arc::clone(&self.banned_peers),
+            governance: Arc::clone(&self.governance),
+            faucet_grants: Arc::clone(&self.faucet_grants),
+            contracts: Arc::clone(&self.contracts),
+            proofs: Arc::clone(&self.proofs),
+            quantum_scores: Arc::clone(&self.quantum_scores),
+            tier_scores: Arc::clone(&self.tier_scores),
+            metrics: Arc::clone(&self.metrics),
+            rate_limiter: Arc::clone(&self.rate_limiter),
+            clock: Arc::clone(&self.clock),
+        }
     }
 
-    fn stake_tokens(&self, amount: u64) -> bool {
-        let state = self.state.lock().unwrap();
-        let mut consensus = self.consensus.lock().unwrap();
-        consensus.stake(&state.config.address, amount)
+    fn subscribe_events(&self) -> tokio::sync::broadcast::Receiver<consensus::events::ConsensusEvent> {
+        self.consensus.lock().unwrap().subscribe()
     }
-}
 
-async fn handle_request(
-    req: Request<Body>,
-    node: Arc<Mutex<NyxoraNode>>
-) -> Result<Response<Body>, Infallible> {
-    match (req.method(), req.uri().path()) {
-        (&hyper::Method::GET, "/status") => {
-            let status = node.lock().unwrap().get_status();
-            let json = serde_json::to_string(&status).unwrap();
-            Ok(Response::new(Body::from(json)))
-        },
-        (&hyper::Method::POST, "/stake") => {
-            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
-            let amount: u64 = String::from_utf8_lossy(&body_bytes).parse().unwrap_or(0);
+    fn get_peers(&self) -> Vec<String> {
+        self.state.lock().unwrap().peers.clone()
+    }
 
-            let success = node.lock().unwrap().stake_tokens(amount);
-            let response = if success {
-                Response::builder()
-                    .status(StatusCode::OK)
-                    .body(Body::from("Stake successful"))
-            } else {
-                Response::builder()
-                    .status(StatusCode::BAD_REQUEST)
-                    .body(Body::from("Stake failed"))
+    // Stops talking to `peer` for `duration`. A longer ban already in place
+    // is kept.
+    fn ban_peer(&self, peer: &str, duration: std::time::Duration) {
+        let until = self.clock.now_secs().saturating_add(duration.as_secs());
+        let mut banned = self.banned_peers.lock().unwrap();
+        let entry = banned.entry(peer.to_string()).or_insert(until);
+        *entry = (*entry).max(until);
+        tracing::warn!(%peer, until, "peer banned");
+    }
+
+    // Bans still in force, dropping the ones that have run out
+    fn active_bans(&self) -> std::sync::MutexGuard<'_, HashMap<String, u64>> {
+        let now = self.clock.now_secs();
+        let mut banned = self.banned_peers.lock().unwrap();
+        banned.retain(|_, until| *until > now);
+        banned
+    }
+
+    // Whether requests from `ip` come from a banned peer. Peers are known by
+    // their listening address, not the port they connect from, so a ban on
+    // `host:port` covers any connection from that host. Loopback is the
+    // exception: the wallet and other local clients share it with any local
+    // peer, so only a ban on the bare IP refuses them.
+    fn is_banned_ip(&self, ip: std::net::IpAddr) -> bool {
+        self.active_bans().keys().any(|peer| match peer.parse::<std::net::SocketAddr>() {
+            Ok(addr) => addr.ip() == ip && !ip.is_loopback(),
+            Err(_) => peer.parse::<std::net::IpAddr>() == Ok(ip),
+        })
+    }
+
+    // Known peers that are not banned, which gossip and sync talk to
+    fn gossip_targets(&self) -> Vec<String> {
+        let banned = self.active_bans();
+        self.get_peers().into_iter().filter(|peer| !banned.contains_key(peer)).collect()
+    }
+
+    // Adds previously unknown peers, never this node itself, up to MAX_PEERS.
+    // Returns how many were added.
+    fn merge_peers(&self, discovered: Vec<String>, own_addr: &str) -> usize {
+        let mut state = self.state.lock().unwrap();
+        let mut added = 0;
+        for peer in discovered {
+            if state.peers.len() >= MAX_PEERS {
+                break;
+            }
+            if peer != own_addr && !state.peers.contains(&peer) {
+                state.peers.push(peer);
+                added += 1;
+            }
+        }
+        added
+    }
+
+    // One gossip round: asks every known peer for its peer list and merges
+    // the results. Unreachable peers are skipped until the next round.
+    async fn gossip_peers(&self, own_addr: &str) -> usize {
+        let client = hyper::Client::new();
+        let mut added = 0;
+
+        for peer in self.gossip_targets() {
+            let uri: hyper::Uri = match format!("http://{}/peers", peer).parse() {
+                Ok(uri) => uri,
+                Err(_) => continue,
             };
-            Ok(response.unwrap())
-        },
-        (&hyper::Method::GET, "/blocks") => {
-            // Return current block height
-            let height = node.lock().unwrap().get_status().block_height;
-            let response = format!("Current block height: {}", height);
-            Ok(Response::new(Body::from(response)))
-        },
-        _ => {
-            Ok(Response::builder()
-                .status(StatusCode::NOT_FOUND)
-                .body(Body::from("Not found"))
-                .unwrap())
+            let fetch = async {
+                let response = client.get(uri).await.ok()?;
+                let body = hyper::body::to_bytes(response.into_body()).await.ok()?;
+                serde_json::from_slice::<Vec<String>>(&body).ok()
+            };
+
+            if let Ok(Some(discovered)) = tokio::time::timeout(PEER_REQUEST_TIMEOUT, fetch).await {
+                self.mark_ready();
+                added += self.merge_peers(discovered, own_addr);
+            }
         }
+        added
     }
-}
 
-#[tokio::main]
-async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+    // Asks every peer for its chain and adopts the longest one that starts
+    // from our genesis, verifies, holds only signed transfers in nonce order
+    // and replays, if it is longer than ours.
+    // Equally long chains go to the one backed by more stake. Returns the
+    // height reached.
+    async fn sync_from_peers(&self) -> u64 {
+        let client = hyper::Client::new();
+        let hash_algo = self.state.lock().unwrap().config.hash_algo;
+        let (local_len, local_genesis) = {
+            let chain = self.chain.lock().unwrap();
+            (chain.len(), chain[0].clone())
+        };
+        let verifier = self.block_verifier();
 
-    // Load configuration
-    let config = if fs::metadata(&cli.config).is_ok() {
-        let contents = fs::read_to_string(&cli.config)?;
-        serde_json::from_str(&contents)?
-    } else {
-        eprintln!("Configuration file '{}' not found, using defaults", cli.config);
-        NodeConfig::default()
-    };
+        let mut best: Option<(Vec<consensus::HybridBlock>, consensus::pos::PoSConsensus)> = None;
+        for peer in self.gossip_targets() {
+            let uri: hyper::Uri = match format!("http://{}/chain", peer).parse() {
+                Ok(uri) => uri,
+                Err(_) => continue,
+            };
+            let fetch = async {
+                let response = client.get(uri).await.ok()?;
+                let body = hyper::body::to_bytes(response.into_body()).await.ok()?;
+                serde_json::from_slice::<Vec<consensus::HybridBlock>>(&body).ok()
+            };
+            let blocks = match tokio::time::timeout(PEER_REQUEST_TIMEOUT, fetch).await {
+                Ok(Some(blocks)) if blocks.len() > local_len => blocks,
+                _ => continue,
+            };
+            // Replaying a chain against its own first block would accept any
+            // genesis, so it has to be the one this node was configured with
+            if blocks[0].hybrid_hash != local_genesis.hybrid_hash {
+                tracing::warn!(%peer, "peer chain starts from a different genesis");
+                continue;
+            }
+            if !verifier.verify_chain(&blocks) {
+                tracing::warn!(%peer, "peer served an invalid chain");
+                self.ban_peer(&peer, PEER_BAN_DURATION);
+                continue;
+            }
+            if let Err(e) = check_transfers(&blocks) {
+                tracing::warn!(%peer, error = %e, "peer chain holds invalid transfers");
+                self.ban_peer(&peer, PEER_BAN_DURATION);
+                continue;
+            }
 
-    // Update config based on CLI args
-    let config = NodeConfig {
-        is_validator: cli.validator,
-        ..config
-    };
+            let replayed = consensus::replay::Genesis::from_block(&local_genesis, hash_algo)
+                .and_then(|genesis| consensus::replay::replay_pos(&genesis, &blocks, Arc::clone(&self.clock)));
+            let pos = match replayed {
+                Ok(pos) => pos,
+                Err(e) => {
+                    tracing::warn!(%peer, error = %e, "peer chain does not replay");
+                    self.ban_peer(&peer, PEER_BAN_DURATION);
+                    continue;
+                },
+            };
 
-    println!("Starting Nyxora node...");
-    println!("Validator mode: {}", config.is_validator);
-    println!("Quantum enabled: {}", config.quantum_enabled);
+            let better = match &best {
+                None => true,
+                Some((best_blocks, best_pos)) => blocks.len() > best_blocks.len()
+                    || (blocks.len() == best_blocks.len() && pos.total_stake > best_pos.total_stake),
+            };
+            if better {
+                best = Some((blocks, pos));
+            }
+        }
 
-    // Create and start the node
-    let node = NyxoraNode::new(config);
+        match best {
+            Some((blocks, pos)) => self.adopt_chain(blocks, pos),
+            None => self.state.lock().unwrap().block_height,
+        }
+    }
 
-    // If running as validator, start block production
-    if cli.validator {
-        println!("Validator node started, listening for transactions...");
-        // In a real implementation, we would start the block production loop here
+    // Asks peers for a state snapshot and imports the first one taken at the
+    // block with `trusted_hash`. Returns the height reached, or `None` if no
+    // peer had one.
+    async fn sync_from_snapshot(&self, trusted_hash: &str) -> Option<u64> {
+        let client = hyper::Client::new();
+        for peer in self.gossip_targets() {
+            let uri: hyper::Uri = match format!("http://{}/snapshot", peer).parse() {
+                Ok(uri) => uri,
+                Err(_) => continue,
+            };
+            let fetch = async {
+                let response = client.get(uri).await.ok()?;
+                let body = hyper::body::to_bytes(response.into_body()).await.ok()?;
+                serde_json::from_slice::<Snapshot>(&body).ok()
+            };
+            let snapshot = match tokio::time::timeout(PEER_REQUEST_TIMEOUT, fetch).await {
+                Ok(Some(snapshot)) => snapshot,
+                _ => continue,
+            };
+            match self.import_snapshot(snapshot, trusted_hash) {
+                Ok(height) => return Some(height),
+                Err(e) => tracing::warn!(%peer, error = %e, "peer snapshot rejected"),
+            }
+        }
+        None
     }
 
-    // Start the HTTP server
-    node.start_server(cli.port).await?;
+    // Replaces the local chain and stake table with a synced chain and the
+    // state replayed from it, dropping pending transactions it already holds.
+    // Balances written by local blocks the synced chain does not share are
+    // discarded, and the transfers in its blocks from there on are applied.
+    fn adopt_chain(&self, blocks: Vec<consensus::HybridBlock>, mut pos: consensus::pos::PoSConsensus) -> u64 {
+        let height = blocks.last().map(|block| block.pos_block.index).unwrap_or(0);
+        let included: Vec<Transaction> = blocks.iter()
+            .flat_map(|block| &block.pos_block.transactions)
+            .filter_map(|entry| serde_json::from_str(entry).ok())
+            .collect();
+        let fork_point = {
+            let chain = self.chain.lock().unwrap();
+            chain.iter().zip(&blocks)
+                .take_while(|(ours, theirs)| ours.hybrid_hash == theirs.hybrid_hash)
+                .count()
+        };
 
-    Ok(())
-}
\ No newline at end of file
+        {
+            let mut consensus = self.consensus.lock().unwrap();
+            pos.copy_parameters_from(&consensus.pos);
+            consensus.pos = pos;
+            self.metrics.observe_stake(&consensus);
+        }
+        {
+            let mut tx_index = self.tx_index.lock().unwrap();
+            tx_index.clear();
+            for block in &blocks {
+                index_transactions(&mut tx_index, block);
+            }
+        }
+        self.history.lock().unwrap().truncate_after(fork_point.saturating_sub(1) as u64);
+        for block in blocks.iter().skip(fork_point) {
+            let transactions: Vec<Transaction> = block.pos_block.transactions.iter()
+                .filter_map(|entry| serde_json::from_str(entry).ok())
+                .collect();
+            let mut ledger = self.ledger_for(&transactions);
+            for tx in &transactions {
+                if let Err(e) = ledger.apply_transaction(tx) {
+                    tracing::warn!(height = block.pos_block.index, "synced transaction does not apply: {}", e);
+                }
+            }
+            self.record_balances(block.pos_block.index, ledger);
+        }
+        *self.chain.lock().unwrap() = blocks;
+        {
+            let mut nonces = self.nonces.lock().unwrap();
+            for tx in &included {
+                let nonce = nonces.entry(tx.from.clone()).or_insert(0);
+                *nonce = (*nonce).max(tx.nonce);
+            }
+        }
+        {
+            let mut mempool = self.mempool.lock().unwrap();
+            mempool.remove_included(&included);
+            self.metrics.set_mempool_size(mempool.len());
+        }
+        {
+            let mut state = self.state.lock().unwrap();
+            state.block_height = height;
+            state.ready = true;
+        }
+        self.metrics.set_block_height(height);
+        tracing::info!(height, "synced chain from peers");
+        height
+    }
+
+    fn get_status(&self) -> NodeState {
+        let mut status = self.state.lock().unwrap().clone();
+        status.view = self.consensus.lock().unwrap().pos.view;
+        status
+    }
+
+    fn stake_tokens(&self, amount: u64) -> bool {
+        let state = self.state.lock().unwrap();
+        let mut consensus = self.consensus.lock().unwrap();
+        let staked = consensus.stake(&state.config.address, amount);
+        self.metrics.observe_stake(&consensus);
+        staked
+    }
+
+    // Moves accrued staking rewards into this node's spendable balance
+    fn withdraw_rewards(&self, amount: u64) -> Result<u64, String> {
+        let (address, height) = {
+            let state = self.state.lock().unwrap();
+            (state.config.address.clone(), state.block_height)
+        };
+
+        let withdrawn = {
+            let mut consensus = self.consensus.lock().unwrap();
+            let withdrawn = consensus.pos.withdraw_rewards(&address, amount)?;
+            self.metrics.observe_stake(&consensus);
+            withdrawn
+        };
+
+        let mut history = self.history.lock().unwrap();
+        let balance = history.latest_balance(&address) + withdrawn;
+        history.record(height, [(address, balance)].into_iter().collect());
+        Ok(balance)
+    }
+
+    // Returns this node's validator to proposer selection after its jail term
+    fn unjail(&self) -> Result<(), String> {
+        let address = self.state.lock().unwrap().config.address.clone();
+        self.consensus.lock().unwrap().pos.unjail(&address)
+    }
+
+    // Credits `address` with the testnet faucet amount, at most once per
+    // cooldown. Returns the amount granted and the new balance.
+    fn faucet(&self, address: &str) -> Result<(u64, u64), String> {
+        let (amount, cooldown, height) = {
+            let state = self.state.lock().unwrap();
+            (state.config.faucet_amount, state.config.faucet_cooldown_secs, state.block_height)
+        };
+
+        let now = self.clock.now_secs();
+        {
+            let mut grants = self.faucet_grants.lock().unwrap();
+            if let Some(&granted_at) = grants.get(address) {
+                let ready_at = granted_at.saturating_add(cooldown);
+                if now < ready_at {
+                    return Err(format!("Faucet already used by {}; retry in {}s", address, ready_at - now));
+                }
+            }
+            grants.insert(address.to_string(), now);
+        }
+
+        let mut history = self.history.lock().unwrap();
+        let balance = history.latest_balance(address).saturating_add(amount);
+        history.record(height, [(address.to_string(), balance)].into_iter().collect());
+        Ok((amount, balance))
+    }
+
+    fn set_paused(&self, paused: bool) {
+        self.state.lock().unwrap().paused = paused;
+    }
+
+    fn is_paused(&self) -> bool {
+        self.state.lock().unwrap().paused
+    }
+
+    fn mark_ready(&self) {
+        self.state.lock().unwrap().ready = true;
+    }
+
+    fn is_ready(&self) -> bool {
+        self.state.lock().unwrap().ready
+    }
+
+    fn admin_key(&self) -> Option<String> {
+        self.state.lock().unwrap().config.admin_key.clone()
+    }
+
+    // Accepts a transaction into the mempool only if its sender signed it and
+    // it carries the sender's next nonce, which rules out forged senders,
+    // replays and out-of-order submissions
+    fn submit_transaction(&self, tx: Transaction) -> Result<(), String> {
+        if !tx.has_valid_signature() {
+            return Err(format!("Invalid signature for transaction from {}", tx.from));
+        }
+
+        let mut nonces = self.nonces.lock().unwrap();
+        let expected = nonces.get(&tx.from).copied().unwrap_or(0) + 1;
+        if tx.nonce != expected {
+            return Err(format!(
+                "Invalid nonce {} for {}: expected {}",
+                tx.nonce, tx.from, expected
+            ));
+        }
+
+        nonces.insert(tx.from.clone(), tx.nonce);
+        let mut mempool = self.mempool.lock().unwrap();
+        mempool.push(tx, self.clock.now_secs());
+        self.metrics.set_mempool_size(mempool.len());
+        Ok(())
+    }
+
+    fn next_nonce(&self, address: &str) -> u64 {
+        self.nonces.lock().unwrap().get(address).copied().unwrap_or(0) + 1
+    }
+
+    // Drops transactions that have waited longer than `mempool_ttl_secs`,
+    // along with their senders' later ones, and rewinds those senders'
+    // nonces so they can resubmit. Returns how many were pruned.
+    fn prune_mempool(&self) -> usize {
+        let ttl_secs = self.state.lock().unwrap().config.mempool_ttl_secs;
+        if ttl_secs == 0 {
+            return 0;
+        }
+
+        let mut nonces = self.nonces.lock().unwrap();
+        let mut mempool = self.mempool.lock().unwrap();
+        let pruned = mempool.prune_expired(self.clock.now_secs(), ttl_secs);
+        for tx in &pruned {
+            let nonce = nonces.entry(tx.from.clone()).or_insert(0);
+            *nonce = (*nonce).min(tx.nonce.saturating_sub(1));
+        }
+        self.metrics.set_mempool_size(mempool.len());
+        if !pruned.is_empty() {
+            tracing::info!(pruned = pruned.len(), "pruned expired transactions");
+        }
+        pruned.len()
+    }
+
+    fn get_mempool(&self) -> Vec<Transaction> {
+        self.mempool.lock().unwrap().transactions().to_vec()
+    }
+
+    // Proposes a block if this node is a validator leading the current view.
+    // A paused node sits its turn out.
+    fn produce_block_if_leader(&self) -> Option<consensus::HybridBlock> {
+        let (address, is_validator, paused) = {
+            let state = self.state.lock().unwrap();
+            (state.config.address.clone(), state.config.is_validator, state.paused)
+        };
+        if !is_validator || paused {
+            return None;
+        }
+
+        let leader = self.consensus.lock().unwrap().pos.leader_for_view();
+        if leader.as_deref() != Some(address.as_str()) {
+            return None;
+        }
+        self.propose_block()
+    }
+
+    // Proposes a block from this node with pending transactions chosen by fee,
+    // up to the configured transaction count and byte limits, evicting them
+    // from the mempool once included. Transactions their senders cannot pay
+    // for stay pending.
+    fn propose_block(&self) -> Option<consensus::HybridBlock> {
+        let (address, max_txs, max_bytes) = {
+            let state = self.state.lock().unwrap();
+            (state.config.address.clone(), state.config.max_block_txs, state.config.max_block_bytes)
+        };
+
+        self.prune_mempool();
+
+        let candidates = self.mempool.lock().unwrap().select_by_fee(max_txs, max_bytes, self.clock.now_secs());
+        let (transactions, ledger) = self.apply_to_ledger(candidates);
+        let entries = transactions.iter().map(Transaction::to_block_entry).collect();
+
+        let block = {
+            let mut consensus = self.consensus.lock().unwrap();
+            let block = consensus.propose_hybrid_block(&address, entries);
+            self.metrics.observe_stake(&consensus);
+            block
+        };
+        // If this node could not propose, the transactions simply stay pending
+        if let Some(block) = &block {
+            {
+                let mut state = self.state.lock().unwrap();
+                state.block_height = block.pos_block.index;
+                state.ready = true;
+            }
+            self.metrics.set_block_height(block.pos_block.index);
+            index_transactions(&mut self.tx_index.lock().unwrap(), block);
+            self.chain.lock().unwrap().push(block.clone());
+            {
+                let mut mempool = self.mempool.lock().unwrap();
+                mempool.remove_included(&transactions);
+                self.metrics.set_mempool_size(mempool.len());
+            }
+            self.record_balances(block.pos_block.index, ledger);
+        }
+        block
+    }
+
+    // Applies the candidates in order to the current balances of the accounts
+    // they touch, keeping those whose senders can cover amount and fee. Once a
+    // sender's transaction fails, its later ones are held back too so nonces
+    // stay in order. Fees leave the ledger for the proposer's rewards, which
+    // is the only way the supply of these accounts may change.
+    fn apply_to_ledger(&self, candidates: Vec<Transaction>) -> (Vec<Transaction>, Ledger) {
+        let mut ledger = self.ledger_for(&candidates);
+        let supply = ledger.total_supply();
+
+        let mut held_back = HashSet::new();
+        let mut included = Vec::new();
+        let mut fees = 0u128;
+        for tx in candidates {
+            if held_back.contains(&tx.from) {
+                continue;
+            }
+            match ledger.apply_transaction(&tx) {
+                Ok(()) => {
+                    fees += tx.fee as u128;
+                    included.push(tx);
+                },
+                Err(e) => {
+                    tracing::debug!(nonce = tx.nonce, "holding back transaction: {}", e);
+                    held_back.insert(tx.from.clone());
+                },
+            }
+        }
+
+        assert_eq!(ledger.total_supply() + fees, supply, "transfers changed the total supply");
+        (included, ledger)
+    }
+
+    // Ledger holding the current balances of the accounts `transactions` touch
+    fn ledger_for(&self, transactions: &[Transaction]) -> Ledger {
+        let mut ledger = Ledger::new();
+        let history = self.history.lock().unwrap();
+        for address in transactions.iter().flat_map(|tx| [&tx.from, &tx.to]) {
+            ledger.balances.entry(address.clone()).or_insert_with(|| history.latest_balance(address));
+        }
+        ledger
+    }
+
+    // Writes the balances a block changed into the history
+    fn record_balances(&self, height: u64, ledger: Ledger) {
+        let mut history = self.history.lock().unwrap();
+        let changes = ledger.balances.into_iter()
+            .filter(|(address, balance)| history.latest_balance(address) != *balance)
+            .collect::<HashMap<_, _>>();
+        if !changes.is_empty() {
+            history.record(height, changes);
+        }
+    }
+
+    // Where the transaction with `hash` stands: included in a block, still
+    // in the mempool, or unknown to this node
+    fn transaction_status(&self, hash: &str) -> Option<TxStatus> {
+        let included = self.tx_index.lock().unwrap().get(hash).copied();
+        if let Some((height, index)) = included {
+            let transaction = self.chain.lock().unwrap().get(height as usize)
+                .filter(|block| block.pos_block.index == height)
+                .and_then(|block| block.pos_block.transactions.get(index))
+                .and_then(|entry| serde_json::from_str(entry).ok());
+            return Some(TxStatus {
+                hash: hash.to_string(),
+                status: "confirmed",
+                height: Some(height),
+                index: Some(index),
+                transaction,
+            });
+        }
+
+        self.mempool.lock().unwrap().transactions().iter()
+            .find(|tx| tx.hash() == hash)
+            .map(|tx| TxStatus {
+                hash: hash.to_string(),
+                status: "pending",
+                height: None,
+                index: None,
+                transaction: Some(tx.clone()),
+            })
+    }
+
+    fn get_chain(&self) -> Vec<consensus::HybridBlock> {
+        self.chain.lock().unwrap().clone()
+    }
+
+    fn export_snapshot(&self) -> Snapshot {
+        let (block_height, block_hash, state_root, validators, delegations, total_stake) = {
+            let consensus = self.consensus.lock().unwrap();
+            let pos = &consensus.pos;
+            (
+                pos.current_block,
+                pos.last_block_hash.clone(),
+                pos.state_root(),
+                pos.validators.clone(),
+                pos.delegations.clone(),
+                pos.total_stake,
+            )
+        };
+        Snapshot {
+            block_height,
+            block_hash,
+            state_root,
+            balances: self.history.lock().unwrap().latest_balances(),
+            nonces: self.nonces.lock().unwrap().clone(),
+            validators,
+            delegations,
+            total_stake,
+        }
+    }
+
+    // Bootstraps this node from `snapshot`, which must be taken at the block
+    // with `trusted_hash` and whose validator set must match its state root.
+    // Blocks before the snapshot are not fetched, so balances below its
+    // height read as pruned. Returns the height reached.
+    fn import_snapshot(&self, snapshot: Snapshot, trusted_hash: &str) -> Result<u64, String> {
+        if snapshot.block_hash != trusted_hash {
+            return Err(format!("Snapshot is of block {}, not the trusted {}", snapshot.block_hash, trusted_hash));
+        }
+
+        {
+            let mut consensus = self.consensus.lock().unwrap();
+            let mut pos = consensus::pos::PoSConsensus::with_clock(Arc::clone(&self.clock));
+            pos.copy_parameters_from(&consensus.pos);
+            pos.current_block = snapshot.block_height;
+            pos.last_block_hash = snapshot.block_hash;
+            pos.validators = snapshot.validators;
+            pos.delegations = snapshot.delegations;
+            pos.total_stake = snapshot.total_stake;
+            if pos.state_root() != snapshot.state_root {
+                return Err("Snapshot validator set does not match its state root".to_string());
+            }
+            consensus.pos = pos;
+            self.metrics.observe_stake(&consensus);
+        }
+
+        let height = snapshot.block_height;
+        {
+            let mut history = BalanceHistory::new();
+            history.record(height, snapshot.balances);
+            history.prune_before(height);
+            *self.history.lock().unwrap() = history;
+        }
+        *self.nonces.lock().unwrap() = snapshot.nonces;
+        {
+            let mut state = self.state.lock().unwrap();
+            state.block_height = height;
+            state.ready = true;
+        }
+        self.metrics.set_block_height(height);
+        tracing::info!(height, "imported state snapshot");
+        Ok(height)
+    }
+
+    // Writes the stored chain in the compact binary form read by `replay`
+    fn save_chain(&self, path: &str) -> Result<(), String> {
+        let bytes = consensus::encoding::to_bytes(&*self.chain.lock().unwrap());
+        fs::write(path, bytes).map_err(|e| format!("Could not write chain file '{}': {}", path, e))
+    }
+
+    // Verifies the stored chain, returning the index of the first bad block
+    fn verify_chain(&self) -> Option<u64> {
+        let verifier = self.block_verifier();
+        let chain = self.chain.lock().unwrap();
+        verifier.find_invalid_block(&chain).map(|i| chain[i].pos_block.index)
+    }
+
+    // Verifier for this node's hash algorithm and configured block limits
+    fn block_verifier(&self) -> BlockVerifier {
+        let state = self.state.lock().unwrap();
+        BlockVerifier {
+            max_block_txs: state.config.max_block_txs,
+            max_block_bytes: state.config.max_block_bytes,
+            ..BlockVerifier::new(state.config.hash_algo)
+        }
+    }
+
+    fn balance_at(&self, address: &str, height: u64) -> Result<u64, String> {
+        self.history.lock().unwrap().balance_at(address, height)
+    }
+
+    fn get_balance(&self, address: &str) -> u64 {
+        self.history.lock().unwrap().latest_balance(address)
+    }
+
+    // Balance counting only blocks with at least `min_confirmations`; the tip
+    // block itself has one confirmation
+    fn confirmed_balance(&self, address: &str, min_confirmations: u64) -> Result<u64, String> {
+        if min_confirmations == 0 {
+            return Ok(self.get_balance(address));
+        }
+
+        let tip = self.state.lock().unwrap().block_height;
+        match (tip + 1).checked_sub(min_confirmations) {
+            Some(height) => self.balance_at(address, height),
+            None => Ok(0),
+        }
+    }
+
+    fn query_contract_state(&self, id: &str, prefix: &str) -> Option<Vec<(String, String)>> {
+        let contracts = self.contracts.lock().unwrap();
+        if contracts.contracts.contains_key(id) {
+            Some(contracts.query_state_prefix(id, prefix))
+        } else {
+            None
+        }
+    }
+
+    fn estimate_gas(&self, id: &str, function: &str, args: Vec<String>) -> Result<u64, String> {
+        self.contracts.lock().unwrap().estimate_gas(id, function, args)
+    }
+
+    fn get_emission(&self) -> serde_json::Value {
+        let consensus = self.consensus.lock().unwrap();
+        let schedule = &consensus.pos.emission;
+        let height = consensus.pos.current_block;
+        let next_halving = schedule.next_halving_height(height);
+        let emitted = schedule.emitted_through(height);
+        let projected = schedule.projected_total();
+
+        serde_json::json!({
+            "height": height,
+            "base_reward": schedule.reward_at(height + 1),
+            "schedule": schedule,
+            "next_halving_height": next_halving,
+            "blocks_until_halving": next_halving - height,
+            "emitted": emitted,
+            // Both are null while a tail reward keeps emission unbounded
+            "projected_total_emission": projected,
+            "remaining_supply": projected.map(|total| total - emitted),
+        })
+    }
+
+    fn submit_proofs(&self, proofs: Vec<consensus::poq::QuantumProof>) -> Vec<Result<u64, consensus::error::ConsensusError>> {
+        self.consensus.lock().unwrap().submit_quantum_proofs(proofs)
+    }
+
+    // Counts an attestation for a stored block and attaches the quorum
+    // certificate once the block is finalized. Returns whether it is final.
+    fn attest(&self, attestation: consensus::finality::Attestation) -> Result<bool, String> {
+        let height = self.chain.lock().unwrap().iter()
+            .find(|block| block.hybrid_hash == attestation.block_hash)
+            .map(|block| block.pos_block.index)
+            .ok_or_else(|| format!("Unknown block {}", attestation.block_hash))?;
+
+        let block_hash = attestation.block_hash.clone();
+        let (certificate, finalized) = {
+            let mut consensus = self.consensus.lock().unwrap();
+            let certificate = consensus.attest(attestation, height).map_err(|e| e.to_string())?;
+            (certificate, consensus.finality.is_finalized(&block_hash))
+        };
+        if let Some(certificate) = certificate {
+            if let Some(block) = self.chain.lock().unwrap().iter_mut().find(|block| block.hybrid_hash == block_hash) {
+                block.quorum_certificate = Some(certificate);
+            }
+        }
+        Ok(finalized)
+    }
+
+    // Current block height and `address`'s stake for governance
+    fn governance_standing(&self, address: &str) -> (u64, u64) {
+        let consensus = self.consensus.lock().unwrap();
+        (consensus.pos.current_block, consensus.pos.governance_stake(address))
+    }
+
+    fn submit_proposal(&self, request: ProposalRequest) -> Result<u64, String> {
+        let check = SignatureCheck {
+            address: request.proposer.clone(),
+            public_key: request.public_key,
+            message: governance::proposal_message(&request.description, request.deadline),
+            signature: request.signature,
+        };
+        if !check.is_valid() {
+            return Err("Invalid signature".to_string());
+        }
+
+        let (height, stake) = self.governance_standing(&request.proposer);
+        self.governance.lock().unwrap()
+            .submit_proposal(&request.proposer, stake, &request.description, request.deadline, height)
+            .map_err(|e| e.to_string())
+    }
+
+    // Casts a signed vote with the voter's current stake, returning its
+    // quadratic voting power
+    fn vote(&self, request: VoteRequest) -> Result<f64, String> {
+        let check = SignatureCheck {
+            address: request.voter.clone(),
+            public_key: request.public_key,
+            message: governance::vote_message(request.proposal_id, request.choice),
+            signature: request.signature,
+        };
+        if !check.is_valid() {
+            return Err("Invalid signature".to_string());
+        }
+
+        let (height, stake) = self.governance_standing(&request.voter);
+        self.governance.lock().unwrap()
+            .vote(request.proposal_id, &request.voter, stake, request.choice, height)
+            .map_err(|e| e.to_string())
+    }
+
+    // A proposal with its vote totals and current outcome
+    fn get_proposal(&self, id: u64) -> Option<serde_json::Value> {
+        let height = self.consensus.lock().unwrap().pos.current_block;
+        let governance = self.governance.lock().unwrap();
+        let proposal = governance.proposals.get(&id)?;
+        Some(serde_json::json!({
+            "id": proposal.id,
+            "description": proposal.description,
+            "proposer": proposal.proposer,
+            "deadline": proposal.deadline,
+            "votes": proposal.votes.len(),
+            "yes_power": proposal.power_for(VoteChoice::Yes),
+            "no_power": proposal.power_for(VoteChoice::No),
+            "outcome": governance.tally(id, height).ok()?,
+        }))
+    }
+
+    // Height and hash of the latest finalized block
+    fn get_finalized(&self) -> (u64, Option<String>) {
+        let consensus = self.consensus.lock().unwrap();
+        (consensus.finality.finalized_height, consensus.finality.finalized_hash.clone())
+    }
+
+    fn get_recent_proofs(&self) -> Vec<consensus::poq::QuantumProof> {
+        consensus::poq::recent_proofs(&self.proofs)
+    }
+
+    fn get_quantum_score(&self, address: &str) -> u64 {
+        *self.quantum_scores.read().unwrap().get(address).unwrap_or(&0)
+    }
+
+    fn get_leaderboard(&self, tier: Option<consensus::poq::DifficultyTier>) -> Vec<(String, u64)> {
+        consensus::poq::leaderboard(&self.tier_scores, tier)
+    }
+
+    // Current validator set, ordered by address
+    fn get_validators(&self) -> Vec<consensus::pos::Validator> {
+        let consensus = self.consensus.lock().unwrap();
+        let mut validators: Vec<_> = consensus.pos.validators.values().cloned().collect();
+        validators.sort_by(|a, b| a.address.cmp(&b.address));
+        validators
+    }
+
+    fn get_slash_history(&self, address: &str) -> Option<Vec<consensus::pos::SlashEvent>> {
+        let consensus = self.consensus.lock().unwrap();
+        if consensus.pos.validators.contains_key(address) {
+            Some(consensus.pos.get_slash_history(address))
+        } else {
+            None
+        }
+    }
+
+    fn get_apr(&self, address: &str) -> f64 {
+        self.consensus.lock().unwrap().pos.estimate_apr(address)
+    }
+
+    // Stake of `address` still unbonding, with the height each part is released at
+    fn get_unbonding(&self, address: &str) -> Vec<consensus::pos::UnbondingEntry> {
+        self.consensus.lock().unwrap().pos.pending_unbonding(address)
+    }
+}
+
+// Extracts the segment between `prefix` and `suffix`, e.g. the address in
+// `/validators/{address}/slashes`
+fn path_param<'a>(path: &'a str, prefix: &str, suffix: &str) -> Option<&'a str> {
+    path.strip_prefix(prefix)
+        .and_then(|rest| rest.strip_suffix(suffix))
+        .filter(|param| !param.is_empty() && !param.contains('/'))
+}
+
+// Looks up a query string parameter, e.g. `height` in `/state?height=5`
+fn query_param(req: &Request<Body>, name: &str) -> Option<String> {
+    req.uri().query()?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value.to_string())
+}
+
+fn text_response(status: StatusCode, body: &str) -> Response<Body> {
+    Response::builder()
+        .status(status)
+        .body(Body::from(body.to_string()))
+        .unwrap()
+}
+
+// Admin routes, along with the privileged `/unjail` and `/faucet`, are
+// disabled unless an admin key is configured, and otherwise require the
+// caller to present it in the `X-Admin-Key` header
+fn admin_rejection(req: &Request<Body>, node: &Arc<Mutex<NyxoraNode>>) -> Option<Response<Body>> {
+    let admin_key = match node.lock().unwrap().admin_key() {
+        Some(key) => key,
+        None => return Some(text_response(StatusCode::FORBIDDEN, "Admin API disabled")),
+    };
+
+    let provided = req.headers()
+        .get("x-admin-key")
+        .map(|value| value.as_bytes());
+
+    match provided {
+        Some(provided) if constant_time_eq(provided, admin_key.as_bytes()) => None,
+        _ => Some(text_response(StatusCode::UNAUTHORIZED, "Invalid admin key")),
+    }
+}
+
+// Compares secrets in time that depends only on their length, so response
+// timing does not reveal how much of a guessed key was right
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+// Checks that every transfer in a chain is signed by its sender and carries
+// the sender's next nonce, counting from genesis. The `/tx` checks only cover
+// this node's mempool, so blocks from peers must be held to them too.
+fn check_transfers(blocks: &[consensus::HybridBlock]) -> Result<(), String> {
+    let mut nonces: HashMap<String, u64> = HashMap::new();
+    for block in blocks {
+        let height = block.pos_block.index;
+        for entry in &block.pos_block.transactions {
+            let tx: Transaction = match serde_json::from_str(entry) {
+                Ok(tx) => tx,
+                Err(_) => continue,
+            };
+            if !tx.has_valid_signature() {
+                return Err(format!("Block {} holds a transaction from {} with an invalid signature", height, tx.from));
+            }
+            let last = nonces.entry(tx.from.clone()).or_insert(0);
+            if tx.nonce != *last + 1 {
+                return Err(format!(
+                    "Block {} holds nonce {} for {}: expected {}",
+                    height, tx.nonce, tx.from, *last + 1
+                ));
+            }
+            *last = tx.nonce;
+        }
+    }
+    Ok(())
+}
+
+// Adds the transactions of `block` to the hash index. Entries that are not
+// transactions are skipped but keep their position.
+fn index_transactions(tx_index: &mut HashMap<String, (u64, usize)>, block: &consensus::HybridBlock) {
+    let height = block.pos_block.index;
+    for (index, entry) in block.pos_block.transactions.iter().enumerate() {
+        if serde_json::from_str::<Transaction>(entry).is_ok() {
+            tx_index.insert(Transaction::entry_hash(entry), (height, index));
+        }
+    }
+}
+
+// Block production for validators: every `block_time` the node proposes a
+// block if it leads the current view. The proposal reaches peers through the
+// `/events` stream and their chain sync.
+async fn produce_blocks(node: NyxoraNode, block_time: std::time::Duration) {
+    let mut interval = tokio::time::interval(block_time);
+    // The first tick completes at once; the first block is due a full period in
+    interval.tick().await;
+    loop {
+        interval.tick().await;
+        node.produce_block_if_leader();
+    }
+}
+
+#[tracing::instrument(skip_all, fields(method = %req.method(), path = %req.uri().path()))]
+async fn handle_request(
+    req: Request<Body>,
+    node: Arc<Mutex<NyxoraNode>>
+) -> Result<Response<Body>, Infallible> {
+    // Requests that did not come over a connection (e.g. in tests) carry no
+    // peer address and are not limited
+    if let Some(peer) = req.extensions().get::<std::net::SocketAddr>() {
+        if node.lock().unwrap().is_banned_ip(peer.ip()) {
+            return Ok(text_response(StatusCode::FORBIDDEN, "Peer is banned"));
+        }
+        let rate_limiter = Arc::clone(&node.lock().unwrap().rate_limiter);
+        if !rate_limiter.allow(peer.ip()) {
+            tracing::warn!(client = %peer.ip(), "rate limited");
+            return Ok(text_response(StatusCode::TOO_MANY_REQUESTS, "Too many requests"));
+        }
+    }
+
+    let cors_origin = {
+        let node = node.lock().unwrap();
+        let state = node.state.lock().unwrap();
+        cors::allowed_origin(&state.config.cors_origins, req.headers())
+    };
+    if req.method() == hyper::Method::OPTIONS {
+        return Ok(cors::preflight(cors_origin));
+    }
+
+    let encoding = compression::negotiate(req.headers());
+    let response = route_request(req, node).await?;
+    tracing::debug!(status = %response.status(), "request handled");
+    let response = match encoding {
+        Some(encoding) => compression::compress(response, encoding).await,
+        None => response,
+    };
+    match cors_origin {
+        Some(origin) => Ok(cors::allow(response, origin)),
+        None => Ok(response),
+    }
+}
+
+async fn route_request(
+    req: Request<Body>,
+    node: Arc<Mutex<NyxoraNode>>
+) -> Result<Response<Body>, Infallible> {
+    match (req.method(), req.uri().path()) {
+        // Liveness: answering at all means the process is up
+        (&hyper::Method::GET, "/health") => Ok(text_response(StatusCode::OK, "OK")),
+        (&hyper::Method::GET, "/ready") => {
+            if node.lock().unwrap().is_ready() {
+                Ok(text_response(StatusCode::OK, "Ready"))
+            } else {
+                Ok(text_response(StatusCode::SERVICE_UNAVAILABLE, "Not ready"))
+            }
+        },
+        (&hyper::Method::GET, "/status") => {
+            let status = node.lock().unwrap().get_status();
+            let json = serde_json::to_string(&status).unwrap();
+            Ok(Response::new(Body::from(json)))
+        },
+        (&hyper::Method::POST, "/stake") => {
+            if node.lock().unwrap().is_paused() {
+                return Ok(text_response(StatusCode::SERVICE_UNAVAILABLE, "Node is paused"));
+            }
+
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            let amount: u64 = String::from_utf8_lossy(&body_bytes).parse().unwrap_or(0);
+
+            let success = node.lock().unwrap().stake_tokens(amount);
+            let response = if success {
+                Response::builder()
+                    .status(StatusCode::OK)
+                    .body(Body::from("Stake successful"))
+            } else {
+                Response::builder()
+                    .status(StatusCode::BAD_REQUEST)
+                    .body(Body::from("Stake failed"))
+            };
+            Ok(response.unwrap())
+        },
+        (&hyper::Method::POST, "/withdraw_rewards") => {
+            if node.lock().unwrap().is_paused() {
+                return Ok(text_response(StatusCode::SERVICE_UNAVAILABLE, "Node is paused"));
+            }
+
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            let amount: u64 = match String::from_utf8_lossy(&body_bytes).trim().parse() {
+                Ok(amount) => amount,
+                Err(_) => return Ok(text_response(StatusCode::BAD_REQUEST, "Expected a numeric amount")),
+            };
+
+            match node.lock().unwrap().withdraw_rewards(amount) {
+                Ok(balance) => {
+                    let json = serde_json::json!({ "withdrawn": amount, "balance": balance });
+                    Ok(Response::new(Body::from(json.to_string())))
+                },
+                Err(e) => Ok(text_response(StatusCode::BAD_REQUEST, &e)),
+            }
+        },
+        (&hyper::Method::POST, "/unjail") => {
+            if let Some(response) = admin_rejection(&req, &node) {
+                return Ok(response);
+            }
+            if node.lock().unwrap().is_paused() {
+                return Ok(text_response(StatusCode::SERVICE_UNAVAILABLE, "Node is paused"));
+            }
+
+            match node.lock().unwrap().unjail() {
+                Ok(()) => Ok(Response::new(Body::from(serde_json::json!({ "jailed": false }).to_string()))),
+                Err(e) => Ok(text_response(StatusCode::BAD_REQUEST, &e)),
+            }
+        },
+        (&hyper::Method::POST, "/faucet") => {
+            if !node.lock().unwrap().state.lock().unwrap().config.testnet {
+                return Ok(text_response(StatusCode::FORBIDDEN, "The faucet is only available on testnet"));
+            }
+            if let Some(response) = admin_rejection(&req, &node) {
+                return Ok(response);
+            }
+            if node.lock().unwrap().is_paused() {
+                return Ok(text_response(StatusCode::SERVICE_UNAVAILABLE, "Node is paused"));
+            }
+
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            let address = match serde_json::from_slice::<serde_json::Value>(&body_bytes) {
+                Ok(body) => body["address"].as_str().map(str::to_string),
+                Err(_) => None,
+            };
+            let address = match address {
+                Some(address) if !address.is_empty() => address,
+                _ => return Ok(text_response(StatusCode::BAD_REQUEST, "Expected a JSON body with an address")),
+            };
+
+            match node.lock().unwrap().faucet(&address) {
+                Ok((amount, balance)) => {
+                    let json = serde_json::json!({ "address": address, "granted": amount, "balance": balance });
+                    Ok(Response::new(Body::from(json.to_string())))
+                },
+                Err(e) => Ok(text_response(StatusCode::TOO_MANY_REQUESTS, &e)),
+            }
+        },
+        (&hyper::Method::POST, "/tx") => {
+            if node.lock().unwrap().is_paused() {
+                return Ok(text_response(StatusCode::SERVICE_UNAVAILABLE, "Node is paused"));
+            }
+
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            match serde_json::from_slice::<Transaction>(&body_bytes) {
+                Ok(tx) => match node.lock().unwrap().submit_transaction(tx) {
+                    Ok(()) => Ok(text_response(StatusCode::OK, "Transaction accepted")),
+                    Err(e) => Ok(text_response(StatusCode::BAD_REQUEST, &e)),
+                },
+                Err(e) => Ok(text_response(StatusCode::BAD_REQUEST, &format!("Invalid transaction: {}", e))),
+            }
+        },
+        (&hyper::Method::POST, "/proof") => {
+            if node.lock().unwrap().is_paused() {
+                return Ok(text_response(StatusCode::SERVICE_UNAVAILABLE, "Node is paused"));
+            }
+
+            // CBOR is picked by content type or `?format=cbor`; JSON otherwise
+            let is_cbor = query_param(&req, "format").as_deref() == Some("cbor")
+                || req.headers().get(hyper::header::CONTENT_TYPE)
+                    .and_then(|value| value.to_str().ok())
+                    .is_some_and(|value| value.starts_with("application/cbor"));
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            let proof = if is_cbor {
+                consensus::poq::QuantumProof::from_cbor(&body_bytes)
+            } else {
+                serde_json::from_slice(&body_bytes).map_err(|e| e.to_string())
+            };
+            let proof = match proof {
+                Ok(proof) => proof,
+                Err(e) => return Ok(text_response(StatusCode::BAD_REQUEST, &format!("Invalid proof: {}", e))),
+            };
+
+            let node = node.lock().unwrap().clone_shared();
+            let result = node.submit_proofs(vec![proof]).remove(0);
+            match result {
+                Ok(reward) => {
+                    let json = serde_json::json!({ "accepted": true, "reward": reward });
+                    Ok(Response::new(Body::from(json.to_string())))
+                },
+                Err(e) => Ok(text_response(StatusCode::BAD_REQUEST, &e.to_string())),
+            }
+        },
+        (&hyper::Method::POST, "/proofs/batch") => {
+            if node.lock().unwrap().is_paused() {
+                return Ok(text_response(StatusCode::SERVICE_UNAVAILABLE, "Node is paused"));
+            }
+
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            let proofs = match serde_json::from_slice::<Vec<consensus::poq::QuantumProof>>(&body_bytes) {
+                Ok(proofs) => proofs,
+                Err(e) => return Ok(text_response(StatusCode::BAD_REQUEST, &format!("Invalid proof batch: {}", e))),
+            };
+            if proofs.len() > MAX_PROOF_BATCH {
+                return Ok(text_response(
+                    StatusCode::BAD_REQUEST,
+                    &format!("Batch of {} proofs exceeds the limit of {}", proofs.len(), MAX_PROOF_BATCH),
+                ));
+            }
+
+            // One status per proof, in submission order. The submission runs on
+            // a shared handle so proof and score readers aren't held up behind it.
+            let node = node.lock().unwrap().clone_shared();
+            let statuses: Vec<serde_json::Value> = node
+                .submit_proofs(proofs)
+                .into_iter()
+                .map(|result| match result {
+                    Ok(reward) => serde_json::json!({ "accepted": true, "reward": reward }),
+                    Err(e) => serde_json::json!({ "accepted": false, "error": e.to_string() }),
+                })
+                .collect();
+            Ok(Response::builder()
+                .header("Content-Type", "application/json")
+                .body(Body::from(serde_json::Value::Array(statuses).to_string()))
+                .unwrap())
+        },
+        (&hyper::Method::POST, "/attest") => {
+            if node.lock().unwrap().is_paused() {
+                return Ok(text_response(StatusCode::SERVICE_UNAVAILABLE, "Node is paused"));
+            }
+
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            let attestation = match serde_json::from_slice::<consensus::finality::Attestation>(&body_bytes) {
+                Ok(attestation) => attestation,
+                Err(e) => return Ok(text_response(StatusCode::BAD_REQUEST, &format!("Invalid attestation: {}", e))),
+            };
+
+            let node = node.lock().unwrap();
+            match node.attest(attestation) {
+                Ok(finalized) => {
+                    let json = serde_json::json!({
+                        "finalized": finalized,
+                        "finalized_height": node.get_finalized().0,
+                    });
+                    Ok(Response::new(Body::from(json.to_string())))
+                },
+                Err(e) => Ok(text_response(StatusCode::BAD_REQUEST, &e)),
+            }
+        },
+        (&hyper::Method::POST, "/governance/propose") => {
+            if node.lock().unwrap().is_paused() {
+                return Ok(text_response(StatusCode::SERVICE_UNAVAILABLE, "Node is paused"));
+            }
+
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            let request = match serde_json::from_slice::<ProposalRequest>(&body_bytes) {
+                Ok(request) => request,
+                Err(e) => return Ok(text_response(StatusCode::BAD_REQUEST, &format!("Invalid proposal: {}", e))),
+            };
+            match node.lock().unwrap().submit_proposal(request) {
+                Ok(id) => Ok(Response::new(Body::from(serde_json::json!({ "id": id }).to_string()))),
+                Err(e) => Ok(text_response(StatusCode::BAD_REQUEST, &e)),
+            }
+        },
+        (&hyper::Method::POST, "/governance/vote") => {
+            if node.lock().unwrap().is_paused() {
+                return Ok(text_response(StatusCode::SERVICE_UNAVAILABLE, "Node is paused"));
+            }
+
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            let request = match serde_json::from_slice::<VoteRequest>(&body_bytes) {
+                Ok(request) => request,
+                Err(e) => return Ok(text_response(StatusCode::BAD_REQUEST, &format!("Invalid vote: {}", e))),
+            };
+            match node.lock().unwrap().vote(request) {
+                Ok(power) => Ok(Response::new(Body::from(serde_json::json!({ "power": power }).to_string()))),
+                Err(e) => Ok(text_response(StatusCode::BAD_REQUEST, &e)),
+            }
+        },
+        (&hyper::Method::GET, path) if path_param(path, "/governance/", "").is_some() => {
+            let proposal = path_param(path, "/governance/", "")
+                .and_then(|id| id.parse().ok())
+                .and_then(|id| node.lock().unwrap().get_proposal(id));
+            match proposal {
+                Some(proposal) => Ok(Response::new(Body::from(proposal.to_string()))),
+                None => Ok(text_response(StatusCode::NOT_FOUND, "Proposal not found")),
+            }
+        },
+        (&hyper::Method::GET, "/finalized") => {
+            let (height, hash) = node.lock().unwrap().get_finalized();
+
+            let json = serde_json::json!({
+                "height": height,
+                "hash": hash,
+            });
+            Ok(Response::new(Body::from(json.to_string())))
+        },
+        (&hyper::Method::POST, "/rpc") => {
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            match rpc::handle(&node.lock().unwrap(), &body_bytes) {
+                Some(response) => Ok(Response::builder()
+                    .header("Content-Type", "application/json")
+                    .body(Body::from(response.to_string()))
+                    .unwrap()),
+                // Only notifications were sent, so there is nothing to return
+                None => Ok(text_response(StatusCode::NO_CONTENT, "")),
+            }
+        },
+        (&hyper::Method::POST, "/verify_signature") => {
+            let body_bytes = hyper::body::to_bytes(req.into_body()).await.unwrap();
+            match serde_json::from_slice::<SignatureCheck>(&body_bytes) {
+                Ok(check) => {
+                    let json = serde_json::json!({ "valid": check.is_valid() });
+                    Ok(Response::new(Body::from(json.to_string())))
+                },
+                Err(e) => Ok(text_response(StatusCode::BAD_REQUEST, &format!("Invalid request: {}", e))),
+            }
+        },
+        (&hyper::Method::GET, "/events") => {
+            let mut events = node.lock().unwrap().subscribe_events();
+            let (mut sender, body) = Body::channel();
+
+            // Stream each event as a server-sent event until the client goes away
+            tokio::spawn(async move {
+                loop {
+                    let event = match events.recv().await {
+                        Ok(event) => event,
+                        Err(tokio::sync::broadcast::error::RecvError::Lagged(_)) => continue,
+                        Err(tokio::sync::broadcast::error::RecvError::Closed) => break,
+                    };
+                    let frame = format!("data: {}\n\n", serde_json::to_string(&event).unwrap());
+                    if sender.send_data(frame.into()).await.is_err() {
+                        break;
+                    }
+                }
+            });
+
+            Ok(Response::builder()
+                .header("Content-Type", "text/event-stream")
+                .header("Cache-Control", "no-cache")
+                .body(body)
+                .unwrap())
+        },
+        (&hyper::Method::GET, "/ws") => {
+            let events = node.lock().unwrap().subscribe_events();
+            Ok(ws::upgrade(req, events))
+        },
+        (&hyper::Method::GET, "/snapshot") => {
+            let snapshot = node.lock().unwrap().export_snapshot();
+            let json = serde_json::to_string(&snapshot).unwrap();
+            Ok(Response::new(Body::from(json)))
+        },
+        (&hyper::Method::GET, "/peers") => {
+            let peers = node.lock().unwrap().get_peers();
+            let json = serde_json::to_string(&peers).unwrap();
+            Ok(Response::new(Body::from(json)))
+        },
+        (&hyper::Method::GET, "/mempool") => {
+            let mempool = node.lock().unwrap().get_mempool();
+            let json = serde_json::to_string(&mempool).unwrap();
+            Ok(Response::new(Body::from(json)))
+        },
+        (&hyper::Method::POST, "/admin/pause") => {
+            if let Some(response) = admin_rejection(&req, &node) {
+                return Ok(response);
+            }
+
+            node.lock().unwrap().set_paused(true);
+            Ok(text_response(StatusCode::OK, "Node paused"))
+        },
+        (&hyper::Method::POST, "/admin/resume") => {
+            if let Some(response) = admin_rejection(&req, &node) {
+                return Ok(response);
+            }
+
+            node.lock().unwrap().set_paused(false);
+            Ok(text_response(StatusCode::OK, "Node resumed"))
+        },
+        (&hyper::Method::GET, "/state") => {
+            let address = query_param(&req, "address");
+            let height = query_param(&req, "height").and_then(|h| h.parse::<u64>().ok());
+
+            let (address, height) = match (address, height) {
+                (Some(address), Some(height)) => (address, height),
+                _ => return Ok(text_response(StatusCode::BAD_REQUEST, "Expected address and numeric height query parameters")),
+            };
+
+            match node.lock().unwrap().balance_at(&address, height) {
+                Ok(balance) => {
+                    let json = serde_json::json!({
+                        "address": address,
+                        "height": height,
+                        "balance": balance,
+                    });
+                    Ok(Response::new(Body::from(json.to_string())))
+                },
+                Err(e) => Ok(text_response(StatusCode::GONE, &e)),
+            }
+        },
+        (&hyper::Method::GET, path) if path_param(path, "/balance/", "").is_some() => {
+            let address = path_param(path, "/balance/", "").unwrap();
+            let min_confirmations = match query_param(&req, "min_confirmations").map(|n| n.parse::<u64>()) {
+                None => None,
+                Some(Ok(n)) => Some(n),
+                Some(Err(_)) => return Ok(text_response(StatusCode::BAD_REQUEST, "min_confirmations must be a non-negative integer")),
+            };
+
+            let node = node.lock().unwrap();
+            let balance = node.get_balance(address);
+
+            let json = match min_confirmations {
+                Some(min_confirmations) => match node.confirmed_balance(address, min_confirmations) {
+                    Ok(confirmed) => serde_json::json!({
+                        "address": address,
+                        "balance": balance,
+                        "confirmed_balance": confirmed,
+                        "min_confirmations": min_confirmations,
+                    }),
+                    Err(e) => return Ok(text_response(StatusCode::GONE, &e)),
+                },
+                None => serde_json::json!({
+                    "address": address,
+                    "balance": balance,
+                }),
+            };
+            Ok(Response::new(Body::from(json.to_string())))
+        },
+        (&hyper::Method::GET, path) if path_param(path, "/nonce/", "").is_some() => {
+            let address = path_param(path, "/nonce/", "").unwrap();
+            let nonce = node.lock().unwrap().next_nonce(address);
+
+            let json = serde_json::json!({
+                "address": address,
+                "nonce": nonce,
+            });
+            Ok(Response::new(Body::from(json.to_string())))
+        },
+        (&hyper::Method::GET, path) if path_param(path, "/tx/", "").is_some() => {
+            let hash = path_param(path, "/tx/", "").unwrap();
+            let status = node.lock().unwrap().transaction_status(hash);
+
+            match status {
+                Some(status) => {
+                    let json = serde_json::to_string(&status).unwrap();
+                    Ok(Response::new(Body::from(json)))
+                },
+                None => Ok(text_response(StatusCode::NOT_FOUND, "Transaction not found")),
+            }
+        },
+        (&hyper::Method::GET, "/chain") => {
+            let chain = node.lock().unwrap().get_chain();
+            let json = serde_json::to_string(&chain).unwrap();
+            Ok(Response::new(Body::from(json)))
+        },
+        (&hyper::Method::GET, "/verify") => {
+            let failing_index = node.lock().unwrap().verify_chain();
+
+            let json = serde_json::json!({
+                "valid": failing_index.is_none(),
+                "failing_index": failing_index,
+            });
+            Ok(Response::new(Body::from(json.to_string())))
+        },
+        (&hyper::Method::GET, "/emission") => {
+            let emission = node.lock().unwrap().get_emission();
+            Ok(Response::new(Body::from(emission.to_string())))
+        },
+        (&hyper::Method::GET, "/metrics") => {
+            // The node lock is held only long enough to reach the metrics
+            let metrics = Arc::clone(&node.lock().unwrap().metrics);
+            Ok(Response::builder()
+                .header("Content-Type", "text/plain; version=0.0.4")
+                .body(Body::from(metrics.render()))
+                .unwrap())
+        },
+        (&hyper::Method::GET, "/proofs") => {
+            // Read through a shared handle so readers don't queue on the node lock
+            let node = node.lock().unwrap().clone_shared();
+            let proofs = node.get_recent_proofs();
+            let json = serde_json::to_string(&proofs).unwrap();
+            Ok(Response::new(Body::from(json)))
+        },
+        (&hyper::Method::GET, path) if path_param(path, "/score/", "").is_some() => {
+            let address = path_param(path, "/score/", "").unwrap();
+            let node = node.lock().unwrap().clone_shared();
+            let score = node.get_quantum_score(address);
+
+            let json = serde_json::json!({
+                "address": address,
+                "score": score,
+            });
+            Ok(Response::new(Body::from(json.to_string())))
+        },
+        (&hyper::Method::GET, "/leaderboard") => {
+            let tier = match query_param(&req, "tier") {
+                Some(name) => match consensus::poq::DifficultyTier::from_name(&name) {
+                    Some(tier) => Some(tier),
+                    None => return Ok(text_response(StatusCode::BAD_REQUEST, "Expected tier low, medium or high")),
+                },
+                None => None,
+            };
+            let node = node.lock().unwrap().clone_shared();
+            let entries: Vec<serde_json::Value> = node.get_leaderboard(tier).into_iter()
+                .map(|(address, score)| serde_json::json!({ "address": address, "score": score }))
+                .collect();
+
+            let json = serde_json::json!({
+                "tier": tier,
+                "leaderboard": entries,
+            });
+            Ok(Response::new(Body::from(json.to_string())))
+        },
+        (&hyper::Method::GET, "/blocks") => {
+            // Return current block height
+            let height = node.lock().unwrap().get_status().block_height;
+            let response = format!("Current block height: {}", height);
+            Ok(Response::new(Body::from(response)))
+        },
+        (&hyper::Method::GET, path) if path_param(path, "/apr/", "").is_some() => {
+            let address = path_param(path, "/apr/", "").unwrap();
+            let apr = node.lock().unwrap().get_apr(address);
+
+            let json = serde_json::json!({
+                "address": address,
+                "apr": apr,
+            });
+            Ok(Response::new(Body::from(json.to_string())))
+        },
+        (&hyper::Method::GET, path) if path_param(path, "/unbonding/", "").is_some() => {
+            let address = path_param(path, "/unbonding/", "").unwrap();
+            let unbonding = node.lock().unwrap().get_unbonding(address);
+            let json = serde_json::to_string(&unbonding).unwrap();
+            Ok(Response::new(Body::from(json)))
+        },
+        (&hyper::Method::GET, path) if path_param(path, "/validators/", "/slashes").is_some() => {
+            let address = path_param(path, "/validators/", "/slashes").unwrap();
+
+            match node.lock().unwrap().get_slash_history(address) {
+                Some(history) => {
+                    let json = serde_json::to_string(&history).unwrap();
+                    Ok(Response::new(Body::from(json)))
+                },
+                None => {
+                    Ok(Response::builder()
+                        .status(StatusCode::NOT_FOUND)
+                        .body(Body::from("Validator not found"))
+                        .unwrap())
+                }
+            }
+        },
+        (&hyper::Method::GET, path) if path_param(path, "/contract/", "/state").is_some() => {
+            let id = path_param(path, "/contract/", "/state").unwrap();
+            let prefix = query_param(&req, "prefix").unwrap_or_default();
+
+            match node.lock().unwrap().query_contract_state(id, &prefix) {
+                Some(entries) => {
+                    let state: serde_json::Map<String, serde_json::Value> = entries.into_iter()
+                        .map(|(key, value)| (key, serde_json::Value::String(value)))
+                        .collect();
+                    Ok(Response::new(Body::from(serde_json::Value::Object(state).to_string())))
+                },
+                None => Ok(text_response(StatusCode::NOT_FOUND, "Contract not found")),
+            }
+        },
+        _ => {
+            Ok(Response::builder()
+                .status(StatusCode::NOT_FOUND)
+                .body(Body::from("Not found"))
+                .unwrap())
+        }
+    }
+}
+
+// Chain files are binary as written by the node; JSON dumps of `/chain` are
+// accepted too
+fn read_chain_file(path: &str) -> Result<Vec<consensus::HybridBlock>, String> {
+    let bytes = fs::read(path).map_err(|e| format!("Could not read chain file '{}': {}", path, e))?;
+    consensus::encoding::from_bytes(&bytes).or_else(|binary_error| {
+        serde_json::from_slice(&bytes).map_err(|_| format!("Chain file '{}' is not a valid chain: {}", path, binary_error))
+    })
+}
+
+fn run_replay(genesis_path: &str, chain_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+    let genesis: consensus::replay::Genesis = serde_json::from_str(&fs::read_to_string(genesis_path)?)?;
+    let blocks = read_chain_file(chain_path)?;
+
+    let state = match consensus::replay::replay_from_genesis(&genesis, &blocks) {
+        Ok(state) => state,
+        Err(e) => {
+            eprintln!("Replay failed: {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    println!("Replayed {} blocks to height {}", blocks.len(), state.height);
+    println!("Replayed state root: {}", state.state_root);
+
+    let matches = match blocks.last() {
+        Some(tip) => {
+            println!("Committed state root: {}", tip.state_root);
+            tip.state_root == state.state_root
+        },
+        None => true,
+    };
+
+    if matches {
+        println!("State root matches the chain tip");
+    } else {
+        eprintln!("State root mismatch: the chain does not reproduce its committed state");
+        std::process::exit(1);
+    }
+
+    Ok(())
+}
+
+// Verifies a chain file, returning how many blocks it holds or which block
+// fails and why
+fn verify_chain_file(path: &str, hash_algo: HashAlgo) -> Result<usize, String> {
+    let blocks = read_chain_file(path)?;
+    match BlockVerifier::new(hash_algo).first_failure(&blocks) {
+        Some((index, reason)) => Err(format!("Block {} is invalid: {}", index, reason)),
+        None => Ok(blocks.len()),
+    }
+}
+
+fn run_verify(chain_path: &str, hash_algo: HashAlgo) {
+    match verify_chain_file(chain_path, hash_algo) {
+        Ok(count) => println!("Chain is valid: {} blocks verified", count),
+        Err(e) => {
+            eprintln!("Verification failed: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let cli = Cli::parse();
+
+    // Log filtering follows RUST_LOG, e.g. `RUST_LOG=nyxora_node=debug`
+    tracing_subscriber::fmt()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .init();
+
+    match &cli.command {
+        Some(Command::Replay { genesis, chain }) => return run_replay(genesis, chain),
+        Some(Command::Verify { chain, hash_algo }) => {
+            run_verify(chain, *hash_algo);
+            return Ok(());
+        },
+        None => {},
+    }
+
+    // Load configuration
+    let config = if fs::metadata(&cli.config).is_ok() {
+        let contents = fs::read_to_string(&cli.config)?;
+        match serde_json::from_str(&contents) {
+            Ok(config) => config,
+            Err(e) => {
+                tracing::error!(config = %cli.config, "invalid configuration file: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        tracing::warn!(config = %cli.config, "configuration file not found, using defaults");
+        NodeConfig::default()
+    };
+
+    // Update config based on CLI args
+    let config = NodeConfig {
+        is_validator: cli.validator,
+        ..config
+    };
+
+    if let Err(e) = config.validate() {
+        tracing::error!(config = %cli.config, "invalid configuration: {}", e);
+        std::process::exit(1);
+    }
+
+    tracing::info!(
+        chain_id = %config.chain_id,
+        validator = config.is_validator,
+        quantum_enabled = config.quantum_enabled,
+        "starting node"
+    );
+
+    // Create and start the node
+    let node = NyxoraNode::new(config);
+
+    // Validators propose blocks from within the server's background tasks
+    if cli.validator {
+        tracing::info!(block_time_secs = node.get_status().config.block_time_secs, "validator node started");
+    }
+
+    // Start the HTTP server
+    node.start_server(cli.port, None).await?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_node(config: NodeConfig) -> Arc<Mutex<NyxoraNode>> {
+        NyxoraNode::new(config).clone_for_hyper()
+    }
+
+    fn validator_config() -> NodeConfig {
+        NodeConfig {
+            is_validator: true,
+            admin_key: Some("secret".to_string()),
+            ..NodeConfig::default()
+        }
+    }
+
+    async fn send(node: &Arc<Mutex<NyxoraNode>>, req: Request<Body>) -> (StatusCode, String) {
+        let response = handle_request(req, Arc::clone(node)).await.unwrap();
+        let status = response.status();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        (status, String::from_utf8_lossy(&body).to_string())
+    }
+
+    fn admin_request(path: &str, key: Option<&str>) -> Request<Body> {
+        let mut builder = Request::builder().method("POST").uri(path);
+        if let Some(key) = key {
+            builder = builder.header("X-Admin-Key", key);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    fn stake_request(amount: u64) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/stake")
+            .body(Body::from(amount.to_string()))
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stake_rejected_while_paused() {
+        let node = test_node(validator_config());
+
+        let (status, _) = send(&node, admin_request("/admin/pause", Some("secret"))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = send(&node, stake_request(100)).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+        let (status, _) = send(&node, tx_request(&transfer(1, 10))).await;
+        assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+        // Reads keep working while paused
+        let (status, body) = send(&node, Request::get("/status").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("\"paused\":true"));
+
+        let (status, _) = send(&node, admin_request("/admin/resume", Some("secret"))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, _) = send(&node, stake_request(100)).await;
+        assert_eq!(status, StatusCode::OK);
+    }
+
+    fn tx_request(tx: &Transaction) -> Request<Body> {
+        Request::builder()
+            .method("POST")
+            .uri("/tx")
+            .body(Body::from(serde_json::to_string(tx).unwrap()))
+            .unwrap()
+    }
+
+    // Seeds of the accounts that sign test transactions
+    const ALICE: [u8; 32] = [1; 32];
+    const BOB: [u8; 32] = [2; 32];
+    const CAROL: [u8; 32] = [3; 32];
+    const DAVE: [u8; 32] = [4; 32];
+
+    fn address_of(seed: &[u8]) -> String {
+        crypto::address_from_public_key(&crypto::public_key_hex(seed).unwrap()).unwrap()
+    }
+
+    // `tx` sent from and signed by the account with `seed`
+    fn signed(seed: &[u8], tx: Transaction) -> Transaction {
+        let mut tx = Transaction { from: address_of(seed), ..tx };
+        tx.sign(seed).unwrap();
+        tx
+    }
+
+    fn unsigned_transfer(nonce: u64, amount: u64) -> Transaction {
+        Transaction {
+            to: "Qbob".to_string(),
+            amount,
+            nonce,
+            ..Default::default()
+        }
+    }
+
+    // Transfer from ALICE to Qbob
+    fn transfer(nonce: u64, amount: u64) -> Transaction {
+        signed(&ALICE, unsigned_transfer(nonce, amount))
+    }
+
+    // Credits `address` in the balance history, as if it had received funds
+    fn fund(node: &Arc<Mutex<NyxoraNode>>, address: &str, amount: u64) {
+        node.lock().unwrap().history.lock().unwrap()
+            .record(0, [(address.to_string(), amount)].into_iter().collect());
+    }
+
+    #[tokio::test]
+    async fn test_submitted_transaction_appears_in_mempool() {
+        let node = test_node(NodeConfig::default());
+
+        let (status, _) = send(&node, tx_request(&transfer(1, 25))).await;
+        assert_eq!(status, StatusCode::OK);
+
+        let (status, body) = send(&node, Request::get("/mempool").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let mempool: Vec<Transaction> = serde_json::from_str(&body).unwrap();
+        assert_eq!(mempool, vec![transfer(1, 25)]);
+
+        let malformed = Request::post("/tx").body(Body::from("not json")).unwrap();
+        let (status, _) = send(&node, malformed).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_needs_sender_signature() {
+        let node = test_node(NodeConfig::default());
+        let alice = address_of(&ALICE);
+
+        // Signed by Carol, but claiming to spend Alice's funds
+        let forged = Transaction { from: alice.clone(), ..signed(&CAROL, unsigned_transfer(1, 10)) };
+        let (status, body) = send(&node, tx_request(&forged)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, format!("Invalid signature for transaction from {}", alice));
+
+        // Unsigned, or changed after signing
+        let unsigned = Transaction { from: alice.clone(), ..unsigned_transfer(1, 10) };
+        assert_eq!(send(&node, tx_request(&unsigned)).await.0, StatusCode::BAD_REQUEST);
+        let altered = Transaction { amount: 1_000, ..transfer(1, 10) };
+        assert_eq!(send(&node, tx_request(&altered)).await.0, StatusCode::BAD_REQUEST);
+
+        assert!(node.lock().unwrap().get_mempool().is_empty());
+        assert_eq!(node.lock().unwrap().next_nonce(&alice), 1);
+        assert_eq!(send(&node, tx_request(&transfer(1, 10))).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_nonces() {
+        let node = test_node(NodeConfig::default());
+        let next_nonce = |body: String| serde_json::from_str::<serde_json::Value>(&body).unwrap()["nonce"].clone();
+
+        let nonce_request = || Request::get(format!("/nonce/{}", address_of(&ALICE))).body(Body::empty()).unwrap();
+        let (status, body) = send(&node, nonce_request()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(next_nonce(body), 1);
+
+        let (status, _) = send(&node, tx_request(&transfer(1, 10))).await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = send(&node, nonce_request()).await;
+        assert_eq!(next_nonce(body), 2);
+
+        // Replaying the same transaction is rejected
+        let (status, body) = send(&node, tx_request(&transfer(1, 10))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, format!("Invalid nonce 1 for {}: expected 2", address_of(&ALICE)));
+
+        // So is skipping ahead
+        let (status, _) = send(&node, tx_request(&transfer(3, 10))).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        assert_eq!(node.lock().unwrap().get_mempool(), vec![transfer(1, 10)]);
+    }
+
+    #[tokio::test]
+    async fn test_proposal_drains_mempool() {
+        let node = test_node(validator_config());
+        fund(&node, &address_of(&ALICE), 10_000);
+        for amount in 1..=(DEFAULT_MAX_BLOCK_TXS as u64 + 5) {
+            send(&node, tx_request(&transfer(amount, amount))).await;
+        }
+
+        let block = node.lock().unwrap().propose_block().unwrap();
+
+        assert_eq!(block.pos_block.transactions.len(), DEFAULT_MAX_BLOCK_TXS);
+        assert_eq!(block.pos_block.transactions[0], transfer(1, 1).to_block_entry());
+        assert_eq!(node.lock().unwrap().get_mempool().len(), 5);
+        assert_eq!(node.lock().unwrap().get_status().block_height, 1);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn test_block_production_follows_leadership() {
+        let node = NyxoraNode::new(validator_config());
+        let block_time = std::time::Duration::from_secs(node.get_status().config.block_time_secs);
+        let production = tokio::spawn(produce_blocks(node.clone_shared(), block_time));
+
+        // As the only validator, the node leads every height. The paused
+        // clock skips ahead through three ticks.
+        tokio::time::sleep(block_time * 3 + block_time / 2).await;
+        assert_eq!(node.get_status().block_height, 3);
+        assert_eq!(node.get_chain().len(), 4);
+
+        // A validator holding nearly all the stake takes over leadership
+        {
+            let mut consensus = node.consensus.lock().unwrap();
+            consensus.pos.activation_delay = 0;
+            consensus.register_validator("Qwhale".to_string(), 1_000_000_000_000);
+        }
+        tokio::time::sleep(block_time * 3).await;
+        assert_eq!(node.get_status().block_height, 3);
+        production.abort();
+
+        // Non-validators never propose
+        let observer = NyxoraNode::new(NodeConfig::default());
+        assert!(observer.produce_block_if_leader().is_none());
+        assert_eq!(observer.get_status().block_height, 0);
+    }
+
+    #[tokio::test]
+    async fn test_cors_preflight_for_allowed_origin() {
+        let node = test_node(NodeConfig { cors_origins: vec!["https://dash.example".to_string()], ..NodeConfig::default() });
+        let preflight = Request::builder()
+            .method("OPTIONS")
+            .uri("/tx")
+            .header("Origin", "https://dash.example")
+            .header("Access-Control-Request-Method", "POST")
+            .body(Body::empty())
+            .unwrap();
+
+        let response = handle_request(preflight, Arc::clone(&node)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let headers = response.headers();
+        assert_eq!(headers["access-control-allow-origin"], "https://dash.example");
+        assert_eq!(headers["access-control-allow-methods"], "GET, POST, OPTIONS");
+        assert_eq!(headers["access-control-allow-headers"], "Content-Type, X-Admin-Key");
+
+        // Ordinary responses carry the origin too
+        let get = Request::get("/health").header("Origin", "https://dash.example").body(Body::empty()).unwrap();
+        let response = handle_request(get, Arc::clone(&node)).await.unwrap();
+        assert_eq!(response.headers()["access-control-allow-origin"], "https://dash.example");
+        assert_eq!(response.headers()["vary"], "Origin");
+
+        // A wildcard admits any origin
+        let open = test_node(NodeConfig { cors_origins: vec!["*".to_string()], ..NodeConfig::default() });
+        let get = Request::get("/health").header("Origin", "https://anywhere.example").body(Body::empty()).unwrap();
+        let response = handle_request(get, open).await.unwrap();
+        assert_eq!(response.headers()["access-control-allow-origin"], "*");
+    }
+
+    #[tokio::test]
+    async fn test_cors_headers_omitted_for_other_origins() {
+        let node = test_node(NodeConfig { cors_origins: vec!["https://dash.example".to_string()], ..NodeConfig::default() });
+
+        let preflight = Request::builder()
+            .method("OPTIONS")
+            .uri("/tx")
+            .header("Origin", "https://evil.example")
+            .body(Body::empty())
+            .unwrap();
+        let response = handle_request(preflight, Arc::clone(&node)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        assert!(!response.headers().contains_key("access-control-allow-origin"));
+        assert!(!response.headers().contains_key("access-control-allow-methods"));
+
+        let get = Request::get("/health").header("Origin", "https://evil.example").body(Body::empty()).unwrap();
+        let response = handle_request(get, node).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(!response.headers().contains_key("access-control-allow-origin"));
+    }
+
+    #[tokio::test]
+    async fn test_proposal_caps_at_configured_tx_count() {
+        let node = test_node(NodeConfig { max_block_txs: 3, ..validator_config() });
+        fund(&node, &address_of(&ALICE), 10_000);
+        for nonce in 1..=5 {
+            send(&node, tx_request(&transfer(nonce, 10))).await;
+        }
+
+        let block = node.lock().unwrap().propose_block().unwrap();
+        assert_eq!(block.pos_block.transactions.len(), 3);
+        assert_eq!(node.lock().unwrap().get_mempool(), vec![transfer(4, 10), transfer(5, 10)]);
+        assert_eq!(node.lock().unwrap().verify_chain(), None);
+    }
+
+    #[tokio::test]
+    async fn test_proposal_caps_at_configured_bytes() {
+        // Room for two and a half transfers
+        let entry_size = transfer(1, 10).to_block_entry().len();
+        let node = test_node(NodeConfig { max_block_bytes: entry_size * 5 / 2, ..validator_config() });
+        fund(&node, &address_of(&ALICE), 10_000);
+        for nonce in 1..=4 {
+            send(&node, tx_request(&transfer(nonce, 10))).await;
+        }
+
+        let block = node.lock().unwrap().propose_block().unwrap();
+        assert_eq!(block.pos_block.transactions, vec![transfer(1, 10).to_block_entry(), transfer(2, 10).to_block_entry()]);
+        assert_eq!(node.lock().unwrap().get_mempool(), vec![transfer(3, 10), transfer(4, 10)]);
+    }
+
+    #[tokio::test]
+    async fn test_transaction_status_follows_inclusion() {
+        let node = test_node(validator_config());
+        fund(&node, &address_of(&ALICE), 10_000);
+        let hash = transfer(1, 10).hash();
+        let status_of = |body: String| serde_json::from_str::<serde_json::Value>(&body).unwrap();
+
+        let (status, _) = send(&node, Request::get(format!("/tx/{}", hash)).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+
+        send(&node, tx_request(&transfer(1, 10))).await;
+        let (status, body) = send(&node, Request::get(format!("/tx/{}", hash)).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(status_of(body)["status"], "pending");
+
+        node.lock().unwrap().propose_block().unwrap();
+        let (_, body) = send(&node, Request::get(format!("/tx/{}", hash)).body(Body::empty()).unwrap()).await;
+        let body = status_of(body);
+        assert_eq!(body["status"], "confirmed");
+        assert_eq!(body["height"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_tx_lookup_returns_included_transaction() {
+        let node = test_node(validator_config());
+        fund(&node, &address_of(&ALICE), 10_000);
+        send(&node, tx_request(&transfer(1, 10))).await;
+        send(&node, tx_request(&transfer(2, 10))).await;
+        node.lock().unwrap().propose_block().unwrap();
+
+        let (status, body) = send(&node, Request::get(format!("/tx/{}", transfer(2, 10).hash())).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["height"], 1);
+        assert_eq!(body["index"], 1);
+        assert_eq!(serde_json::from_value::<Transaction>(body["transaction"].clone()).unwrap(), transfer(2, 10));
+
+        let (status, body) = send(&node, Request::get(format!("/tx/{}", "ab".repeat(32))).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body, "Transaction not found");
+    }
+
+    #[tokio::test]
+    async fn test_included_transactions_leave_mempool() {
+        let node = test_node(validator_config());
+        fund(&node, &address_of(&ALICE), 10_000);
+        send(&node, tx_request(&transfer(1, 10))).await;
+        send(&node, tx_request(&transfer(2, 20))).await;
+        // A conflicting, still time-locked transaction reusing nonce 1 goes
+        // stale once nonce 1 is included
+        let conflicting = signed(&ALICE, Transaction { valid_after: Some(u64::MAX), ..unsigned_transfer(1, 99) });
+        let now = node.lock().unwrap().clock.now_secs();
+        node.lock().unwrap().mempool.lock().unwrap().push(conflicting, now);
+
+        let block = node.lock().unwrap().propose_block().unwrap();
+        assert_eq!(block.pos_block.transactions, vec![transfer(1, 10).to_block_entry(), transfer(2, 20).to_block_entry()]);
+
+        let (status, body) = send(&node, Request::get("/mempool").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(serde_json::from_str::<Vec<Transaction>>(&body).unwrap(), vec![]);
+
+        // Later nonces from the same sender stay pending
+        send(&node, tx_request(&transfer(3, 30))).await;
+        assert_eq!(node.lock().unwrap().get_mempool(), vec![transfer(3, 30)]);
+    }
+
+    #[tokio::test]
+    async fn test_proposal_prefers_higher_fees() {
+        let node = test_node(validator_config());
+        for sender in [ALICE, CAROL, DAVE] {
+            fund(&node, &address_of(&sender), 100);
+        }
+
+        let from = |sender: [u8; 32], nonce, fee| signed(&sender, Transaction { fee, ..unsigned_transfer(nonce, 10) });
+        let pending = [from(ALICE, 1, 1), from(ALICE, 2, 50), from(CAROL, 1, 20), from(DAVE, 1, 5)];
+        for tx in &pending {
+            assert_eq!(send(&node, tx_request(tx)).await.0, StatusCode::OK);
+        }
+
+        let block = node.lock().unwrap().propose_block().unwrap();
+
+        // Alice's high fee can't jump ahead of its own earlier nonce
+        let expected: Vec<String> = [&pending[2], &pending[3], &pending[0], &pending[1]]
+            .iter()
+            .map(|tx| tx.to_block_entry())
+            .collect();
+        assert_eq!(block.pos_block.transactions, expected);
+        // Carol paid both its transfer and its fee
+        assert_eq!(node.lock().unwrap().get_balance(&address_of(&CAROL)), 70);
+
+        let node = node.lock().unwrap();
+        let address = node.get_status().config.address;
+        let consensus = node.consensus.lock().unwrap();
+        assert_eq!(consensus.pos.validators[&address].rewards, consensus.pos.block_reward(1) + 76);
+    }
+
+    #[tokio::test]
+    async fn test_time_locked_transaction_waits_in_mempool() {
+        let node = test_node(validator_config());
+        fund(&node, &address_of(&ALICE), 10_000);
+        fund(&node, &address_of(&CAROL), 100);
+        let clock = Arc::new(consensus::clock::MockClock::new(1_000));
+        node.lock().unwrap().clock = clock.clone();
+
+        let locked = signed(&ALICE, Transaction { valid_after: Some(1_060), ..unsigned_transfer(1, 10) });
+        let carol = signed(&CAROL, unsigned_transfer(1, 5));
+        for tx in [&locked, &transfer(2, 20), &carol] {
+            assert_eq!(send(&node, tx_request(tx)).await.0, StatusCode::OK);
+        }
+
+        // Alice's later nonce waits behind its locked transaction
+        let block = node.lock().unwrap().propose_block().unwrap();
+        assert_eq!(block.pos_block.transactions, vec![carol.to_block_entry()]);
+        assert_eq!(node.lock().unwrap().get_mempool(), vec![locked.clone(), transfer(2, 20)]);
+
+        clock.set(1_060);
+        let block = node.lock().unwrap().propose_block().unwrap();
+        assert_eq!(block.pos_block.transactions, vec![locked.to_block_entry(), transfer(2, 20).to_block_entry()]);
+        assert!(node.lock().unwrap().get_mempool().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_events_stream_block_proposals() {
+        use hyper::body::HttpBody;
+
+        let node = test_node(validator_config());
+        let response = handle_request(Request::get("/events").body(Body::empty()).unwrap(), Arc::clone(&node))
+            .await
+            .unwrap();
+        assert_eq!(response.headers()["Content-Type"], "text/event-stream");
+
+        node.lock().unwrap().propose_block().unwrap();
+
+        let mut body = response.into_body();
+        let frame = tokio::time::timeout(std::time::Duration::from_secs(5), body.data())
+            .await
+            .expect("no event received")
+            .unwrap()
+            .unwrap();
+        assert_eq!(String::from_utf8_lossy(&frame), "data: {\"BlockProposed\":1}\n\n");
+    }
+
+    #[tokio::test]
+    async fn test_websocket_receives_new_blocks() {
+        use futures_util::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message;
+
+        type Socket = tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>;
+        async fn receive(socket: &mut Socket) -> serde_json::Value {
+            let message = tokio::time::timeout(std::time::Duration::from_secs(5), socket.next())
+                .await
+                .expect("no message received");
+            serde_json::from_str(&message.unwrap().unwrap().into_text().unwrap()).unwrap()
+        }
+
+        let node = NyxoraNode::new(validator_config());
+        let handle = node.clone_shared();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { node.serve(listener, None).await.unwrap() });
+
+        let (mut socket, _) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr)).await.unwrap();
+
+        socket.send(Message::Text(r#"{"subscribe": ["newMempool"]}"#.to_string())).await.unwrap();
+        assert_eq!(receive(&mut socket).await["error"], "Unknown topic 'newMempool'");
+        socket.send(Message::Text(r#"{"subscribe": ["newBlock"]}"#.to_string())).await.unwrap();
+        assert_eq!(receive(&mut socket).await, serde_json::json!({ "subscribed": ["newBlock"] }));
+
+        handle.propose_block().unwrap();
+        assert_eq!(
+            receive(&mut socket).await,
+            serde_json::json!({ "topic": "newBlock", "event": { "BlockProposed": 1 } })
+        );
+
+        // The node keeps serving after the client goes away
+        socket.close(None).await.unwrap();
+        handle.propose_block().unwrap();
+        assert_eq!(http_get(addr, "/health").await, "OK");
+    }
+
+    #[tokio::test]
+    async fn test_verify_stored_chain() {
+        let node = test_node(validator_config());
+        for _ in 0..4 {
+            node.lock().unwrap().propose_block().unwrap();
+        }
+
+        let (status, body) = send(&node, Request::get("/chain").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let chain: Vec<consensus::HybridBlock> = serde_json::from_str(&body).unwrap();
+        assert_eq!(chain.len(), 5);
+        assert_eq!(chain[0].pos_block.index, 0);
+        assert_eq!(chain[1].pos_block.prev_hash, chain[0].pos_block.hash);
+
+        let (_, body) = send(&node, Request::get("/verify").body(Body::empty()).unwrap()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json, serde_json::json!({ "valid": true, "failing_index": null }));
+
+        node.lock().unwrap().chain.lock().unwrap()[3].pos_block.hash = "corrupted".to_string();
+        let (_, body) = send(&node, Request::get("/verify").body(Body::empty()).unwrap()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json, serde_json::json!({ "valid": false, "failing_index": 3 }));
+    }
+
+    #[tokio::test]
+    async fn test_state_at_past_height() {
+        let node = test_node(NodeConfig::default());
+        {
+            let node = node.lock().unwrap();
+            let mut history = node.history.lock().unwrap();
+            history.record(1, [("Qalice".to_string(), 100)].into_iter().collect());
+            // A later transfer moves funds from alice to bob
+            history.record(5, [("Qalice".to_string(), 40), ("Qbob".to_string(), 60)].into_iter().collect());
+        }
+
+        let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+        let (status, body) = send(&node, get("/state?address=Qalice&height=3")).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["balance"], 100);
+
+        let (_, body) = send(&node, get("/state?address=Qalice&height=5")).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["balance"], 40);
+
+        node.lock().unwrap().history.lock().unwrap().prune_before(4);
+        let (status, _) = send(&node, get("/state?address=Qalice&height=3")).await;
+        assert_eq!(status, StatusCode::GONE);
+
+        let (status, _) = send(&node, get("/state?address=Qalice")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_balance_endpoint() {
+        let node = test_node(NodeConfig::default());
+        node.lock().unwrap().history.lock().unwrap()
+            .record(1, [("Qalice".to_string(), 250)].into_iter().collect());
+
+        let (status, body) = send(&node, Request::get("/balance/Qalice").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["balance"], 250);
+
+        // Unknown addresses hold nothing
+        let (_, body) = send(&node, Request::get("/balance/Qnobody").body(Body::empty()).unwrap()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["balance"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_genesis_allocations() {
+        let funded = "Qfunded000000000000000000000000000000000".to_string();
+        let allocations: HashMap<String, u64> = [(funded.clone(), 5_000)].into_iter().collect();
+        let config = NodeConfig { genesis_allocations: allocations.clone(), ..validator_config() };
+        let node = test_node(config.clone());
+
+        let (status, body) = send(&node, Request::get(format!("/balance/{}", funded)).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["balance"], 5_000);
+
+        let genesis_hash = |node: &Arc<Mutex<NyxoraNode>>| node.lock().unwrap().get_chain()[0].hybrid_hash.clone();
+        assert_eq!(genesis_hash(&node), genesis_hash(&test_node(config)));
+        assert_ne!(genesis_hash(&node), genesis_hash(&test_node(validator_config())));
+        let other = NodeConfig { genesis_allocations: [(funded, 5_001)].into_iter().collect(), ..validator_config() };
+        assert_ne!(genesis_hash(&node), genesis_hash(&test_node(other)));
+    }
+
+    #[tokio::test]
+    async fn test_included_transfers_move_balances() {
+        let node = test_node(validator_config());
+        fund(&node, &address_of(&ALICE), 50);
+        let first = signed(&ALICE, Transaction { fee: 2, ..unsigned_transfer(1, 30) });
+        let carol = signed(&CAROL, unsigned_transfer(1, 5));
+        for tx in [&first, &transfer(2, 30), &carol] {
+            assert_eq!(send(&node, tx_request(tx)).await.0, StatusCode::OK);
+        }
+
+        // Alice can't cover a second transfer and Carol holds nothing, so
+        // both overdrafts stay pending
+        let block = node.lock().unwrap().propose_block().unwrap();
+        assert_eq!(block.pos_block.transactions, vec![first.to_block_entry()]);
+        assert_eq!(node.lock().unwrap().get_mempool(), vec![transfer(2, 30), carol]);
+
+        let balance_of = |body: String| serde_json::from_str::<serde_json::Value>(&body).unwrap()["balance"].clone();
+        let (_, body) = send(&node, Request::get(format!("/balance/{}", address_of(&ALICE))).body(Body::empty()).unwrap()).await;
+        assert_eq!(balance_of(body), 18);
+        let (_, body) = send(&node, Request::get("/balance/Qbob").body(Body::empty()).unwrap()).await;
+        assert_eq!(balance_of(body), 30);
+    }
+
+    // Gives `node` consensus settings other than the defaults and returns
+    // them, so a test can check that syncing keeps them
+    fn customize_consensus(node: &NyxoraNode) -> (u64, u64, u64, u64) {
+        let mut consensus = node.consensus.lock().unwrap();
+        consensus.pos.cooldown_blocks = 2;
+        consensus.pos.activation_delay = 7;
+        consensus.pos.unbonding_period = 50;
+        consensus_settings(&consensus)
+    }
+
+    fn consensus_settings(consensus: &consensus::HybridConsensus) -> (u64, u64, u64, u64) {
+        let pos = &consensus.pos;
+        (pos.target_block_time, pos.cooldown_blocks, pos.activation_delay, pos.unbonding_period)
+    }
+
+    #[tokio::test]
+    async fn test_snapshot_round_trip() {
+        let source = NyxoraNode::new(validator_config());
+        source.history.lock().unwrap().record(0, [(address_of(&ALICE), 50)].into_iter().collect());
+        source.consensus.lock().unwrap().pos.delegate("Qcarol".to_string(), &validator_config().address, 200);
+        source.submit_transaction(transfer(1, 30)).unwrap();
+        source.propose_block().unwrap();
+        let tip_hash = source.get_chain()[1].pos_block.hash.clone();
+        let snapshot = source.export_snapshot();
+        assert_eq!(snapshot.block_height, 1);
+
+        let source_handle = source.clone_shared();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move { source.serve(listener, None).await.unwrap() });
+
+        let fresh = NyxoraNode::new(NodeConfig {
+            peers: vec!["127.0.0.1:1".to_string(), addr],
+            block_time_secs: 30,
+            ..NodeConfig::default()
+        });
+        let settings = customize_consensus(&fresh);
+        // A snapshot of some other block, or one whose validators were
+        // tampered with, is refused
+        assert_eq!(fresh.sync_from_snapshot("0xother").await, None);
+        let mut forged = snapshot.clone();
+        forged.validators.values_mut().for_each(|validator| validator.stake += 1);
+        assert_eq!(
+            fresh.import_snapshot(forged, &tip_hash),
+            Err("Snapshot validator set does not match its state root".to_string())
+        );
+        assert_eq!(fresh.get_status().block_height, 0);
+
+        assert_eq!(fresh.sync_from_snapshot(&tip_hash).await, Some(1));
+        assert_eq!(fresh.get_status().block_height, 1);
+        {
+            let (ours, theirs) = (source_handle.consensus.lock().unwrap(), fresh.consensus.lock().unwrap());
+            assert_eq!(serde_json::to_value(&theirs.pos.validators).unwrap(), serde_json::to_value(&ours.pos.validators).unwrap());
+            assert_eq!(theirs.pos.delegations, ours.pos.delegations);
+            assert_eq!(theirs.pos.state_root(), ours.pos.state_root());
+            assert_eq!(consensus_settings(&theirs), settings);
+        }
+        for address in [address_of(&ALICE), "Qbob".to_string(), "Qcarol".to_string()] {
+            assert_eq!(fresh.get_balance(&address), source_handle.get_balance(&address));
+        }
+        assert_eq!(fresh.get_balance("Qbob"), 30);
+        assert_eq!(fresh.next_nonce(&address_of(&ALICE)), 2);
+    }
+
+    #[tokio::test]
+    async fn test_balance_min_confirmations() {
+        let node = test_node(NodeConfig::default());
+        {
+            let node = node.lock().unwrap();
+            let mut history = node.history.lock().unwrap();
+            history.record(2, [("Qalice".to_string(), 100)].into_iter().collect());
+            history.record(10, [("Qalice".to_string(), 300)].into_iter().collect());
+            node.state.lock().unwrap().block_height = 10;
+        }
+        let get = |uri: &str| Request::get(uri).body(Body::empty()).unwrap();
+
+        // The block at height 10 has a single confirmation, so only the
+        // older funds count as confirmed
+        let (status, body) = send(&node, get("/balance/Qalice?min_confirmations=6")).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["balance"], 300);
+        assert_eq!(json["confirmed_balance"], 100);
+
+        // Once buried under enough blocks the newer funds are confirmed too
+        node.lock().unwrap().state.lock().unwrap().block_height = 15;
+        let (_, body) = send(&node, get("/balance/Qalice?min_confirmations=6")).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["confirmed_balance"], 300);
+
+        // Nothing is that deep yet
+        let (_, body) = send(&node, get("/balance/Qalice?min_confirmations=100")).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["confirmed_balance"], 0);
+
+        let (status, _) = send(&node, get("/balance/Qalice?min_confirmations=many")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_contract_state_prefix_query() {
+        let node = test_node(NodeConfig::default());
+        {
+            let node = node.lock().unwrap();
+            let mut contracts = node.contracts.lock().unwrap();
+            contracts.deploy_contract("kv_store".to_string(), String::new(), "Qalice".to_string()).unwrap();
+            for (key, value) in [("user_alice", "1"), ("user_bob", "2"), ("config", "3")] {
+                contracts.execute_contract("kv_store", "set", vec![key.to_string(), value.to_string()]).unwrap();
+            }
+        }
+
+        let (status, body) = send(&node, Request::get("/contract/kv_store/state?prefix=user_").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json, serde_json::json!({ "user_alice": "1", "user_bob": "2" }));
+
+        let (status, _) = send(&node, Request::get("/contract/missing/state").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    async fn http_get(addr: std::net::SocketAddr, path: &str) -> String {
+        let uri: hyper::Uri = format!("http://{}{}", addr, path).parse().unwrap();
+        let response = hyper::Client::new().get(uri).await.unwrap();
+        let body = hyper::body::to_bytes(response.into_body()).await.unwrap();
+        String::from_utf8_lossy(&body).to_string()
+    }
+
+    #[tokio::test]
+    async fn test_nodes_in_one_process_are_isolated() {
+        let mut addrs = Vec::new();
+        let mut nodes = Vec::new();
+        for chain_id in ["nyxora-mainnet", "nyxora-testnet"] {
+            let node = NyxoraNode::new(NodeConfig {
+                chain_id: chain_id.to_string(),
+                ..NodeConfig::default()
+            });
+            let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+            listener.set_nonblocking(true).unwrap();
+            addrs.push(listener.local_addr().unwrap());
+
+            let handle = node.clone_for_hyper();
+            tokio::spawn(async move { node.serve(listener, None).await.unwrap() });
+            nodes.push(handle);
+        }
+
+        nodes[0].lock().unwrap().submit_transaction(transfer(1, 10)).unwrap();
+        nodes[1].lock().unwrap().set_paused(true);
+
+        let mainnet: NodeState = serde_json::from_str(&http_get(addrs[0], "/status").await).unwrap();
+        let testnet: NodeState = serde_json::from_str(&http_get(addrs[1], "/status").await).unwrap();
+        assert_eq!(mainnet.config.chain_id, "nyxora-mainnet");
+        assert_eq!(testnet.config.chain_id, "nyxora-testnet");
+        assert!(!mainnet.paused);
+        assert!(testnet.paused);
+
+        let mainnet_pool: Vec<Transaction> = serde_json::from_str(&http_get(addrs[0], "/mempool").await).unwrap();
+        let testnet_pool: Vec<Transaction> = serde_json::from_str(&http_get(addrs[1], "/mempool").await).unwrap();
+        assert_eq!(mainnet_pool, vec![transfer(1, 10)]);
+        assert!(testnet_pool.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_per_client() {
+        let node = test_node(NodeConfig {
+            rate_limit_per_sec: 1,
+            rate_limit_burst: 5,
+            ..NodeConfig::default()
+        });
+        let from = |peer: &str| {
+            let mut req = Request::get("/status").body(Body::empty()).unwrap();
+            req.extensions_mut().insert(peer.parse::<std::net::SocketAddr>().unwrap());
+            req
+        };
+
+        let mut statuses = Vec::new();
+        for _ in 0..8 {
+            statuses.push(send(&node, from("10.0.0.1:5000")).await.0);
+        }
+        assert_eq!(statuses[..5], [StatusCode::OK; 5]);
+        assert_eq!(statuses[5..], [StatusCode::TOO_MANY_REQUESTS; 3]);
+
+        // Buckets are per IP, whatever the source port
+        assert_eq!(send(&node, from("10.0.0.1:6000")).await.0, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(send(&node, from("10.0.0.2:5000")).await.0, StatusCode::OK);
+
+        // Once a bucket has refilled it is dropped as idle
+        let limiter = Arc::clone(&node.lock().unwrap().rate_limiter);
+        let now = std::time::Instant::now();
+        assert_eq!(limiter.prune_idle(now), 0);
+        let later = now + std::time::Duration::from_secs(6);
+        assert_eq!(limiter.prune_idle(later), 2);
+        assert_eq!(limiter.prune_idle(later), 0);
+    }
+
+    #[tokio::test]
+    async fn test_served_requests_are_rate_limited() {
+        let node = NyxoraNode::new(NodeConfig {
+            rate_limit_per_sec: 1,
+            rate_limit_burst: 2,
+            ..NodeConfig::default()
+        });
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move { node.serve(listener, None).await.unwrap() });
+
+        assert!(http_get(addr, "/status").await.contains("\"block_height\""));
+        assert!(http_get(addr, "/status").await.contains("\"block_height\""));
+        assert_eq!(http_get(addr, "/status").await, "Too many requests");
+    }
+
+    #[tokio::test]
+    async fn test_peer_gossip_round() {
+        let listeners: Vec<std::net::TcpListener> = (0..2)
+            .map(|_| std::net::TcpListener::bind("127.0.0.1:0").unwrap())
+            .collect();
+        let addrs: Vec<String> = listeners.iter().map(|l| l.local_addr().unwrap().to_string()).collect();
+
+        // Each node knows the other plus one peer of its own; the extra
+        // peers refuse connections, so the round does not wait on them
+        let a = NyxoraNode::new(NodeConfig { peers: vec![addrs[1].clone(), "127.0.0.1:1".to_string()], ..NodeConfig::default() });
+        let b = NyxoraNode::new(NodeConfig { peers: vec![addrs[0].clone(), "127.0.0.1:2".to_string()], ..NodeConfig::default() });
+        let (gossip_a, gossip_b) = (a.clone_shared(), b.clone_shared());
+
+        for (node, listener) in [a, b].into_iter().zip(listeners) {
+            listener.set_nonblocking(true).unwrap();
+            tokio::spawn(async move { node.serve(listener, None).await.unwrap() });
+        }
+
+        // The servers also gossip in the background, so check where the
+        // peer lists end up rather than what each round added
+        gossip_a.gossip_peers(&addrs[0]).await;
+        gossip_b.gossip_peers(&addrs[1]).await;
+
+        // Neither node lists itself, even though its peer advertises it
+        let mut peers_a = gossip_a.get_peers();
+        peers_a.sort();
+        assert_eq!(peers_a, vec!["127.0.0.1:1".to_string(), "127.0.0.1:2".to_string(), addrs[1].clone()]);
+        let peers_b: Vec<String> = serde_json::from_str(&http_get(addrs[1].parse().unwrap(), "/peers").await).unwrap();
+        assert!(peers_b.contains(&"127.0.0.1:1".to_string()));
+        assert!(!peers_b.contains(&addrs[1]));
+
+        // A second round learns nothing new
+        assert_eq!(gossip_a.gossip_peers(&addrs[0]).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_fresh_node_syncs_from_full_node() {
+        let allocations: HashMap<String, u64> = [(address_of(&ALICE), 100)].into_iter().collect();
+        let full = NyxoraNode::new(NodeConfig { genesis_allocations: allocations.clone(), ..validator_config() });
+        full.submit_transaction(transfer(1, 10)).unwrap();
+        for _ in 0..3 {
+            full.propose_block().unwrap();
+        }
+        let full_handle = full.clone_shared();
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move { full.serve(listener, None).await.unwrap() });
+
+        // The unreachable peer is skipped
+        let fresh = NyxoraNode::new(NodeConfig {
+            peers: vec!["127.0.0.1:1".to_string(), addr],
+            genesis_validators: [(validator_config().address, validator_config().stake_amount)].into_iter().collect(),
+            genesis_allocations: allocations,
+            block_time_secs: 30,
+            ..NodeConfig::default()
+        });
+        let settings = customize_consensus(&fresh);
+        fresh.submit_transaction(transfer(1, 10)).unwrap();
+        assert_eq!(fresh.get_status().block_height, 0);
+
+        assert_eq!(fresh.sync_from_peers().await, 3);
+        assert_eq!(fresh.get_status().block_height, 3);
+        assert!(fresh.is_ready());
+        assert_eq!(
+            serde_json::to_string(&fresh.get_chain()).unwrap(),
+            serde_json::to_string(&full_handle.get_chain()).unwrap()
+        );
+        assert_eq!(
+            fresh.consensus.lock().unwrap().pos.state_root(),
+            full_handle.consensus.lock().unwrap().pos.state_root()
+        );
+        assert_eq!(consensus_settings(&fresh.consensus.lock().unwrap()), settings);
+        // The synced chain already holds the pending transaction
+        assert!(fresh.get_mempool().is_empty());
+        assert_eq!(fresh.next_nonce(&address_of(&ALICE)), 2);
+        // The synced transfer moved the same balances as on the full node
+        for address in [address_of(&ALICE), "Qbob".to_string()] {
+            assert_eq!(fresh.get_balance(&address), full_handle.get_balance(&address));
+        }
+        assert_eq!(fresh.get_balance(&address_of(&ALICE)), 90);
+        assert_eq!(fresh.get_balance("Qbob"), 10);
+
+        // Nothing longer is on offer the second time
+        assert_eq!(fresh.sync_from_peers().await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_sync_refuses_peer_with_other_genesis() {
+        let other = NyxoraNode::new(NodeConfig { stake_amount: 5_000, ..validator_config() });
+        for _ in 0..3 {
+            other.propose_block().unwrap();
+        }
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move { other.serve(listener, None).await.unwrap() });
+
+        // The peer's chain is longer and valid, but starts from a genesis
+        // with a different validator set
+        let fresh = NyxoraNode::new(NodeConfig {
+            peers: vec![addr.clone()],
+            genesis_validators: [(validator_config().address, validator_config().stake_amount)].into_iter().collect(),
+            ..NodeConfig::default()
+        });
+        let genesis = fresh.get_chain()[0].clone();
+        assert_eq!(fresh.sync_from_peers().await, 0);
+        assert_eq!(fresh.get_chain().len(), 1);
+        assert_eq!(fresh.get_chain()[0].hybrid_hash, genesis.hybrid_hash);
+        assert_eq!(fresh.consensus.lock().unwrap().pos.validators[&validator_config().address].stake, 1_000);
+    }
+
+    // Serves a validator node whose one block holds `transactions`, put
+    // straight into its mempool without the `/tx` checks. Returns its
+    // address and the config a node needs to share its genesis.
+    fn serve_block_of(transactions: Vec<Transaction>) -> (String, NodeConfig) {
+        let config = NodeConfig {
+            genesis_allocations: [(address_of(&ALICE), 100)].into_iter().collect(),
+            ..validator_config()
+        };
+        let node = NyxoraNode::new(config.clone());
+        let now = node.clock.now_secs();
+        let count = transactions.len();
+        for tx in transactions {
+            node.mempool.lock().unwrap().push(tx, now);
+        }
+        assert_eq!(node.propose_block().unwrap().pos_block.transactions.len(), count);
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap().to_string();
+        tokio::spawn(async move { node.serve(listener, None).await.unwrap() });
+
+        let fresh_config = NodeConfig {
+            peers: vec![addr.clone()],
+            genesis_validators: [(config.address.clone(), config.stake_amount)].into_iter().collect(),
+            genesis_allocations: config.genesis_allocations,
+            ..NodeConfig::default()
+        };
+        (addr, fresh_config)
+    }
+
+    #[tokio::test]
+    async fn test_sync_refuses_unsigned_or_out_of_order_transfers() {
+        let unsigned = Transaction { from: address_of(&ALICE), ..unsigned_transfer(1, 10) };
+        let forged = Transaction { from: address_of(&ALICE), ..signed(&CAROL, unsigned_transfer(1, 10)) };
+        for transactions in [vec![unsigned], vec![forged], vec![transfer(2, 10)], vec![transfer(1, 10), transfer(1, 20)]] {
+            let (addr, config) = serve_block_of(transactions);
+            let fresh = NyxoraNode::new(config);
+
+            assert_eq!(fresh.sync_from_peers().await, 0);
+            assert_eq!(fresh.get_balance(&address_of(&ALICE)), 100);
+            assert_eq!(fresh.get_balance("Qbob"), 0);
+            assert!(fresh.active_bans().contains_key(&addr));
+        }
+
+        // The same chain with a properly signed transfer is adopted
+        let (_, config) = serve_block_of(vec![transfer(1, 10)]);
+        let fresh = NyxoraNode::new(config);
+        assert_eq!(fresh.sync_from_peers().await, 1);
+        assert_eq!(fresh.get_balance("Qbob"), 10);
+    }
+
+    #[test]
+    fn test_peer_list_is_capped() {
+        let node = NyxoraNode::new(NodeConfig::default());
+        let discovered = (0..2 * MAX_PEERS).map(|i| format!("10.0.0.{}:33333", i)).collect();
+        node.merge_peers(discovered, "127.0.0.1:33333");
+        assert_eq!(node.get_peers().len(), MAX_PEERS);
+    }
+
+    #[tokio::test]
+    async fn test_banned_peer_is_not_a_gossip_target() {
+        let node = test_node(NodeConfig { peers: vec!["10.0.0.1:9001".to_string(), "10.0.0.2:9002".to_string()], ..NodeConfig::default() });
+        node.lock().unwrap().ban_peer("10.0.0.1:9001", PEER_BAN_DURATION);
+        assert_eq!(node.lock().unwrap().gossip_targets(), vec!["10.0.0.2:9002".to_string()]);
+        // Still known, just not contacted
+        assert_eq!(node.lock().unwrap().get_peers().len(), 2);
+
+        // Its inbound requests are refused, whatever port they come from
+        assert_eq!(send(&node, status_from("10.0.0.1:50123")).await, (StatusCode::FORBIDDEN, "Peer is banned".to_string()));
+        assert_eq!(send(&node, status_from("10.0.0.2:50123")).await.0, StatusCode::OK);
+    }
+
+    fn status_from(client: &str) -> Request<Body> {
+        let mut req = Request::get("/status").body(Body::empty()).unwrap();
+        req.extensions_mut().insert(client.parse::<std::net::SocketAddr>().unwrap());
+        req
+    }
+
+    #[tokio::test]
+    async fn test_banned_local_peer_leaves_local_clients_alone() {
+        let node = test_node(NodeConfig::default());
+        node.lock().unwrap().ban_peer("127.0.0.1:8081", PEER_BAN_DURATION);
+        assert!(node.lock().unwrap().gossip_targets().is_empty());
+
+        // The wallet and other local clients share the host with the peer
+        assert_eq!(send(&node, status_from("127.0.0.1:50123")).await.0, StatusCode::OK);
+        assert_eq!(send(&node, status_from("127.0.0.1:8081")).await.0, StatusCode::OK);
+
+        // Banning the bare address still refuses the whole host
+        node.lock().unwrap().ban_peer("127.0.0.1", PEER_BAN_DURATION);
+        assert_eq!(send(&node, status_from("127.0.0.1:50123")).await.0, StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_peer_ban_expires() {
+        let mut node = NyxoraNode::new(NodeConfig { peers: vec!["10.0.0.1:9001".to_string()], ..NodeConfig::default() });
+        let clock = Arc::new(consensus::clock::MockClock::new(1_000));
+        node.clock = clock.clone();
+
+        node.ban_peer("10.0.0.1:9001", std::time::Duration::from_secs(60));
+        clock.set(1_059);
+        assert!(node.gossip_targets().is_empty());
+        assert!(node.is_banned_ip("10.0.0.1".parse().unwrap()));
+
+        clock.set(1_060);
+        assert_eq!(node.gossip_targets(), vec!["10.0.0.1:9001".to_string()]);
+        assert!(!node.is_banned_ip("10.0.0.1".parse().unwrap()));
+        assert!(node.banned_peers.lock().unwrap().is_empty());
+    }
+
+    async fn verify_signature(node: &Arc<Mutex<NyxoraNode>>, body: serde_json::Value) -> bool {
+        let req = Request::post("/verify_signature").body(Body::from(body.to_string())).unwrap();
+        let (status, body) = send(node, req).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        json["valid"].as_bool().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_verify_signature_endpoint() {
+        let node = test_node(NodeConfig::default());
+        let seed = [7u8; 32];
+        let public_key = crypto::public_key_hex(&seed).unwrap();
+        let address = crypto::address_from_public_key(&public_key).unwrap();
+        let signature = crypto::sign(&seed, b"pay Qbob 10").unwrap();
+
+        let mut body = serde_json::json!({
+            "address": address,
+            "public_key": public_key,
+            "message": "pay Qbob 10",
+            "signature": signature,
+        });
+        assert!(verify_signature(&node, body.clone()).await);
+
+        body["message"] = "pay Qbob 1000".into();
+        assert!(!verify_signature(&node, body).await);
+    }
+
+    #[tokio::test]
+    async fn test_server_stops_on_shutdown_signal() {
+        let node = NyxoraNode::new(NodeConfig::default());
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let addr = listener.local_addr().unwrap();
+
+        let (shutdown, receiver) = oneshot::channel();
+        let server = tokio::spawn(async move { node.serve(listener, Some(receiver)).await.is_ok() });
+
+        // The server answers requests until it is told to stop
+        assert!(http_get(addr, "/status").await.contains("\"status\":\"running\""));
+
+        shutdown.send(()).unwrap();
+        let stopped = tokio::time::timeout(std::time::Duration::from_secs(5), server).await;
+        assert!(stopped.expect("server did not shut down").unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_writes_binary_chain_file() {
+        let dir = std::env::temp_dir().join(format!("nyxora-chain-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chain.bin").to_string_lossy().to_string();
+
+        let node = NyxoraNode::new(NodeConfig { chain_file: Some(path.clone()), ..validator_config() });
+        node.propose_block().unwrap();
+        node.propose_block().unwrap();
+        let expected = node.get_chain();
+
+        let listener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        listener.set_nonblocking(true).unwrap();
+        let (shutdown, receiver) = oneshot::channel();
+        let server = tokio::spawn(async move { node.serve(listener, Some(receiver)).await.is_ok() });
+        shutdown.send(()).unwrap();
+        assert!(server.await.unwrap());
+
+        let blocks = read_chain_file(&path).unwrap();
+        assert_eq!(serde_json::to_string(&blocks).unwrap(), serde_json::to_string(&expected).unwrap());
+        assert!(fs::read(&path).unwrap().len() < serde_json::to_vec(&expected).unwrap().len());
+
+        // JSON dumps of /chain still replay
+        let json_path = dir.join("chain.json").to_string_lossy().to_string();
+        fs::write(&json_path, serde_json::to_vec(&expected).unwrap()).unwrap();
+        assert_eq!(read_chain_file(&json_path).unwrap().len(), 3);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_verify_subcommand() {
+        let dir = std::env::temp_dir().join(format!("nyxora-verify-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("chain.json").to_string_lossy().to_string();
+        let verify = |args: &[&str]| match Cli::try_parse_from(args).unwrap().command {
+            Some(Command::Verify { chain, hash_algo }) => verify_chain_file(&chain, hash_algo),
+            _ => panic!("expected the verify subcommand"),
+        };
+
+        let node = NyxoraNode::new(validator_config());
+        node.propose_block().unwrap();
+        node.propose_block().unwrap();
+        let mut blocks = node.get_chain();
+        fs::write(&path, serde_json::to_vec(&blocks).unwrap()).unwrap();
+        assert_eq!(verify(&["nyxora-node", "verify", "--chain", &path]), Ok(3));
+        // Hashed with another algorithm, the same chain does not verify
+        assert!(verify(&["nyxora-node", "verify", "--chain", &path, "--hash-algo", "blake3"]).is_err());
+        assert!(Cli::try_parse_from(["nyxora-node", "verify", "--chain", &path, "--hash-algo", "md5"]).is_err());
+
+        blocks[2].pos_block.prev_hash = "corrupted".to_string();
+        fs::write(&path, serde_json::to_vec(&blocks).unwrap()).unwrap();
+        assert_eq!(
+            verify(&["nyxora-node", "verify", "--chain", &path]),
+            Err("Block 2 is invalid: hash, transaction root or size check failed".to_string())
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_emission_endpoint() {
+        let node = test_node(validator_config());
+        {
+            let node = node.lock().unwrap();
+            let mut consensus = node.consensus.lock().unwrap();
+            consensus.pos.emission = consensus::emission::EmissionSchedule {
+                initial_reward: 100,
+                halving_interval: 10,
+                min_reward: 0,
+            };
+        }
+        for _ in 0..12 {
+            node.lock().unwrap().propose_block().unwrap();
+        }
+
+        let (status, body) = send(&node, Request::get("/emission").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["height"], 12);
+        assert_eq!(json["base_reward"], 50);
+        assert_eq!(json["next_halving_height"], 20);
+        assert_eq!(json["blocks_until_halving"], 8);
+        assert_eq!(json["emitted"], 900 + 3 * 50);
+        assert_eq!(json["projected_total_emission"], 900 + 10 * (50 + 25 + 12 + 6 + 3 + 1));
+        assert_eq!(json["remaining_supply"], 10 * (50 + 25 + 12 + 6 + 3 + 1) - 3 * 50);
+    }
+
+    fn withdraw_request(amount: u64) -> Request<Body> {
+        Request::post("/withdraw_rewards").body(Body::from(amount.to_string())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_withdraw_rewards_endpoint() {
+        let node = test_node(validator_config());
+        node.lock().unwrap().propose_block().unwrap();
+        let address = NodeConfig::default().address;
+        let accrued = node.lock().unwrap().consensus.lock().unwrap().pos.validators[&address].rewards;
+
+        let (status, body) = send(&node, withdraw_request(accrued / 2)).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["balance"], accrued / 2);
+        assert_eq!(node.lock().unwrap().get_balance(&address), accrued / 2);
+
+        let (status, _) = send(&node, withdraw_request(accrued)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_unjail_endpoint() {
+        let node = test_node(NodeConfig { jail_threshold: 1, min_jail_blocks: 2, ..validator_config() });
+        let unjail = || admin_request("/unjail", Some("secret"));
+
+        let (status, body) = send(&node, unjail()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Validator is not jailed");
+
+        node.lock().unwrap().consensus.lock().unwrap().pos.timeout_to_next_view();
+        assert!(node.lock().unwrap().produce_block_if_leader().is_none());
+        let (status, body) = send(&node, unjail()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Validator is jailed until block 2");
+
+        node.lock().unwrap().propose_block().unwrap();
+        node.lock().unwrap().propose_block().unwrap();
+        let (status, _) = send(&node, unjail()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(node.lock().unwrap().produce_block_if_leader().is_some());
+    }
+
+    #[tokio::test]
+    async fn test_metrics_endpoint() {
+        let node = test_node(validator_config());
+        let metric = |body: &str, name: &str| -> u64 {
+            body.lines()
+                .find_map(|line| line.strip_prefix(&format!("{} ", name)))
+                .unwrap()
+                .parse()
+                .unwrap()
+        };
+
+        let (status, body) = send(&node, Request::get("/metrics").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        for name in ["nyxora_block_height", "nyxora_validator_count", "nyxora_total_stake", "nyxora_mempool_size", "nyxora_proofs_total"] {
+            assert!(body.contains(&format!("# TYPE {} ", name)), "missing {}", name);
+        }
+        assert_eq!(metric(&body, "nyxora_block_height"), 0);
+        assert_eq!(metric(&body, "nyxora_validator_count"), 1);
+
+        fund(&node, &address_of(&ALICE), 10);
+        send(&node, tx_request(&transfer(1, 10))).await;
+        let (_, body) = send(&node, Request::get("/metrics").body(Body::empty()).unwrap()).await;
+        assert_eq!(metric(&body, "nyxora_mempool_size"), 1);
+        let stake_before = metric(&body, "nyxora_total_stake");
+
+        node.lock().unwrap().propose_block().unwrap();
+        let (_, body) = send(&node, Request::get("/metrics").body(Body::empty()).unwrap()).await;
+        assert_eq!(metric(&body, "nyxora_block_height"), 1);
+        assert_eq!(metric(&body, "nyxora_mempool_size"), 0);
+        assert!(metric(&body, "nyxora_total_stake") > stake_before);
+    }
+
+    #[tokio::test]
+    async fn test_status_reports_view() {
+        let node = test_node(validator_config());
+        node.lock().unwrap().consensus.lock().unwrap().pos.timeout_to_next_view();
+
+        let (_, body) = send(&node, Request::get("/status").body(Body::empty()).unwrap()).await;
+        let status: NodeState = serde_json::from_str(&body).unwrap();
+        assert_eq!(status.view, 1);
+
+        node.lock().unwrap().propose_block().unwrap();
+        assert_eq!(node.lock().unwrap().get_status().view, 0);
+    }
+
+    #[tokio::test]
+    async fn test_ready_after_first_block() {
+        let node = test_node(validator_config());
+        let get = |path: &str| Request::get(path).body(Body::empty()).unwrap();
+
+        assert_eq!(send(&node, get("/health")).await.0, StatusCode::OK);
+        assert_eq!(send(&node, get("/ready")).await.0, StatusCode::SERVICE_UNAVAILABLE);
+
+        node.lock().unwrap().propose_block().unwrap();
+        assert_eq!(send(&node, get("/ready")).await.0, StatusCode::OK);
+        assert_eq!(send(&node, get("/health")).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_mempool_prunes_expired_transactions() {
+        let node = test_node(NodeConfig { mempool_ttl_secs: 60, ..validator_config() });
+        let clock = Arc::new(consensus::clock::MockClock::new(1_000));
+        node.lock().unwrap().clock = clock.clone();
+        fund(&node, &address_of(&ALICE), 10_000);
+        fund(&node, &address_of(&BOB), 10_000);
+        let from_bob = signed(&BOB, unsigned_transfer(1, 10));
+
+        send(&node, tx_request(&transfer(1, 10))).await;
+        clock.advance(30);
+        send(&node, tx_request(&from_bob)).await;
+        send(&node, tx_request(&transfer(2, 10))).await;
+
+        clock.set(1_060);
+        assert_eq!(node.lock().unwrap().prune_mempool(), 0);
+
+        // Alice's first transaction expires and takes her later one with it
+        clock.set(1_061);
+        assert_eq!(node.lock().unwrap().prune_mempool(), 2);
+        assert_eq!(node.lock().unwrap().get_mempool(), vec![from_bob.clone()]);
+        assert_eq!(node.lock().unwrap().next_nonce(&address_of(&ALICE)), 1);
+
+        // Block production prunes first, so Bob's now-expired transaction is left out
+        clock.set(1_091);
+        let block = node.lock().unwrap().propose_block().unwrap();
+        assert!(block.pos_block.transactions.is_empty());
+        assert!(node.lock().unwrap().get_mempool().is_empty());
+    }
+
+    fn faucet_request(address: &str) -> Request<Body> {
+        Request::post("/faucet")
+            .header("X-Admin-Key", "secret")
+            .body(Body::from(serde_json::json!({ "address": address }).to_string()))
+            .unwrap()
+    }
+
+    fn testnet_config() -> NodeConfig {
+        NodeConfig { testnet: true, admin_key: Some("secret".to_string()), ..NodeConfig::default() }
+    }
+
+    #[tokio::test]
+    async fn test_faucet_grants_funds_once_per_cooldown() {
+        let node = test_node(NodeConfig { faucet_amount: 500, faucet_cooldown_secs: 60, ..testnet_config() });
+        let clock = Arc::new(consensus::clock::MockClock::new(1_000));
+        node.lock().unwrap().clock = clock.clone();
+        fund(&node, "Qalice", 20);
+
+        let (status, body) = send(&node, faucet_request("Qalice")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&body).unwrap(),
+            serde_json::json!({ "address": "Qalice", "granted": 500, "balance": 520 })
+        );
+        assert_eq!(node.lock().unwrap().get_balance("Qalice"), 520);
+
+        clock.advance(59);
+        let (status, body) = send(&node, faucet_request("Qalice")).await;
+        assert_eq!(status, StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(body, "Faucet already used by Qalice; retry in 1s");
+        assert_eq!(node.lock().unwrap().get_balance("Qalice"), 520);
+        // The cooldown is per address
+        assert_eq!(send(&node, faucet_request("Qbob")).await.0, StatusCode::OK);
+
+        clock.advance(1);
+        assert_eq!(send(&node, faucet_request("Qalice")).await.0, StatusCode::OK);
+        assert_eq!(node.lock().unwrap().get_balance("Qalice"), 1_020);
+
+        let req = Request::post("/faucet").header("X-Admin-Key", "secret").body(Body::from("Qalice")).unwrap();
+        assert_eq!(send(&node, req).await.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_privileged_routes_require_admin_key() {
+        let node = test_node(NodeConfig { is_validator: true, ..testnet_config() });
+        let faucet = |key: Option<&str>| {
+            let mut builder = Request::post("/faucet");
+            if let Some(key) = key {
+                builder = builder.header("X-Admin-Key", key);
+            }
+            builder.body(Body::from(r#"{"address": "Qalice"}"#)).unwrap()
+        };
+
+        assert_eq!(send(&node, faucet(Some("secret"))).await.0, StatusCode::OK);
+        assert_eq!(node.lock().unwrap().get_balance("Qalice"), 1_000);
+        // Authorized, so the request reaches the handler, which has nothing to unjail
+        let (status, body) = send(&node, admin_request("/unjail", Some("secret"))).await;
+        assert_eq!((status, body.as_str()), (StatusCode::BAD_REQUEST, "Validator is not jailed"));
+
+        for key in [None, Some("wrong")] {
+            let (status, body) = send(&node, faucet(key)).await;
+            assert_eq!((status, body.as_str()), (StatusCode::UNAUTHORIZED, "Invalid admin key"));
+            assert_eq!(send(&node, admin_request("/unjail", key)).await.0, StatusCode::UNAUTHORIZED);
+        }
+        assert_eq!(node.lock().unwrap().get_balance("Qalice"), 1_000);
+
+        // The key guarding these routes is never published by a public read
+        let public_reads = [
+            "/health", "/ready", "/status", "/finalized", "/snapshot", "/peers", "/mempool", "/state",
+            "/chain", "/verify", "/emission", "/metrics", "/proofs", "/leaderboard", "/blocks",
+        ];
+        for path in public_reads {
+            let (_, body) = send(&node, Request::get(path).body(Body::empty()).unwrap()).await;
+            assert!(!body.contains("secret"), "{} exposes the admin key", path);
+        }
+        for method in ["nyx_getStatus", "nyx_getBlockHeight", "nyx_getValidators"] {
+            let call = format!(r#"{{"jsonrpc": "2.0", "method": "{}", "id": 1}}"#, method);
+            assert!(!rpc(&node, &call).await.to_string().contains("secret"), "{} exposes the admin key", method);
+        }
+
+        let keyless = test_node(NodeConfig { admin_key: None, is_validator: true, ..testnet_config() });
+        let (status, body) = send(&keyless, faucet(Some("secret"))).await;
+        assert_eq!((status, body.as_str()), (StatusCode::FORBIDDEN, "Admin API disabled"));
+        assert_eq!(send(&keyless, admin_request("/unjail", Some("secret"))).await.0, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_faucet_is_forbidden_on_mainnet() {
+        let node = test_node(NodeConfig::default());
+        let (status, body) = send(&node, faucet_request("Qalice")).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert_eq!(body, "The faucet is only available on testnet");
+        assert_eq!(node.lock().unwrap().get_balance("Qalice"), 0);
+    }
+
+    #[tokio::test]
+    async fn test_governance_endpoints() {
+        let seed = [7u8; 32];
+        let public_key = crypto::public_key_hex(&seed).unwrap();
+        let address = crypto::address_from_public_key(&public_key).unwrap();
+        let node = test_node(NodeConfig { address: address.clone(), ..validator_config() });
+
+        let proposal = ProposalRequest {
+            proposer: address.clone(),
+            description: "Raise the block size".to_string(),
+            deadline: 5,
+            public_key: public_key.clone(),
+            signature: crypto::sign(&seed, governance::proposal_message("Raise the block size", 5).as_bytes()).unwrap(),
+        };
+        let req = Request::post("/governance/propose").body(Body::from(serde_json::to_string(&proposal).unwrap())).unwrap();
+        let (status, body) = send(&node, req).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body, r#"{"id":0}"#);
+
+        let vote = |signer: &[u8; 32], choice: VoteChoice| {
+            let public_key = crypto::public_key_hex(signer).unwrap();
+            let vote = VoteRequest {
+                proposal_id: 0,
+                voter: crypto::address_from_public_key(&public_key).unwrap(),
+                choice,
+                public_key,
+                signature: crypto::sign(signer, governance::vote_message(0, choice).as_bytes()).unwrap(),
+            };
+            Request::post("/governance/vote").body(Body::from(serde_json::to_string(&vote).unwrap())).unwrap()
+        };
+
+        // The node's validator stakes 1,000, worth sqrt(1000) votes
+        let (status, body) = send(&node, vote(&seed, VoteChoice::Yes)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["power"], 1000f64.sqrt());
+        assert_eq!(send(&node, vote(&seed, VoteChoice::No)).await, (StatusCode::BAD_REQUEST, "Already voted on this proposal".to_string()));
+        let (status, body) = send(&node, vote(&[8u8; 32], VoteChoice::No)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Only validators and delegators with stake may take part");
+
+        // A vote signed for the other choice does not count
+        let mut forged: serde_json::Value = serde_json::from_slice(&hyper::body::to_bytes(vote(&seed, VoteChoice::Yes).into_body()).await.unwrap()).unwrap();
+        forged["choice"] = "no".into();
+        let req = Request::post("/governance/vote").body(Body::from(forged.to_string())).unwrap();
+        assert_eq!(send(&node, req).await, (StatusCode::BAD_REQUEST, "Invalid signature".to_string()));
+
+        let (_, body) = send(&node, Request::get("/governance/0").body(Body::empty()).unwrap()).await;
+        let proposal: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(proposal["votes"], 1);
+        assert_eq!(proposal["outcome"], "pending");
+        let (status, _) = send(&node, Request::get("/governance/9").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_attestations_finalize_blocks() {
+        let seed = [7u8; 32];
+        let public_key = crypto::public_key_hex(&seed).unwrap();
+        let address = crypto::address_from_public_key(&public_key).unwrap();
+        let node = test_node(NodeConfig { address: address.clone(), ..validator_config() });
+        let block = node.lock().unwrap().propose_block().unwrap();
+
+        let attest = |signer: &[u8; 32], block_hash: &str| {
+            let public_key = crypto::public_key_hex(signer).unwrap();
+            let attestation = consensus::finality::Attestation {
+                block_hash: block_hash.to_string(),
+                validator: crypto::address_from_public_key(&public_key).unwrap(),
+                public_key,
+                signature: crypto::sign(signer, block_hash.as_bytes()).unwrap(),
+            };
+            Request::post("/attest").body(Body::from(serde_json::to_string(&attestation).unwrap())).unwrap()
+        };
+
+        let (status, body) = send(&node, attest(&[8u8; 32], &block.hybrid_hash)).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        assert_eq!(body, "Validator not found");
+        let (status, _) = send(&node, attest(&seed, "unknown")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        // The node is the only validator, so its own vote is a supermajority
+        let (status, body) = send(&node, attest(&seed, &block.hybrid_hash)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(serde_json::from_str::<serde_json::Value>(&body).unwrap()["finalized"], true);
+
+        let (_, body) = send(&node, Request::get("/finalized").body(Body::empty()).unwrap()).await;
+        let finalized: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(finalized["height"], 1);
+        assert_eq!(finalized["hash"], block.hybrid_hash.as_str());
+        assert!(node.lock().unwrap().get_chain()[1].quorum_certificate.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_unstaked_funds_show_as_unbonding() {
+        let node = test_node(validator_config());
+        let address = NodeConfig::default().address;
+        assert!(node.lock().unwrap().consensus.lock().unwrap().pos.unstake(&address, 400));
+
+        let (status, body) = send(&node, Request::get(format!("/unbonding/{}", address)).body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let unbonding: Vec<consensus::pos::UnbondingEntry> = serde_json::from_str(&body).unwrap();
+        assert_eq!(unbonding, vec![consensus::pos::UnbondingEntry {
+            address,
+            amount: 400,
+            release_block: consensus::pos::DEFAULT_UNBONDING_PERIOD,
+        }]);
+    }
+
+    #[tokio::test]
+    async fn test_large_responses_are_gzipped() {
+        use std::io::Read;
+
+        let node = test_node(validator_config());
+        for _ in 0..10 {
+            node.lock().unwrap().propose_block().unwrap();
+        }
+        let gzip_get = |path: &str| Request::get(path).header("Accept-Encoding", "deflate, gzip").body(Body::empty()).unwrap();
+
+        let response = handle_request(gzip_get("/chain"), Arc::clone(&node)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["Content-Encoding"], "gzip");
+        let compressed = hyper::body::to_bytes(response.into_body()).await.unwrap();
+
+        let mut json = String::new();
+        flate2::read::GzDecoder::new(&compressed[..]).read_to_string(&mut json).unwrap();
+        let expected = serde_json::to_string(&node.lock().unwrap().get_chain()).unwrap();
+        assert_eq!(json, expected);
+        assert!(compressed.len() < expected.len());
+
+        // Small bodies and clients that don't ask for it get plain responses
+        let response = handle_request(gzip_get("/health"), Arc::clone(&node)).await.unwrap();
+        assert!(!response.headers().contains_key("Content-Encoding"));
+        let (_, body) = send(&node, Request::get("/chain").body(Body::empty()).unwrap()).await;
+        assert_eq!(body, expected);
+    }
+
+    #[tokio::test]
+    async fn test_batch_proof_submission() {
+        let node = test_node(validator_config());
+        let address = NodeConfig::default().address;
+        let challenge = node.lock().unwrap().consensus.lock().unwrap().generate_quantum_challenge(8, 0);
+        let proof = |circuit_descriptor: &str, qubit_count| consensus::poq::QuantumProof {
+            circuit_descriptor: circuit_descriptor.to_string(),
+            measurement_results: vec![0b1011],
+            proof_artifact: "proof".to_string(),
+            qubit_count,
+            validator_address: address.clone(),
+            timestamp: 0,
+        };
+        let batch = vec![proof("challenge_unknown", 8), proof(&challenge.challenge_id, 8), proof(&challenge.challenge_id, 8)];
+        let request = |body: String| Request::post("/proofs/batch").body(Body::from(body)).unwrap();
+
+        let (status, body) = send(&node, request(serde_json::to_string(&batch).unwrap())).await;
+        assert_eq!(status, StatusCode::OK);
+        let statuses: Vec<serde_json::Value> = serde_json::from_str(&body).unwrap();
+        assert_eq!(statuses, vec![
+            serde_json::json!({ "accepted": false, "error": "Challenge not found" }),
+            serde_json::json!({ "accepted": true, "reward": challenge.reward }),
+            serde_json::json!({ "accepted": false, "error": "Proof already submitted" }),
+        ]);
+        assert_eq!(node.lock().unwrap().get_quantum_score(&address), challenge.reward);
+
+        let (status, _) = send(&node, request("{\"not\": \"an array\"}".to_string())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+        let oversized = vec![proof("challenge_unknown", 8); MAX_PROOF_BATCH + 1];
+        let (status, _) = send(&node, request(serde_json::to_string(&oversized).unwrap())).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_cbor_proof_is_accepted_like_json() {
+        let node = test_node(validator_config());
+        let address = NodeConfig::default().address;
+        let challenges: Vec<_> = {
+            let node = node.lock().unwrap();
+            let mut consensus = node.consensus.lock().unwrap();
+            (0..3).map(|nonce| consensus.generate_quantum_challenge(8, nonce)).collect()
+        };
+        let proof = |challenge: &consensus::poq::PoQChallenge| consensus::poq::QuantumProof {
+            circuit_descriptor: challenge.challenge_id.clone(),
+            measurement_results: vec![0b1011],
+            proof_artifact: "proof".to_string(),
+            qubit_count: 8,
+            validator_address: address.clone(),
+            timestamp: 0,
+        };
+
+        let cbor = proof(&challenges[0]).to_cbor();
+        let decoded = consensus::poq::QuantumProof::from_cbor(&cbor).unwrap();
+        assert_eq!(decoded.to_bytes(), proof(&challenges[0]).to_bytes());
+
+        let requests = [
+            Request::post("/proof").body(Body::from(serde_json::to_string(&proof(&challenges[1])).unwrap())).unwrap(),
+            Request::post("/proof").header("Content-Type", "application/cbor").body(Body::from(cbor)).unwrap(),
+            Request::post("/proof?format=cbor").body(Body::from(proof(&challenges[2]).to_cbor())).unwrap(),
+        ];
+        let mut responses = Vec::new();
+        for request in requests {
+            let (status, body) = send(&node, request).await;
+            assert_eq!(status, StatusCode::OK);
+            responses.push(serde_json::from_str::<serde_json::Value>(&body).unwrap());
+        }
+        assert_eq!(responses[0], serde_json::json!({ "accepted": true, "reward": challenges[1].reward }));
+        assert_eq!(responses[1], responses[0]);
+        assert_eq!(responses[2], responses[0]);
+        assert_eq!(node.lock().unwrap().get_quantum_score(&address), challenges[1].reward * 3);
+
+        // A JSON body sent as CBOR does not decode
+        let mislabelled = Request::post("/proof")
+            .header("Content-Type", "application/cbor")
+            .body(Body::from(serde_json::to_string(&proof(&challenges[0])).unwrap()))
+            .unwrap();
+        assert_eq!(send(&node, mislabelled).await.0, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_quantum_proofs_and_scores() {
+        let node = test_node(validator_config());
+        let address = NodeConfig::default().address;
+        {
+            let node = node.lock().unwrap();
+            let mut consensus = node.consensus.lock().unwrap();
+            let challenge = consensus.generate_quantum_challenge(8, 0);
+            consensus.submit_quantum_proof(consensus::poq::QuantumProof {
+                circuit_descriptor: challenge.challenge_id,
+                measurement_results: vec![0b1011],
+                proof_artifact: "proof".to_string(),
+                qubit_count: 8,
+                validator_address: address.clone(),
+                timestamp: 0,
+            }).unwrap();
+        }
+
+        let (status, body) = send(&node, Request::get("/proofs").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let proofs: Vec<consensus::poq::QuantumProof> = serde_json::from_str(&body).unwrap();
+        assert_eq!(proofs.len(), 1);
+
+        let (_, body) = send(&node, Request::get(format!("/score/{}", address)).body(Body::empty()).unwrap()).await;
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["score"], 80);
+
+        let (status, body) = send(&node, Request::get("/score/Qunknown").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(json["score"], 0);
+    }
+
+    #[tokio::test]
+    async fn test_leaderboard_endpoint() {
+        let node = test_node(validator_config());
+        let address = NodeConfig::default().address;
+        {
+            let node = node.lock().unwrap();
+            let mut consensus = node.consensus.lock().unwrap();
+            let challenge = consensus.generate_quantum_challenge(8, 0);
+            consensus.submit_quantum_proof(consensus::poq::QuantumProof {
+                circuit_descriptor: challenge.challenge_id,
+                measurement_results: vec![0b1011],
+                proof_artifact: "proof".to_string(),
+                qubit_count: 8,
+                validator_address: address.clone(),
+                timestamp: 0,
+            }).unwrap();
+        }
+        let board = |body: String| serde_json::from_str::<serde_json::Value>(&body).unwrap();
+
+        let (status, body) = send(&node, Request::get("/leaderboard?tier=low").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        let body = board(body);
+        assert_eq!(body["tier"], "low");
+        assert_eq!(body["leaderboard"], serde_json::json!([{ "address": address, "score": 80 }]));
+
+        let (_, body) = send(&node, Request::get("/leaderboard?tier=high").body(Body::empty()).unwrap()).await;
+        assert_eq!(board(body)["leaderboard"], serde_json::json!([]));
+        let (_, body) = send(&node, Request::get("/leaderboard").body(Body::empty()).unwrap()).await;
+        let body = board(body);
+        assert_eq!(body["tier"], serde_json::Value::Null);
+        assert_eq!(body["leaderboard"][0]["score"], 80);
+
+        let (status, _) = send(&node, Request::get("/leaderboard?tier=extreme").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn test_concurrent_proof_reads_during_submissions() {
+        let node = test_node(validator_config());
+        let address = NodeConfig::default().address;
+        let challenges: Vec<_> = {
+            let node = node.lock().unwrap();
+            let mut consensus = node.consensus.lock().unwrap();
+            (0..20).map(|nonce| consensus.generate_quantum_challenge(8, nonce)).collect()
+        };
+        let expected: u64 = challenges.iter().map(|challenge| challenge.reward).sum();
+
+        let readers: Vec<_> = (0..16).map(|_| {
+            let (node, address) = (Arc::clone(&node), address.clone());
+            tokio::spawn(async move {
+                let mut last_score = 0;
+                for _ in 0..50 {
+                    let (_, body) = send(&node, Request::get(format!("/score/{}", address)).body(Body::empty()).unwrap()).await;
+                    let json: serde_json::Value = serde_json::from_str(&body).unwrap();
+                    let score = json["score"].as_u64().unwrap();
+                    assert!(score >= last_score);
+                    last_score = score;
+
+                    let (status, _) = send(&node, Request::get("/proofs").body(Body::empty()).unwrap()).await;
+                    assert_eq!(status, StatusCode::OK);
+                }
+            })
+        }).collect();
+
+        let writer = {
+            let node = Arc::clone(&node);
+            tokio::spawn(async move {
+                for challenge in challenges {
+                    let proof = consensus::poq::QuantumProof {
+                        circuit_descriptor: challenge.challenge_id,
+                        measurement_results: vec![0b1011],
+                        proof_artifact: "proof".to_string(),
+                        qubit_count: 8,
+                        validator_address: address.clone(),
+                        timestamp: 0,
+                    };
+                    let body = serde_json::to_string(&vec![proof]).unwrap();
+                    let (status, _) = send(&node, Request::post("/proofs/batch").body(Body::from(body)).unwrap()).await;
+                    assert_eq!(status, StatusCode::OK);
+                }
+            })
+        };
+
+        let all_done = async {
+            writer.await.unwrap();
+            for reader in readers {
+                reader.await.unwrap();
+            }
+        };
+        tokio::time::timeout(std::time::Duration::from_secs(10), all_done).await
+            .expect("proof readers and writer deadlocked");
+
+        let node = node.lock().unwrap();
+        assert_eq!(node.get_quantum_score(&NodeConfig::default().address), expected);
+        assert_eq!(node.proofs.read().unwrap().len(), 20);
+    }
+
+    #[tokio::test]
+    async fn test_pause_requires_admin_key() {
+        let node = test_node(validator_config());
+
+        let (status, _) = send(&node, admin_request("/admin/pause", None)).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let (status, _) = send(&node, admin_request("/admin/pause", Some("wrong"))).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        let disabled = test_node(NodeConfig::default());
+        let (status, _) = send(&disabled, admin_request("/admin/pause", Some("secret"))).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"secret", b"secret"));
+        assert!(!constant_time_eq(b"secret", b"secreT"));
+        assert!(!constant_time_eq(b"secret", b"secret2"));
+        assert!(!constant_time_eq(b"", b"secret"));
+        assert!(constant_time_eq(b"", b""));
+    }
+
+    #[test]
+    fn test_config_validation() {
+        assert!(NodeConfig::default().validate().is_ok());
+        assert!(validator_config().validate().is_ok());
+
+        let err = NodeConfig { address: "X123456789012345678901234567890123456789".to_string(), ..NodeConfig::default() }
+            .validate().unwrap_err();
+        assert!(err.contains("starting with 'Q'"));
+        assert!(NodeConfig { address: "Qshort".to_string(), ..NodeConfig::default() }.validate().is_err());
+
+        let err = NodeConfig { peers: Vec::new(), ..validator_config() }.validate().unwrap_err();
+        assert_eq!(err, "A validator needs at least one entry in peers");
+        // Non-validators may run without peers
+        assert!(NodeConfig { peers: Vec::new(), ..NodeConfig::default() }.validate().is_ok());
+
+        let err = NodeConfig { stake_amount: 0, ..validator_config() }.validate().unwrap_err();
+        assert_eq!(err, "A validator needs a stake_amount greater than 0");
+
+        let allocate = |address: &str, amount| NodeConfig {
+            genesis_allocations: [(address.to_string(), amount)].into_iter().collect(),
+            ..NodeConfig::default()
+        };
+        assert!(allocate("Q000000000000000000000000000000000000001", 10).validate().is_ok());
+        assert!(allocate("Qshort", 10).validate().unwrap_err().contains("starting with 'Q'"));
+        // Changing the case of one letter breaks the checksum
+        let address = address_of(&ALICE);
+        let letter = address[1..].chars().find(char::is_ascii_alphabetic).unwrap();
+        let swapped = if letter.is_ascii_uppercase() { letter.to_ascii_lowercase() } else { letter.to_ascii_uppercase() };
+        let mistyped = format!("Q{}", address[1..].replacen(letter, &swapped.to_string(), 1));
+        assert!(allocate(&address, 10).validate().is_ok());
+        let err = allocate(&mistyped, 10).validate().unwrap_err();
+        assert_eq!(err, format!("Invalid address '{}': checksum does not match", mistyped));
+        let err = allocate("Q000000000000000000000000000000000000001", 0).validate().unwrap_err();
+        assert_eq!(err, "Genesis allocation to 'Q000000000000000000000000000000000000001' must be greater than 0");
+
+        let err = NodeConfig {
+            genesis_validators: [("Q000000000000000000000000000000000000001".to_string(), 0)].into_iter().collect(),
+            ..NodeConfig::default()
+        }.validate().unwrap_err();
+        assert_eq!(err, "Genesis validator 'Q000000000000000000000000000000000000001' needs a stake greater than 0");
+    }
+
+    async fn rpc(node: &Arc<Mutex<NyxoraNode>>, body: &str) -> serde_json::Value {
+        let req = Request::post("/rpc").body(Body::from(body.to_string())).unwrap();
+        let (status, body) = send(node, req).await;
+        assert_eq!(status, StatusCode::OK);
+        serde_json::from_str(&body).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_rpc_calls() {
+        let node = test_node(validator_config());
+
+        let response = rpc(&node, r#"{"jsonrpc": "2.0", "method": "nyx_stake", "params": [500], "id": 1}"#).await;
+        assert_eq!(response, serde_json::json!({ "jsonrpc": "2.0", "result": true, "id": 1 }));
+
+        let response = rpc(&node, r#"{"jsonrpc": "2.0", "method": "nyx_getValidators", "id": "v"}"#).await;
+        assert_eq!(response["result"][0]["stake"], 1500);
+        assert_eq!(response["id"], "v");
+
+        // A batch answers every call, in order
+        let response = rpc(&node, r#"[
+            {"jsonrpc": "2.0", "method": "nyx_getBlockHeight", "id": 1},
+            {"jsonrpc": "2.0", "method": "nyx_getStatus", "id": 2}
+        ]"#).await;
+        assert_eq!(response[0]["result"], 0);
+        assert_eq!(response[1]["result"]["status"], "running");
+    }
+
+    #[tokio::test]
+    async fn test_status_hides_admin_key() {
+        let node = test_node(NodeConfig { admin_key: Some("hunter2-admin".to_string()), ..validator_config() });
+
+        let (status, body) = send(&node, Request::get("/status").body(Body::empty()).unwrap()).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains("\"block_height\""));
+        assert!(!body.contains("hunter2-admin"));
+        assert!(!body.contains("admin_key"));
+
+        let response = rpc(&node, r#"{"jsonrpc": "2.0", "method": "nyx_getStatus", "id": 1}"#).await;
+        assert_eq!(response["result"]["status"], "running");
+        assert!(!response.to_string().contains("hunter2-admin"));
+
+        // The key still works for the admin routes
+        assert_eq!(send(&node, admin_request("/admin/pause", Some("hunter2-admin"))).await.0, StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_errors() {
+        let node = test_node(NodeConfig::default());
+
+        let response = rpc(&node, r#"{"jsonrpc": "2.0", "method": "nyx_mine", "id": 7}"#).await;
+        assert_eq!(response["error"]["code"], rpc::METHOD_NOT_FOUND);
+        assert_eq!(response["id"], 7);
+
+        for malformed in [r#"{"jsonrpc": "2.0", "id": 1}"#, r#"{"method": "nyx_getStatus", "id": 1}"#, "[]", "42"] {
+            let response = rpc(&node, malformed).await;
+            assert_eq!(response["error"]["code"], rpc::INVALID_REQUEST, "for {}", malformed);
+            assert!(response["id"].is_null());
+        }
+
+        let response = rpc(&node, "{not json").await;
+        assert_eq!(response["error"]["code"], rpc::PARSE_ERROR);
+
+        let response = rpc(&node, r#"{"jsonrpc": "2.0", "method": "nyx_stake", "params": {"amount": "lots"}, "id": 2}"#).await;
+        assert_eq!(response["error"]["code"], rpc::INVALID_PARAMS);
+    }
+
+    #[tokio::test]
+    async fn test_rpc_estimate_gas() {
+        let node = test_node(NodeConfig::default());
+        node.lock().unwrap().contracts.lock().unwrap()
+            .deploy_contract("kv_store".to_string(), String::new(), "Qalice".to_string())
+            .unwrap();
+
+        let response = rpc(&node, r#"{"jsonrpc": "2.0", "method": "nyx_estimateGas", "params": ["kv_store", "get", ["k"]], "id": 1}"#).await;
+        assert_eq!(response["result"], 20);
+
+        let response = rpc(&node, r#"{"jsonrpc": "2.0", "method": "nyx_estimateGas", "params": {"contract": "kv_store", "function": "drop"}, "id": 2}"#).await;
+        assert_eq!(response["error"]["code"], rpc::NODE_ERROR);
+        assert_eq!(response["error"]["message"], "Function 'drop' not found in kv_store contract");
+
+        let response = rpc(&node, r#"{"jsonrpc": "2.0", "method": "nyx_estimateGas", "params": ["kv_store", "get", [1]], "id": 3}"#).await;
+        assert_eq!(response["error"]["code"], rpc::INVALID_PARAMS);
+    }
+}
@@ -0,0 +1,117 @@
+// Transactions accepted by the node and waiting for a block
+use std::collections::{HashMap, HashSet};
+use nyxora_node::chain::transaction::Transaction;
+
+/// Pending transactions in arrival order. A transaction is identified by
+/// its sender and nonce.
+#[derive(Debug, Default)]
+pub struct Mempool {
+    transactions: Vec<Transaction>,
+    received_at: Vec<u64>, // Unix time each transaction arrived, in step with `transactions`
+}
+
+impl Mempool {
+    pub fn push(&mut self, tx: Transaction, received_at: u64) {
+        self.transactions.push(tx);
+        self.received_at.push(received_at);
+    }
+
+    pub fn len(&self) -> usize {
+        self.transactions.len()
+    }
+
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+
+    /// Picks up to `max_txs` transactions for a block, highest fee first,
+    /// leaving the mempool untouched. Only each sender's earliest pending
+    /// transaction competes, so a sender's transactions still leave in nonce
+    /// order. Selection stops once the next pick would take the block entries
+    /// past `max_bytes`. Transactions time-locked past `now` stay behind,
+    /// along with their sender's later ones.
+    pub fn select_by_fee(&self, max_txs: usize, max_bytes: usize, now: u64) -> Vec<Transaction> {
+        let mut pending = self.transactions.clone();
+        let mut selected = Vec::new();
+        let mut bytes = 0;
+        while selected.len() < max_txs {
+            let mut senders = HashSet::new();
+            let best = pending.iter()
+                .enumerate()
+                .filter(|(_, tx)| senders.insert(tx.from.as_str()))
+                .filter(|(_, tx)| tx.is_unlocked_at(now))
+                // Ties go to the transaction that arrived first
+                .max_by(|(i, a), (j, b)| a.fee.cmp(&b.fee).then(j.cmp(i)))
+                .map(|(i, _)| i);
+
+            let i = match best {
+                Some(i) => i,
+                None => break,
+            };
+            let size = pending[i].to_block_entry().len();
+            if bytes + size > max_bytes {
+                break;
+            }
+            bytes += size;
+            selected.push(pending.remove(i));
+        }
+        selected
+    }
+
+    /// Evicts transactions that landed in a block. Matching is on
+    /// `(from, nonce)`, and any of a sender's transactions whose nonce is at
+    /// or below the highest one included is evicted too, since its nonce is
+    /// now stale and it could never be included.
+    pub fn remove_included(&mut self, txs: &[Transaction]) {
+        let mut included: HashMap<&str, u64> = HashMap::new();
+        for tx in txs {
+            let nonce = included.entry(tx.from.as_str()).or_insert(tx.nonce);
+            *nonce = (*nonce).max(tx.nonce);
+        }
+
+        self.retain(|tx| match included.get(tx.from.as_str()) {
+            Some(nonce) => tx.nonce > *nonce,
+            None => true,
+        });
+    }
+
+    /// Drops transactions that have waited more than `ttl_secs` as of `now`,
+    /// returning them. A sender's later transactions go with them, since
+    /// they could only be included after the expired one.
+    pub fn prune_expired(&mut self, now: u64, ttl_secs: u64) -> Vec<Transaction> {
+        let mut expired: HashMap<String, u64> = HashMap::new();
+        for (tx, received_at) in self.transactions.iter().zip(&self.received_at) {
+            if now.saturating_sub(*received_at) > ttl_secs {
+                let nonce = expired.entry(tx.from.clone()).or_insert(tx.nonce);
+                *nonce = (*nonce).min(tx.nonce);
+            }
+        }
+        if expired.is_empty() {
+            return Vec::new();
+        }
+
+        let mut pruned = Vec::new();
+        self.retain(|tx| match expired.get(&tx.from) {
+            Some(nonce) if tx.nonce >= *nonce => {
+                pruned.push(tx.clone());
+                false
+            },
+            _ => true,
+        });
+        pruned
+    }
+
+    // Keeps the transactions `keep` accepts, along with their arrival times
+    fn retain(&mut self, mut keep: impl FnMut(&Transaction) -> bool) {
+        let mut kept = Vec::with_capacity(self.transactions.len());
+        let mut kept_at = Vec::with_capacity(self.received_at.len());
+        for (tx, received_at) in self.transactions.drain(..).zip(self.received_at.drain(..)) {
+            if keep(&tx) {
+                kept.push(tx);
+                kept_at.push(received_at);
+            }
+        }
+        self.transactions = kept;
+        self.received_at = kept_at;
+    }
+}
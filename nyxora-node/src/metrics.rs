@@ -0,0 +1,53 @@
+// Prometheus metrics for the node, served at `GET /metrics`
+use std::sync::atomic::{AtomicU64, Ordering};
+use nyxora_node::chain::consensus::HybridConsensus;
+
+/// Node metrics held in atomics. The node updates them as its state changes,
+/// so a scrape reads them without taking any of the node's locks.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    block_height: AtomicU64,
+    validator_count: AtomicU64,
+    total_stake: AtomicU64,
+    mempool_size: AtomicU64,
+    proofs_total: AtomicU64,
+}
+
+impl Metrics {
+    pub fn set_block_height(&self, height: u64) {
+        self.block_height.store(height, Ordering::Relaxed);
+    }
+
+    pub fn set_mempool_size(&self, size: usize) {
+        self.mempool_size.store(size as u64, Ordering::Relaxed);
+    }
+
+    pub fn inc_proofs(&self) {
+        self.proofs_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Copies the stake table figures; call with the consensus lock already held
+    pub fn observe_stake(&self, consensus: &HybridConsensus) {
+        self.validator_count.store(consensus.pos.validators.len() as u64, Ordering::Relaxed);
+        self.total_stake.store(consensus.pos.total_stake, Ordering::Relaxed);
+    }
+
+    /// Renders every metric in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let metrics = [
+            ("nyxora_block_height", "gauge", "Height of the latest block", &self.block_height),
+            ("nyxora_validator_count", "gauge", "Number of registered validators", &self.validator_count),
+            ("nyxora_total_stake", "gauge", "Total NYX staked across validators", &self.total_stake),
+            ("nyxora_mempool_size", "gauge", "Transactions waiting in the mempool", &self.mempool_size),
+            ("nyxora_proofs_total", "counter", "Quantum proofs accepted by this node", &self.proofs_total),
+        ];
+
+        let mut output = String::new();
+        for (name, kind, help, value) in metrics {
+            output.push_str(&format!("# HELP {} {}\n", name, help));
+            output.push_str(&format!("# TYPE {} {}\n", name, kind));
+            output.push_str(&format!("{} {}\n", name, value.load(Ordering::Relaxed)));
+        }
+        output
+    }
+}
@@ -0,0 +1,67 @@
+// Per-client token buckets guarding the HTTP API
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+struct Bucket {
+    tokens: f64,
+    last_refill: Instant,
+}
+
+/// Token-bucket limiter keyed on client IP. Each client may burst up to
+/// `burst` requests, refilled at `per_sec` tokens per second. A rate of 0
+/// disables limiting.
+pub struct RateLimiter {
+    per_sec: f64,
+    burst: f64,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>,
+}
+
+impl RateLimiter {
+    pub fn new(per_sec: u32, burst: u32) -> Self {
+        RateLimiter {
+            per_sec: per_sec as f64,
+            burst: burst.max(1) as f64,
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn allow(&self, client: IpAddr) -> bool {
+        self.allow_at(client, Instant::now())
+    }
+
+    /// Takes a token from `client`'s bucket if one is available at `now`
+    pub fn allow_at(&self, client: IpAddr, now: Instant) -> bool {
+        if self.per_sec == 0.0 {
+            return true;
+        }
+
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(client).or_insert(Bucket { tokens: self.burst, last_refill: now });
+
+        let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * self.per_sec).min(self.burst);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Drops buckets that would be full again by `now`. A fresh bucket
+    /// starts full, so forgetting them changes nothing for their clients.
+    /// Returns how many were removed.
+    pub fn prune_idle(&self, now: Instant) -> usize {
+        let mut buckets = self.buckets.lock().unwrap();
+        let before = buckets.len();
+        buckets.retain(|_, bucket| {
+            let elapsed = now.saturating_duration_since(bucket.last_refill).as_secs_f64();
+            bucket.tokens + elapsed * self.per_sec < self.burst
+        });
+        before - buckets.len()
+    }
+}
@@ -0,0 +1,105 @@
+// JSON-RPC 2.0 interface to the node, served at `POST /rpc`
+use serde_json::{json, Value};
+use super::NyxoraNode;
+
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// Server-defined error for calls the node refused, e.g. while paused
+pub const NODE_ERROR: i64 = -32000;
+
+type RpcError = (i64, String);
+
+/// Handles a request body holding a single call or a batch. Returns `None`
+/// when nothing should be sent back, i.e. every call was a notification.
+pub fn handle(node: &NyxoraNode, body: &[u8]) -> Option<Value> {
+    let request: Value = match serde_json::from_slice(body) {
+        Ok(request) => request,
+        Err(_) => return Some(error_response(Value::Null, PARSE_ERROR, "Parse error")),
+    };
+
+    match request {
+        Value::Array(calls) if calls.is_empty() => {
+            Some(error_response(Value::Null, INVALID_REQUEST, "Invalid Request"))
+        },
+        Value::Array(calls) => {
+            let responses: Vec<Value> = calls.iter().filter_map(|call| handle_call(node, call)).collect();
+            if responses.is_empty() {
+                None
+            } else {
+                Some(Value::Array(responses))
+            }
+        },
+        call => handle_call(node, &call),
+    }
+}
+
+fn handle_call(node: &NyxoraNode, call: &Value) -> Option<Value> {
+    let id = call.get("id");
+    let method = call.get("method").and_then(Value::as_str);
+    let params = call.get("params").cloned().unwrap_or(Value::Null);
+
+    let valid_id = id.is_none_or(|id| id.is_null() || id.is_string() || id.is_number());
+    let valid_params = params.is_null() || params.is_array() || params.is_object();
+    let method = match method {
+        Some(method) if call.get("jsonrpc") == Some(&json!("2.0")) && valid_id && valid_params => method,
+        // The spec asks for a null id when the request could not be understood
+        _ => return Some(error_response(Value::Null, INVALID_REQUEST, "Invalid Request")),
+    };
+
+    let result = dispatch(node, method, &params);
+
+    // Notifications carry no id and get no response
+    let id = id?.clone();
+    Some(match result {
+        Ok(result) => json!({ "jsonrpc": "2.0", "result": result, "id": id }),
+        Err((code, message)) => error_response(id, code, &message),
+    })
+}
+
+fn dispatch(node: &NyxoraNode, method: &str, params: &Value) -> Result<Value, RpcError> {
+    match method {
+        "nyx_getStatus" => Ok(json!(node.get_status())),
+        "nyx_getBlockHeight" => Ok(json!(node.get_status().block_height)),
+        "nyx_getValidators" => Ok(json!(node.get_validators())),
+        "nyx_stake" => {
+            // Accepts either `[amount]` or `{"amount": amount}`
+            let amount = params.get(0)
+                .or_else(|| params.get("amount"))
+                .and_then(Value::as_u64)
+                .ok_or((INVALID_PARAMS, "Expected a numeric amount".to_string()))?;
+
+            if node.is_paused() {
+                return Err((NODE_ERROR, "Node is paused".to_string()));
+            }
+            if node.stake_tokens(amount) {
+                Ok(json!(true))
+            } else {
+                Err((NODE_ERROR, "Stake failed".to_string()))
+            }
+        },
+        "nyx_estimateGas" => {
+            // Accepts either `[contract, function, args]` or the same as named fields
+            let param = |index: usize, name: &str| params.get(index).or_else(|| params.get(name));
+            let contract = param(0, "contract").and_then(Value::as_str);
+            let function = param(1, "function").and_then(Value::as_str);
+            let args: Option<Vec<String>> = match param(2, "args") {
+                Some(args) => serde_json::from_value(args.clone()).ok(),
+                None => Some(Vec::new()),
+            };
+
+            match (contract, function, args) {
+                (Some(contract), Some(function), Some(args)) => node.estimate_gas(contract, function, args)
+                    .map(|gas| json!(gas))
+                    .map_err(|e| (NODE_ERROR, e)),
+                _ => Err((INVALID_PARAMS, "Expected a contract, a function and string args".to_string())),
+            }
+        },
+        _ => Err((METHOD_NOT_FOUND, "Method not found".to_string())),
+    }
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "error": { "code": code, "message": message }, "id": id })
+}
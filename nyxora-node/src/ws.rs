@@ -0,0 +1,127 @@
+// WebSocket subscriptions to consensus events, served at `GET /ws`
+use std::collections::HashSet;
+use futures_util::{SinkExt, StreamExt};
+use hyper::header::{HeaderValue, CONNECTION, SEC_WEBSOCKET_ACCEPT, SEC_WEBSOCKET_KEY, UPGRADE};
+use hyper::{Body, Request, Response, StatusCode};
+use nyxora_node::chain::consensus::events::ConsensusEvent;
+use serde_json::{json, Value};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_tungstenite::tungstenite::handshake::derive_accept_key;
+use tokio_tungstenite::tungstenite::protocol::Role;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::WebSocketStream;
+
+/// Topics a client can subscribe to
+pub const TOPICS: [&str; 2] = ["newBlock", "newProof"];
+
+/// The topic an event is published under, if any
+pub fn topic(event: &ConsensusEvent) -> Option<&'static str> {
+    match event {
+        ConsensusEvent::BlockProposed(_) => Some("newBlock"),
+        ConsensusEvent::ProofAccepted { .. } => Some("newProof"),
+        _ => None,
+    }
+}
+
+/// Answers the opening handshake and, once the connection is upgraded,
+/// serves the client from `events`. Requests that are not a WebSocket
+/// handshake get 400.
+pub fn upgrade(req: Request<Body>, events: broadcast::Receiver<ConsensusEvent>) -> Response<Body> {
+    let is_websocket = req.headers().get(UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    let key = match req.headers().get(SEC_WEBSOCKET_KEY) {
+        Some(key) if is_websocket => key.clone(),
+        _ => {
+            return Response::builder()
+                .status(StatusCode::BAD_REQUEST)
+                .body(Body::from("Expected a WebSocket handshake"))
+                .unwrap()
+        },
+    };
+
+    tokio::spawn(async move {
+        match hyper::upgrade::on(req).await {
+            Ok(upgraded) => {
+                let socket = WebSocketStream::from_raw_socket(upgraded, Role::Server, None).await;
+                serve(socket, events).await;
+            },
+            Err(e) => tracing::warn!(error = %e, "websocket upgrade failed"),
+        }
+    });
+
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(UPGRADE, HeaderValue::from_static("websocket"))
+        .header(CONNECTION, HeaderValue::from_static("Upgrade"))
+        .header(SEC_WEBSOCKET_ACCEPT, derive_accept_key(key.as_bytes()))
+        .body(Body::empty())
+        .unwrap()
+}
+
+// Relays events on the topics the client asked for until either side goes
+// away. Clients send `{"subscribe": [topics]}` or `{"unsubscribe": [topics]}`
+// and are answered with the topics they are now subscribed to.
+async fn serve<S>(mut socket: WebSocketStream<S>, mut events: broadcast::Receiver<ConsensusEvent>)
+where
+    S: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin,
+{
+    let mut subscribed: HashSet<&'static str> = HashSet::new();
+    loop {
+        let reply = tokio::select! {
+            incoming = socket.next() => match incoming {
+                Some(Ok(Message::Text(text))) => handle_message(&text, &mut subscribed),
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                // Pings are answered by the socket itself
+                Some(Ok(_)) => continue,
+            },
+            event = events.recv() => match event {
+                Ok(event) => match topic(&event) {
+                    Some(topic) if subscribed.contains(topic) => json!({ "topic": topic, "event": event }),
+                    _ => continue,
+                },
+                Err(RecvError::Lagged(_)) => continue,
+                Err(RecvError::Closed) => {
+                    let _ = socket.close(None).await;
+                    break;
+                },
+            },
+        };
+        if socket.send(Message::Text(reply.to_string())).await.is_err() {
+            break;
+        }
+    }
+    tracing::debug!("websocket client disconnected");
+}
+
+fn handle_message(text: &str, subscribed: &mut HashSet<&'static str>) -> Value {
+    let request: Value = match serde_json::from_str(text) {
+        Ok(request) => request,
+        Err(_) => return json!({ "error": "Expected a JSON message" }),
+    };
+    let (names, subscribe) = match (request.get("subscribe"), request.get("unsubscribe")) {
+        (Some(names), None) => (names, true),
+        (None, Some(names)) => (names, false),
+        _ => return json!({ "error": "Expected subscribe or unsubscribe" }),
+    };
+    let names: Vec<&str> = match names.as_array() {
+        Some(names) => names.iter().filter_map(Value::as_str).collect(),
+        None => return json!({ "error": "Expected a list of topics" }),
+    };
+
+    for name in names {
+        let topic = match TOPICS.iter().find(|topic| **topic == name) {
+            Some(topic) => *topic,
+            None => return json!({ "error": format!("Unknown topic '{}'", name) }),
+        };
+        if subscribe {
+            subscribed.insert(topic);
+        } else {
+            subscribed.remove(topic);
+        }
+    }
+
+    let mut topics: Vec<&str> = subscribed.iter().copied().collect();
+    topics.sort();
+    json!({ "subscribed": topics })
+}
@@ -0,0 +1,73 @@
+// Short-lived cache of an account's balance and nonce, kept next to the
+// wallet file so repeated commands don't each query the node
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// Seconds a cached entry is used before the node is asked again
+pub const DEFAULT_CACHE_TTL_SECS: u64 = 10;
+
+/// What the wallet needs from a node; tests substitute a mock
+pub trait NodeClient {
+    fn node_url(&self) -> &str;
+    fn fetch_balance(&self, address: &str) -> Result<u64, String>;
+    fn fetch_nonce(&self, address: &str) -> Result<u64, String>;
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountCache {
+    pub node: String,
+    pub address: String,
+    pub balance: u64,
+    pub nonce: u64,      // Next nonce to use
+    pub fetched_at: u64, // Unix time of the fetch
+}
+
+/// Cache file for the wallet at `wallet_path`
+pub fn cache_path(wallet_path: &str) -> String {
+    format!("{}.cache", wallet_path)
+}
+
+impl AccountCache {
+    /// Reads the cache at `path`; a missing or unreadable file is no cache.
+    pub fn load(path: &str) -> Option<Self> {
+        serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let json = serde_json::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(path, json).map_err(|e| format!("Could not write cache file '{}': {}", path, e))
+    }
+
+    fn is_fresh(&self, now: u64, ttl_secs: u64) -> bool {
+        now.saturating_sub(self.fetched_at) < ttl_secs
+    }
+}
+
+/// Balance and nonce of `address`, served from the cache at `path` while it
+/// is younger than `ttl_secs` and for the same node and address. Otherwise
+/// both are fetched and the cache is rewritten. A `ttl_secs` of 0 always
+/// fetches.
+pub fn account_state(
+    client: &dyn NodeClient,
+    path: &str,
+    address: &str,
+    ttl_secs: u64,
+    now: u64,
+) -> Result<AccountCache, String> {
+    if let Some(cached) = AccountCache::load(path) {
+        if cached.node == client.node_url() && cached.address == address && cached.is_fresh(now, ttl_secs) {
+            return Ok(cached);
+        }
+    }
+
+    let fetched = AccountCache {
+        node: client.node_url().to_string(),
+        address: address.to_string(),
+        balance: client.fetch_balance(address)?,
+        nonce: client.fetch_nonce(address)?,
+        fetched_at: now,
+    };
+    // A cache that can't be written only costs a refetch next time
+    let _ = fetched.save(path);
+    Ok(fetched)
+}
@@ -0,0 +1,70 @@
+// Passphrase encryption of the wallet's private key at rest
+use chacha20poly1305::aead::{Aead, KeyInit};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+
+/// PBKDF2 rounds used for newly encrypted keys; stored alongside each key
+/// so it can be raised later without breaking existing wallets
+pub const PBKDF2_ITERATIONS: u32 = 100_000;
+
+const KDF_NAME: &str = "pbkdf2-sha256";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EncryptedKey {
+    pub kdf: String,
+    pub iterations: u32,
+    pub salt: String,       // Hex-encoded KDF salt
+    pub nonce: String,      // Hex-encoded ChaCha20-Poly1305 nonce
+    pub ciphertext: String, // Hex-encoded key plus authentication tag
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Key {
+    let mut key = Key::default();
+    pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), salt, iterations, &mut key);
+    key
+}
+
+impl EncryptedKey {
+    pub fn encrypt(secret: &[u8], passphrase: &str) -> Result<Self, String> {
+        let mut salt = [0u8; 16];
+        let mut nonce = [0u8; 12];
+        OsRng.fill_bytes(&mut salt);
+        OsRng.fill_bytes(&mut nonce);
+
+        let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt, PBKDF2_ITERATIONS));
+        let ciphertext = cipher.encrypt(Nonce::from_slice(&nonce), secret)
+            .map_err(|_| "Failed to encrypt private key".to_string())?;
+
+        Ok(EncryptedKey {
+            kdf: KDF_NAME.to_string(),
+            iterations: PBKDF2_ITERATIONS,
+            salt: hex::encode(salt),
+            nonce: hex::encode(nonce),
+            ciphertext: hex::encode(ciphertext),
+        })
+    }
+
+    /// Fails if the passphrase is wrong or the ciphertext was tampered with,
+    /// since either breaks the authentication tag.
+    pub fn decrypt(&self, passphrase: &str) -> Result<Vec<u8>, String> {
+        if self.kdf != KDF_NAME {
+            return Err(format!("Unsupported key derivation '{}'", self.kdf));
+        }
+
+        let decode = |field: &str, value: &str| {
+            hex::decode(value).map_err(|e| format!("Invalid {} in wallet file: {}", field, e))
+        };
+        let salt = decode("salt", &self.salt)?;
+        let nonce = decode("nonce", &self.nonce)?;
+        let ciphertext = decode("ciphertext", &self.ciphertext)?;
+        if nonce.len() != 12 {
+            return Err("Invalid nonce in wallet file".to_string());
+        }
+
+        let cipher = ChaCha20Poly1305::new(&derive_key(passphrase, &salt, self.iterations));
+        cipher.decrypt(Nonce::from_slice(&nonce), ciphertext.as_slice())
+            .map_err(|_| "Wrong passphrase or corrupted wallet file".to_string())
+    }
+}
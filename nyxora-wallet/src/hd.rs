@@ -0,0 +1,73 @@
+// BIP39 mnemonics and hierarchical key derivation. Wallet keys are Ed25519
+// seeds, so derivation follows SLIP-0010, which only defines hardened children.
+use bip39::Mnemonic;
+use hmac::{Hmac, Mac};
+use rand::rngs::OsRng;
+use rand::RngCore;
+use sha2::Sha512;
+
+/// Path used for wallets generated or restored from a mnemonic
+pub const DEFAULT_DERIVATION_PATH: &str = "m/44'/33333'/0'/0'/0'";
+
+const HARDENED_OFFSET: u32 = 0x8000_0000;
+const MASTER_KEY_SALT: &[u8] = b"ed25519 seed";
+
+/// Generates a new phrase of 12 or 24 words from OS entropy
+pub fn generate_mnemonic(word_count: usize) -> Result<Mnemonic, String> {
+    let mut entropy = match word_count {
+        12 => vec![0u8; 16],
+        24 => vec![0u8; 32],
+        _ => return Err(format!("Mnemonics have 12 or 24 words, not {}", word_count)),
+    };
+    OsRng.fill_bytes(&mut entropy);
+    Mnemonic::from_entropy(&entropy).map_err(|e| format!("Could not create mnemonic: {}", e))
+}
+
+pub fn parse_mnemonic(phrase: &str) -> Result<Mnemonic, String> {
+    Mnemonic::parse_normalized(phrase.trim()).map_err(|e| format!("Invalid mnemonic: {}", e))
+}
+
+/// Parses a path like `m/44'/33333'/0'`. Every index must be hardened.
+fn parse_path(path: &str) -> Result<Vec<u32>, String> {
+    let mut segments = path.split('/');
+    if segments.next() != Some("m") {
+        return Err(format!("Derivation path '{}' must start with 'm'", path));
+    }
+
+    segments
+        .map(|segment| {
+            let index = segment.strip_suffix('\'')
+                .or_else(|| segment.strip_suffix('h'))
+                .ok_or_else(|| format!("Derivation path index '{}' must be hardened", segment))?;
+            match index.parse::<u32>() {
+                Ok(index) if index < HARDENED_OFFSET => Ok(index + HARDENED_OFFSET),
+                _ => Err(format!("Invalid derivation path index '{}'", segment)),
+            }
+        })
+        .collect()
+}
+
+fn hmac_sha512(key: &[u8], data: &[u8]) -> ([u8; 32], [u8; 32]) {
+    let mut mac = Hmac::<Sha512>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    let output = mac.finalize().into_bytes();
+
+    let mut key = [0u8; 32];
+    let mut chain_code = [0u8; 32];
+    key.copy_from_slice(&output[..32]);
+    chain_code.copy_from_slice(&output[32..]);
+    (key, chain_code)
+}
+
+/// Derives the private key at `path` from a BIP39 seed
+pub fn derive_key(seed: &[u8], path: &str) -> Result<[u8; 32], String> {
+    let (mut key, mut chain_code) = hmac_sha512(MASTER_KEY_SALT, seed);
+    for index in parse_path(path)? {
+        let mut data = Vec::with_capacity(37);
+        data.push(0);
+        data.extend_from_slice(&key);
+        data.extend_from_slice(&index.to_be_bytes());
+        (key, chain_code) = hmac_sha512(&chain_code, &data);
+    }
+    Ok(key)
+}
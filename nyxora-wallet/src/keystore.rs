@@ -0,0 +1,109 @@
+// Import of Ethereum-style (Web3 Secret Storage v3) JSON keystores
+use aes::cipher::{KeyIvInit, StreamCipher};
+use serde::Deserialize;
+use sha3::{Digest, Keccak256};
+
+type Aes128Ctr = ctr::Ctr128BE<aes::Aes128>;
+
+#[derive(Debug, Deserialize)]
+pub struct Keystore {
+    pub version: u32,
+    #[serde(alias = "Crypto")]
+    pub crypto: KeystoreCrypto,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct KeystoreCrypto {
+    pub cipher: String,
+    pub cipherparams: CipherParams,
+    pub ciphertext: String,
+    #[serde(flatten)]
+    pub kdf: Kdf,
+    pub mac: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct CipherParams {
+    pub iv: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "kdf", content = "kdfparams", rename_all = "lowercase")]
+pub enum Kdf {
+    Scrypt {
+        dklen: usize,
+        n: u64,
+        r: u32,
+        p: u32,
+        salt: String,
+    },
+    Pbkdf2 {
+        dklen: usize,
+        c: u32,
+        prf: String,
+        salt: String,
+    },
+}
+
+fn decode(field: &str, value: &str) -> Result<Vec<u8>, String> {
+    hex::decode(value.trim_start_matches("0x")).map_err(|e| format!("Invalid {} in keystore: {}", field, e))
+}
+
+impl Kdf {
+    fn derive_key(&self, passphrase: &str) -> Result<Vec<u8>, String> {
+        match self {
+            Kdf::Scrypt { dklen, n, r, p, salt } => {
+                if !n.is_power_of_two() || *n < 2 {
+                    return Err(format!("Invalid scrypt parameter n = {}", n));
+                }
+                let params = scrypt::Params::new(n.trailing_zeros() as u8, *r, *p, *dklen)
+                    .map_err(|e| format!("Invalid scrypt parameters: {}", e))?;
+                let mut key = vec![0u8; *dklen];
+                scrypt::scrypt(passphrase.as_bytes(), &decode("salt", salt)?, &params, &mut key)
+                    .map_err(|e| format!("Key derivation failed: {}", e))?;
+                Ok(key)
+            },
+            Kdf::Pbkdf2 { dklen, c, prf, salt } => {
+                if prf != "hmac-sha256" {
+                    return Err(format!("Unsupported pbkdf2 prf '{}'", prf));
+                }
+                let mut key = vec![0u8; *dklen];
+                pbkdf2::pbkdf2_hmac::<sha2::Sha256>(passphrase.as_bytes(), &decode("salt", salt)?, *c, &mut key);
+                Ok(key)
+            },
+        }
+    }
+}
+
+impl Keystore {
+    /// Decrypts the private key, checking the MAC first so a wrong
+    /// passphrase or a tampered file is rejected rather than yielding garbage.
+    pub fn decrypt(&self, passphrase: &str) -> Result<Vec<u8>, String> {
+        if self.version != 3 {
+            return Err(format!("Unsupported keystore version {}", self.version));
+        }
+        if self.crypto.cipher != "aes-128-ctr" {
+            return Err(format!("Unsupported keystore cipher '{}'", self.crypto.cipher));
+        }
+
+        let derived_key = self.crypto.kdf.derive_key(passphrase)?;
+        if derived_key.len() < 32 {
+            return Err("Keystore dklen must be at least 32".to_string());
+        }
+        let ciphertext = decode("ciphertext", &self.crypto.ciphertext)?;
+
+        let mut hasher = Keccak256::new();
+        hasher.update(&derived_key[16..32]);
+        hasher.update(&ciphertext);
+        if hasher.finalize().as_slice() != decode("mac", &self.crypto.mac)?.as_slice() {
+            return Err("Keystore MAC mismatch: wrong passphrase or corrupted keystore".to_string());
+        }
+
+        let iv = decode("iv", &self.crypto.cipherparams.iv)?;
+        let mut cipher = Aes128Ctr::new_from_slices(&derived_key[..16], &iv)
+            .map_err(|_| "Invalid keystore IV".to_string())?;
+        let mut private_key = ciphertext;
+        cipher.apply_keystream(&mut private_key);
+        Ok(private_key)
+    }
+}
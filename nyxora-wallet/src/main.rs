@@ -4,14 +4,43 @@ use std::fs;
 use std::path::Path;
 use rand::rngs::OsRng;
 use sha3::{Sha3_256, Digest};
-use hex;
+use nyxora_node::chain::crypto;
+use nyxora_node::chain::transaction::Transaction;
+
+mod cache;
+mod encryption;
+mod hd;
+mod keystore;
+
+/// Balance `stake --percent` keeps back by default so fees can still be paid
+const DEFAULT_FEE_RESERVE: u64 = 1;
 
 #[derive(Parser)]
 #[command(name = "nyxora-wallet")]
 #[command(about = "A CLI wallet for the Nyxora quantum-hybrid cryptocurrency")]
 enum Cli {
     /// Generate a new wallet
-    Generate,
+    Generate {
+        /// Passphrase to encrypt the wallet with; prompted for when omitted
+        #[arg(long)]
+        passphrase: Option<String>,
+        /// Derive the key from a new mnemonic phrase and print it for backup
+        #[arg(long)]
+        mnemonic: bool,
+        /// Number of words in the mnemonic, 12 or 24
+        #[arg(long, default_value_t = 12, requires = "mnemonic")]
+        words: usize,
+    },
+
+    /// Recreate a wallet from its mnemonic phrase
+    Restore {
+        phrase: String,
+        /// Passphrase to encrypt the wallet with; prompted for when omitted
+        #[arg(long)]
+        passphrase: Option<String>,
+        #[arg(short, long, default_value = "wallet.json")]
+        out: String,
+    },
 
     /// Show wallet address
     Address {
@@ -23,6 +52,16 @@ enum Cli {
     Balance {
         #[arg(short, long, default_value = "wallet.json")]
         file: String,
+
+        /// Node URL to query, e.g. http://localhost:33333
+        #[arg(long)]
+        node: Option<String>,
+        /// Always ask the node instead of using a recent cached answer
+        #[arg(long)]
+        no_cache: bool,
+        /// Seconds a cached balance and nonce are reused
+        #[arg(long, default_value_t = cache::DEFAULT_CACHE_TTL_SECS)]
+        cache_ttl: u64,
     },
 
     /// Send tokens to another address
@@ -31,13 +70,50 @@ enum Cli {
         amount: f64,
         #[arg(short, long, default_value = "wallet.json")]
         file: String,
+        /// Wallet passphrase; prompted for when omitted
+        #[arg(long)]
+        passphrase: Option<String>,
+        /// Unix time before which the transaction may not be included in a block
+        #[arg(long)]
+        valid_after: Option<u64>,
+        /// Node URL to take the sender's next nonce from
+        #[arg(long)]
+        node: Option<String>,
+        /// Always ask the node instead of using a recent cached answer
+        #[arg(long)]
+        no_cache: bool,
+        /// Seconds a cached balance and nonce are reused
+        #[arg(long, default_value_t = cache::DEFAULT_CACHE_TTL_SECS)]
+        cache_ttl: u64,
     },
 
     /// Stake tokens
     Stake {
-        amount: f64,
+        /// Amount to stake; give either this or --percent
+        amount: Option<f64>,
+        /// Stake this percentage of the balance reported by --node instead
+        #[arg(long, conflicts_with = "amount")]
+        percent: Option<f64>,
+        /// Balance --percent leaves unstaked to pay fees
+        #[arg(long, default_value_t = DEFAULT_FEE_RESERVE)]
+        reserve: u64,
+        #[arg(short, long, default_value = "wallet.json")]
+        file: String,
+        /// Wallet passphrase; prompted for when omitted
+        #[arg(long)]
+        passphrase: Option<String>,
+        /// Node URL to fetch the estimated staking yield from
+        #[arg(long)]
+        node: Option<String>,
+    },
+
+    /// List the transactions sent from this wallet
+    History {
         #[arg(short, long, default_value = "wallet.json")]
         file: String,
+        /// Node URL to refresh each transaction's status from
+        #[arg(long)]
+        node: Option<String>,
     },
 
     /// Sign a message
@@ -45,6 +121,57 @@ enum Cli {
         message: String,
         #[arg(short, long, default_value = "wallet.json")]
         file: String,
+        /// Wallet passphrase; prompted for when omitted
+        #[arg(long)]
+        passphrase: Option<String>,
+    },
+
+    /// Import the private key from a V3 JSON keystore
+    ImportKeystore {
+        keystore: String,
+        #[arg(long)]
+        passphrase: String,
+        #[arg(short, long, default_value = "wallet.json")]
+        out: String,
+    },
+
+    /// Show the public key this wallet contributes to multisig addresses
+    MultisigKey {
+        #[arg(short, long, default_value = "wallet.json")]
+        file: String,
+        /// Wallet passphrase; prompted for when omitted
+        #[arg(long)]
+        passphrase: Option<String>,
+    },
+
+    /// Create an m-of-n multisig address from participants' public keys
+    MultisigCreate {
+        #[arg(long)]
+        threshold: usize,
+        #[arg(required = true)]
+        public_keys: Vec<String>,
+        #[arg(short, long, default_value = "multisig.json")]
+        out: String,
+    },
+
+    /// Produce this wallet's partial signature over a message
+    MultisigSign {
+        message: String,
+        #[arg(short, long, default_value = "wallet.json")]
+        file: String,
+        /// Wallet passphrase; prompted for when omitted
+        #[arg(long)]
+        passphrase: Option<String>,
+    },
+
+    /// Check that enough partial signatures are present for a multisig
+    MultisigCombine {
+        message: String,
+        /// Partial signatures in `public_key:signature` form
+        #[arg(required = true)]
+        partials: Vec<String>,
+        #[arg(short, long, default_value = "multisig.json")]
+        multisig: String,
     },
 }
 
@@ -53,6 +180,108 @@ struct Wallet {
     private_key: String,
     public_key: String,
     address: String,
+    /// Set when the key was derived from a mnemonic
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    derivation_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    history: Vec<TxRecord>,
+}
+
+/// A transaction sent from this wallet, kept in the wallet file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct TxRecord {
+    to: String,
+    amount: f64,
+    timestamp: u64,
+    tx_hash: String,
+    /// `sent` until a node reports it `pending` or `confirmed`
+    status: String,
+}
+
+impl TxRecord {
+    fn new(tx: &Transaction, amount: f64) -> Self {
+        TxRecord {
+            to: tx.to.clone(),
+            amount,
+            timestamp: unix_now(),
+            tx_hash: tx.hash(),
+            status: "sent".to_string(),
+        }
+    }
+}
+
+/// On-disk wallet. Wallets are written encrypted; plaintext files from older
+/// versions are still read.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum WalletFile {
+    Encrypted {
+        address: String,
+        public_key: String,
+        encrypted_private_key: encryption::EncryptedKey,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        derivation_path: Option<String>,
+        #[serde(default, skip_serializing_if = "Vec::is_empty")]
+        history: Vec<TxRecord>,
+    },
+    Plaintext(Wallet),
+}
+
+impl WalletFile {
+    fn read(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    fn address(&self) -> &str {
+        match self {
+            WalletFile::Encrypted { address, .. } => address,
+            WalletFile::Plaintext(wallet) => &wallet.address,
+        }
+    }
+
+    fn write(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn history(&self) -> &[TxRecord] {
+        match self {
+            WalletFile::Encrypted { history, .. } => history,
+            WalletFile::Plaintext(wallet) => &wallet.history,
+        }
+    }
+
+    fn history_mut(&mut self) -> &mut Vec<TxRecord> {
+        match self {
+            WalletFile::Encrypted { history, .. } => history,
+            WalletFile::Plaintext(wallet) => &mut wallet.history,
+        }
+    }
+
+    /// Appends `record` to the wallet's history. The key stays encrypted, so
+    /// no passphrase is needed.
+    fn record_transaction(path: &str, record: TxRecord) -> Result<(), Box<dyn std::error::Error>> {
+        let mut file = Self::read(path)?;
+        file.history_mut().push(record);
+        file.write(path)
+    }
+}
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+// Asks for the wallet passphrase on the terminal without echoing it
+fn prompt_passphrase(confirm: bool) -> Result<String, String> {
+    let passphrase = rpassword::prompt_password("Wallet passphrase: ")
+        .map_err(|e| format!("Could not read passphrase: {}", e))?;
+    if confirm && rpassword::prompt_password("Repeat passphrase: ").ok().as_ref() != Some(&passphrase) {
+        return Err("Passphrases do not match".to_string());
+    }
+    Ok(passphrase)
 }
 
 impl Wallet {
@@ -64,35 +293,105 @@ impl Wallet {
             *byte = rand::RngCore::next_u32(&mut rng) as u8;
         }
 
-        let private_key = hex::encode(&private_key_bytes);
+        Self::from_private_key(&private_key_bytes)
+    }
+
+    fn from_private_key(private_key_bytes: &[u8]) -> Self {
+        let private_key = hex::encode(private_key_bytes);
 
         // Derive public key (in a real implementation, this would be proper ECC)
         // For now, we'll hash the private key to simulate public key derivation
         let mut hasher = Sha3_256::new();
-        hasher.update(&private_key_bytes);
+        hasher.update(private_key_bytes);
         let public_key_bytes = hasher.finalize();
-        let public_key = hex::encode(&public_key_bytes);
+        let public_key = hex::encode(public_key_bytes);
 
-        // Generate address starting with 'Q' as specified
-        let address = format!("Q{}", &public_key[..39]); // Make it start with Q and be 40 chars
+        // Generate address starting with 'Q' as specified, 40 chars with its checksum
+        let address = crypto::checksum_address(&public_key[..crypto::ADDRESS_BODY_LEN]);
 
         Wallet {
             private_key,
             public_key,
             address,
+            derivation_path: None,
+            history: Vec::new(),
         }
     }
 
-    fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let json = serde_json::to_string_pretty(self)?;
-        fs::write(path, json)?;
-        Ok(())
+    /// Checks an address's format and checksum, catching most typos
+    fn validate_address(address: &str) -> bool {
+        crypto::validate_address(address)
     }
 
-    fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
-        let contents = fs::read_to_string(path)?;
-        let wallet: Wallet = serde_json::from_str(&contents)?;
-        Ok(wallet)
+    /// Derives the wallet at `path` from a BIP39 phrase. The phrase is used
+    /// without a BIP39 passphrase; the wallet passphrase only encrypts the file.
+    fn from_mnemonic(phrase: &str, path: &str) -> Result<Self, String> {
+        let seed = hd::parse_mnemonic(phrase)?.to_seed("");
+        let private_key = hd::derive_key(&seed, path)?;
+        Ok(Wallet {
+            derivation_path: Some(path.to_string()),
+            ..Self::from_private_key(&private_key)
+        })
+    }
+
+    /// Recovers a wallet from a V3 JSON keystore, as written by Ethereum tooling
+    fn import_keystore(json: &str, passphrase: &str) -> Result<Self, String> {
+        let keystore: keystore::Keystore = serde_json::from_str(json)
+            .map_err(|e| format!("Invalid keystore file: {}", e))?;
+        let private_key = keystore.decrypt(passphrase)?;
+        if private_key.len() != 32 {
+            return Err(format!("Keystore holds a {}-byte key, expected 32", private_key.len()));
+        }
+        Ok(Self::from_private_key(&private_key))
+    }
+
+    /// Writes the wallet with its private key encrypted under `passphrase`
+    fn save(&self, path: &str, passphrase: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if passphrase.is_empty() {
+            return Err("The wallet passphrase must not be empty".into());
+        }
+
+        let file = WalletFile::Encrypted {
+            address: self.address.clone(),
+            public_key: self.public_key.clone(),
+            encrypted_private_key: encryption::EncryptedKey::encrypt(&self.private_key_bytes()?, passphrase)?,
+            derivation_path: self.derivation_path.clone(),
+            history: self.history.clone(),
+        };
+        file.write(path)
+    }
+
+    /// Reads a wallet, decrypting it with `passphrase` or one typed at a
+    /// prompt. Plaintext wallets need no passphrase.
+    fn load(path: &str, passphrase: Option<&str>) -> Result<Self, Box<dyn std::error::Error>> {
+        match WalletFile::read(path)? {
+            WalletFile::Plaintext(wallet) => Ok(wallet),
+            WalletFile::Encrypted { encrypted_private_key, derivation_path, history, .. } => {
+                let passphrase = match passphrase {
+                    Some(passphrase) => passphrase.to_string(),
+                    None => prompt_passphrase(false)?,
+                };
+                let private_key = encrypted_private_key.decrypt(&passphrase)?;
+                Ok(Wallet { derivation_path, history, ..Self::from_private_key(&private_key) })
+            },
+        }
+    }
+
+    fn private_key_bytes(&self) -> Result<Vec<u8>, String> {
+        hex::decode(&self.private_key).map_err(|e| format!("Corrupt private key: {}", e))
+    }
+
+    /// Ed25519 public key derived from the private key, used for multisig
+    fn multisig_public_key(&self) -> Result<String, String> {
+        crypto::public_key_hex(&self.private_key_bytes()?)
+    }
+
+    fn multisig_sign(&self, message: &str) -> Result<PartialSignature, String> {
+        let seed = self.private_key_bytes()?;
+        Ok(PartialSignature {
+            public_key: crypto::public_key_hex(&seed)?,
+            signature: crypto::sign(&seed, message.as_bytes())?,
+        })
     }
 
     fn sign_message(&self, message: &str) -> String {
@@ -102,23 +401,308 @@ impl Wallet {
         hasher.update(&self.private_key);
         hasher.update(message);
         let signature_bytes = hasher.finalize();
-        hex::encode(&signature_bytes)
+        hex::encode(signature_bytes)
+    }
+}
+
+/// An m-of-n multisig account over Ed25519 public keys
+#[derive(Debug, Serialize, Deserialize)]
+struct MultisigWallet {
+    public_keys: Vec<String>,
+    threshold: usize,
+    address: String,
+}
+
+/// One participant's signature, written as `public_key:signature`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+struct PartialSignature {
+    public_key: String,
+    signature: String,
+}
+
+impl std::str::FromStr for PartialSignature {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (public_key, signature) = s.split_once(':')
+            .ok_or_else(|| format!("Partial signature '{}' is not in public_key:signature form", s))?;
+        Ok(PartialSignature {
+            public_key: public_key.to_string(),
+            signature: signature.to_string(),
+        })
+    }
+}
+
+impl std::fmt::Display for PartialSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.public_key, self.signature)
+    }
+}
+
+impl MultisigWallet {
+    fn new(mut public_keys: Vec<String>, threshold: usize) -> Result<Self, String> {
+        public_keys.sort();
+        public_keys.dedup();
+
+        if threshold == 0 || threshold > public_keys.len() {
+            return Err(format!(
+                "Threshold must be between 1 and the number of distinct keys ({})",
+                public_keys.len()
+            ));
+        }
+        if let Some(key) = public_keys.iter().find(|key| hex::decode(key).map(|bytes| bytes.len()) != Ok(32)) {
+            return Err(format!("'{}' is not a hex-encoded Ed25519 public key", key));
+        }
+
+        // The threshold is hashed in too, so a 2-of-3 and a 3-of-3 over the
+        // same keys get different addresses
+        let mut hasher = Sha3_256::new();
+        hasher.update(threshold.to_string());
+        hasher.update(public_keys.concat());
+        let address = crypto::checksum_address(&hex::encode(hasher.finalize())[..crypto::ADDRESS_BODY_LEN]);
+
+        Ok(MultisigWallet {
+            public_keys,
+            threshold,
+            address,
+        })
+    }
+
+    fn save(&self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        fs::write(path, serde_json::to_string_pretty(self)?)?;
+        Ok(())
+    }
+
+    fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        Ok(serde_json::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    /// Keeps the valid partials from distinct participants, failing unless at
+    /// least `threshold` of them remain.
+    fn combine(&self, message: &str, partials: &[PartialSignature]) -> Result<Vec<PartialSignature>, String> {
+        let mut valid: Vec<PartialSignature> = Vec::new();
+        for partial in partials {
+            let is_member = self.public_keys.contains(&partial.public_key);
+            let is_new = !valid.iter().any(|seen| seen.public_key == partial.public_key);
+            if is_member && is_new && crypto::verify(&partial.public_key, message.as_bytes(), &partial.signature) {
+                valid.push(partial.clone());
+            }
+        }
+
+        if valid.len() < self.threshold {
+            return Err(format!(
+                "Only {} of the required {} valid signatures are present",
+                valid.len(),
+                self.threshold
+            ));
+        }
+        Ok(valid)
+    }
+}
+
+#[derive(Deserialize)]
+struct BalanceResponse {
+    balance: u64,
+}
+
+// Sends a GET for `path` to a running node
+fn get_from_node(node_url: &str, path: &str) -> Result<reqwest::blocking::Response, String> {
+    let url = format!("{}{}", node_url.trim_end_matches('/'), path);
+
+    reqwest::blocking::Client::builder()
+        .timeout(std::time::Duration::from_secs(10))
+        .build()
+        .and_then(|client| client.get(&url).send())
+        .map_err(|e| format!("Could not reach node at {}: {}", node_url, e))
+}
+
+// Asks a running node for the current balance of `address`
+fn fetch_balance(node_url: &str, address: &str) -> Result<u64, String> {
+    let response = get_from_node(node_url, &format!("/balance/{}", address))?;
+    if !response.status().is_success() {
+        return Err(format!("Node at {} returned {}", node_url, response.status()));
+    }
+
+    response.json::<BalanceResponse>()
+        .map(|body| body.balance)
+        .map_err(|e| format!("Invalid balance response from {}: {}", node_url, e))
+}
+
+#[derive(Deserialize)]
+struct NonceResponse {
+    nonce: u64,
+}
+
+// Asks a running node for the next nonce `address` should use
+fn fetch_nonce(node_url: &str, address: &str) -> Result<u64, String> {
+    let response = get_from_node(node_url, &format!("/nonce/{}", address))?;
+    if !response.status().is_success() {
+        return Err(format!("Node at {} returned {}", node_url, response.status()));
+    }
+
+    response.json::<NonceResponse>()
+        .map(|body| body.nonce)
+        .map_err(|e| format!("Invalid nonce response from {}: {}", node_url, e))
+}
+
+/// A node reached over HTTP at the given URL
+struct HttpNode<'a>(&'a str);
+
+impl cache::NodeClient for HttpNode<'_> {
+    fn node_url(&self) -> &str {
+        self.0
+    }
+
+    fn fetch_balance(&self, address: &str) -> Result<u64, String> {
+        fetch_balance(self.0, address)
+    }
+
+    fn fetch_nonce(&self, address: &str) -> Result<u64, String> {
+        fetch_nonce(self.0, address)
+    }
+}
+
+#[derive(Deserialize)]
+struct TxStatusResponse {
+    status: String,
+}
+
+// Asks a node whether it has seen the transaction with `tx_hash`; `None`
+// when it has not
+fn fetch_tx_status(node_url: &str, tx_hash: &str) -> Result<Option<String>, String> {
+    let response = get_from_node(node_url, &format!("/tx/{}", tx_hash))?;
+    if response.status() == reqwest::StatusCode::NOT_FOUND {
+        return Ok(None);
+    }
+    if !response.status().is_success() {
+        return Err(format!("Node at {} returned {}", node_url, response.status()));
+    }
+
+    response.json::<TxStatusResponse>()
+        .map(|body| Some(body.status))
+        .map_err(|e| format!("Invalid transaction response from {}: {}", node_url, e))
+}
+
+#[derive(Deserialize)]
+struct AprResponse {
+    apr: f64,
+}
+
+// Asks a running node for the estimated yearly yield of staking with
+// `address`, as a fraction
+fn fetch_apr(node_url: &str, address: &str) -> Result<f64, String> {
+    let response = get_from_node(node_url, &format!("/apr/{}", address))?;
+    if !response.status().is_success() {
+        return Err(format!("Node at {} returned {}", node_url, response.status()));
+    }
+
+    response.json::<AprResponse>()
+        .map(|body| body.apr)
+        .map_err(|e| format!("Invalid APR response from {}: {}", node_url, e))
+}
+
+// Works out how much to stake from either an absolute amount or a
+// percentage of the balance above `reserve`. The balance is only known when
+// a node was asked for it.
+fn stake_amount(amount: Option<f64>, percent: Option<f64>, balance: Option<u64>, reserve: u64) -> Result<f64, String> {
+    let percent = match (amount, percent) {
+        (Some(_), Some(_)) => return Err("Give either an amount or --percent, not both".to_string()),
+        (Some(amount), None) => return Ok(amount),
+        (None, None) => return Err("Give an amount to stake or --percent".to_string()),
+        (None, Some(percent)) => percent,
+    };
+    if !(percent > 0.0 && percent <= 100.0) {
+        return Err(format!("--percent must be above 0 and at most 100, got {}", percent));
     }
+    let balance = balance.ok_or("--percent needs --node to read the balance")?;
+
+    let amount = (balance.saturating_sub(reserve) as f64 * percent / 100.0).floor();
+    if amount <= 0.0 {
+        return Err(format!("A balance of {} NYX leaves nothing to stake above the {} NYX reserve", balance, reserve));
+    }
+    Ok(amount)
+}
+
+// Renders the history as a table, oldest first
+fn format_history(records: &[TxRecord]) -> String {
+    if records.is_empty() {
+        return "No transactions yet\n".to_string();
+    }
+
+    let mut table = format!("{:<16}  {:<40}  {:>12}  {:<10}  {}\n", "TX HASH", "TO", "AMOUNT", "STATUS", "TIMESTAMP");
+    for record in records {
+        table.push_str(&format!(
+            "{:<16}  {:<40}  {:>12}  {:<10}  {}\n",
+            &record.tx_hash[..record.tx_hash.len().min(16)],
+            record.to,
+            record.amount,
+            record.status,
+            record.timestamp,
+        ));
+    }
+    table
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let cli = Cli::parse();
 
     match cli {
-        Cli::Generate => {
+        Cli::Generate { passphrase, mnemonic, words } => {
+            let phrase = if mnemonic {
+                match hd::generate_mnemonic(words) {
+                    Ok(phrase) => Some(phrase.to_string()),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                }
+            } else {
+                None
+            };
+
+            let passphrase = match passphrase {
+                Some(passphrase) => passphrase,
+                None => prompt_passphrase(true)?,
+            };
+
             println!("Generating new Nyxora wallet...");
-            let wallet = Wallet::new();
+            let wallet = match &phrase {
+                Some(phrase) => Wallet::from_mnemonic(phrase, hd::DEFAULT_DERIVATION_PATH)?,
+                None => Wallet::new(),
+            };
 
             // Save to default file
-            wallet.save("wallet.json")?;
+            wallet.save("wallet.json", &passphrase)?;
             println!("Wallet generated successfully!");
             println!("Address: {}", wallet.address);
             println!("Wallet saved to wallet.json");
+            if let Some(phrase) = phrase {
+                println!("Mnemonic (write it down; it restores this wallet): {}", phrase);
+            }
+        },
+
+        Cli::Restore { phrase, passphrase, out } => {
+            if Path::new(&out).exists() {
+                eprintln!("Wallet file '{}' already exists. Choose another path with --out.", out);
+                std::process::exit(1);
+            }
+
+            let wallet = match Wallet::from_mnemonic(&phrase, hd::DEFAULT_DERIVATION_PATH) {
+                Ok(wallet) => wallet,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+            let passphrase = match passphrase {
+                Some(passphrase) => passphrase,
+                None => prompt_passphrase(true)?,
+            };
+
+            wallet.save(&out, &passphrase)?;
+            println!("Wallet restored successfully!");
+            println!("Address: {}", wallet.address);
+            println!("Wallet saved to {}", out);
         },
 
         Cli::Address { file } => {
@@ -127,61 +711,662 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 std::process::exit(1);
             }
 
-            let wallet = Wallet::load(&file)?;
-            println!("{}", wallet.address);
+            println!("{}", WalletFile::read(&file)?.address());
         },
 
-        Cli::Balance { file } => {
+        Cli::Balance { file, node, no_cache, cache_ttl } => {
             if !Path::new(&file).exists() {
                 eprintln!("Wallet file '{}' does not exist. Generate a wallet first.", file);
                 std::process::exit(1);
             }
 
-            let wallet = Wallet::load(&file)?;
-            // In a real implementation, this would query the blockchain
-            // For now, we'll return a mock balance
-            println!("Balance for {}: 100.0 NYX", wallet.address);
+            // Only the address is needed, so the wallet stays encrypted
+            let wallet = WalletFile::read(&file)?;
+            let address = wallet.address();
+            let ttl = if no_cache { 0 } else { cache_ttl };
+            match node {
+                Some(node) => match cache::account_state(&HttpNode(&node), &cache::cache_path(&file), address, ttl, unix_now()) {
+                    Ok(account) => println!("Balance for {}: {} NYX", address, account.balance),
+                    Err(e) => {
+                        eprintln!("{}", e);
+                        std::process::exit(1);
+                    }
+                },
+                // Without a node to ask, fall back to a mock balance
+                None => println!("Balance for {}: 100.0 NYX", address),
+            }
         },
 
-        Cli::Send { to, amount, file } => {
+        Cli::Send { to, amount, file, passphrase, valid_after, node, no_cache, cache_ttl } => {
+            if !Wallet::validate_address(&to) {
+                eprintln!("'{}' is not a valid address; check it for typos.", to);
+                std::process::exit(1);
+            }
             if !Path::new(&file).exists() {
                 eprintln!("Wallet file '{}' does not exist. Generate a wallet first.", file);
                 std::process::exit(1);
             }
 
-            let wallet = Wallet::load(&file)?;
+            let wallet = Wallet::load(&file, passphrase.as_deref())?;
             println!("Sending {} NYX from {} to {}", amount, wallet.address, to);
+            if let Some(valid_after) = valid_after {
+                println!("Time-locked until unix time {}", valid_after);
+            }
+
+            // The nonce comes from the node when one is given, through the cache
+            let cache_file = cache::cache_path(&file);
+            let account = match &node {
+                Some(node) => {
+                    let ttl = if no_cache { 0 } else { cache_ttl };
+                    match cache::account_state(&HttpNode(node), &cache_file, &wallet.address, ttl, unix_now()) {
+                        Ok(account) => Some(account),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                },
+                None => None,
+            };
 
             // In a real implementation, this would create and broadcast a transaction
             // For now, we'll just simulate the action
+            let tx = Transaction {
+                from: wallet.address.clone(),
+                to,
+                amount: amount as u64,
+                nonce: account.as_ref().map(|account| account.nonce).unwrap_or(0),
+                fee: 0,
+                valid_after,
+                public_key: String::new(),
+                signature: String::new(),
+            };
+            WalletFile::record_transaction(&file, TxRecord::new(&tx, amount))?;
+            // The nonce is now used, so a cached one must move on with it
+            if let Some(account) = account {
+                cache::AccountCache { nonce: account.nonce + 1, ..account }.save(&cache_file)?;
+            }
             println!("Transaction created and broadcasted successfully!");
+            println!("Transaction hash: {}", tx.hash());
         },
 
-        Cli::Stake { amount, file } => {
+        Cli::Stake { amount, percent, reserve, file, passphrase, node } => {
             if !Path::new(&file).exists() {
                 eprintln!("Wallet file '{}' does not exist. Generate a wallet first.", file);
                 std::process::exit(1);
             }
 
-            let wallet = Wallet::load(&file)?;
+            let wallet = Wallet::load(&file, passphrase.as_deref())?;
+            // The balance is only needed to resolve a percentage
+            let balance = match (&node, percent) {
+                (Some(node), Some(_)) => {
+                    let account = cache::account_state(
+                        &HttpNode(node),
+                        &cache::cache_path(&file),
+                        &wallet.address,
+                        cache::DEFAULT_CACHE_TTL_SECS,
+                        unix_now(),
+                    );
+                    match account {
+                        Ok(account) => Some(account.balance),
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                },
+                _ => None,
+            };
+            let amount = match stake_amount(amount, percent, balance, reserve) {
+                Ok(amount) => amount,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
             println!("Staking {} NYX from {}", amount, wallet.address);
+            if let Some(node) = node {
+                // The estimate is informational, so staking goes ahead without it
+                match fetch_apr(&node, &wallet.address) {
+                    Ok(apr) => println!("Estimated APR: {:.2}%", apr * 100.0),
+                    Err(e) => eprintln!("Could not estimate APR: {}", e),
+                }
+            }
 
             // In a real implementation, this would stake tokens on the blockchain
             // For now, we'll just simulate the action
+            let tx = Transaction {
+                from: wallet.address.clone(),
+                to: "stake".to_string(),
+                amount: amount as u64,
+                nonce: 0,
+                fee: 0,
+                valid_after: None,
+                public_key: String::new(),
+                signature: String::new(),
+            };
+            WalletFile::record_transaction(&file, TxRecord::new(&tx, amount))?;
             println!("Tokens staked successfully!");
         },
 
-        Cli::Sign { message, file } => {
+        Cli::History { file, node } => {
+            if !Path::new(&file).exists() {
+                eprintln!("Wallet file '{}' does not exist. Generate a wallet first.", file);
+                std::process::exit(1);
+            }
+
+            let mut wallet = WalletFile::read(&file)?;
+            if let Some(node) = node {
+                for record in wallet.history_mut().iter_mut() {
+                    match fetch_tx_status(&node, &record.tx_hash) {
+                        Ok(Some(status)) => record.status = status,
+                        Ok(None) => {},
+                        Err(e) => {
+                            eprintln!("{}", e);
+                            std::process::exit(1);
+                        }
+                    }
+                }
+                wallet.write(&file)?;
+            }
+            print!("{}", format_history(wallet.history()));
+        },
+
+        Cli::Sign { message, file, passphrase } => {
             if !Path::new(&file).exists() {
                 eprintln!("Wallet file '{}' does not exist. Generate a wallet first.", file);
                 std::process::exit(1);
             }
 
-            let wallet = Wallet::load(&file)?;
+            let wallet = Wallet::load(&file, passphrase.as_deref())?;
             let signature = wallet.sign_message(&message);
             println!("Signature: {}", signature);
         },
+
+        Cli::ImportKeystore { keystore, passphrase, out } => {
+            if Path::new(&out).exists() {
+                eprintln!("Wallet file '{}' already exists. Choose another path with --out.", out);
+                std::process::exit(1);
+            }
+
+            let json = fs::read_to_string(&keystore)?;
+            let wallet = match Wallet::import_keystore(&json, &passphrase) {
+                Ok(wallet) => wallet,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            // The imported wallet is protected by the keystore's passphrase
+            wallet.save(&out, &passphrase)?;
+            println!("Keystore imported successfully!");
+            println!("Address: {}", wallet.address);
+            println!("Wallet saved to {}", out);
+        },
+
+        Cli::MultisigKey { file, passphrase } => {
+            if !Path::new(&file).exists() {
+                eprintln!("Wallet file '{}' does not exist. Generate a wallet first.", file);
+                std::process::exit(1);
+            }
+
+            let wallet = Wallet::load(&file, passphrase.as_deref())?;
+            println!("{}", wallet.multisig_public_key()?);
+        },
+
+        Cli::MultisigCreate { threshold, public_keys, out } => {
+            let multisig = match MultisigWallet::new(public_keys, threshold) {
+                Ok(multisig) => multisig,
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            };
+
+            multisig.save(&out)?;
+            println!("Created {}-of-{} multisig", multisig.threshold, multisig.public_keys.len());
+            println!("Address: {}", multisig.address);
+            println!("Multisig saved to {}", out);
+        },
+
+        Cli::MultisigSign { message, file, passphrase } => {
+            if !Path::new(&file).exists() {
+                eprintln!("Wallet file '{}' does not exist. Generate a wallet first.", file);
+                std::process::exit(1);
+            }
+
+            let wallet = Wallet::load(&file, passphrase.as_deref())?;
+            println!("Partial signature: {}", wallet.multisig_sign(&message)?);
+        },
+
+        Cli::MultisigCombine { message, partials, multisig } => {
+            let multisig = MultisigWallet::load(&multisig)?;
+            let partials = partials.iter()
+                .map(|partial| partial.parse())
+                .collect::<Result<Vec<PartialSignature>, String>>()?;
+
+            match multisig.combine(&message, &partials) {
+                Ok(valid) => {
+                    println!("Threshold met: {} of {} signatures valid for {}", valid.len(), multisig.threshold, multisig.address);
+                    println!("{}", serde_json::to_string_pretty(&valid)?);
+                },
+                Err(e) => {
+                    eprintln!("{}", e);
+                    std::process::exit(1);
+                }
+            }
+        },
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+    use std::process::{Child, Command, Stdio};
+
+    // A `nyxora-node` process, killed when dropped
+    struct SpawnedNode {
+        url: String,
+        child: Child,
+        config_path: std::path::PathBuf,
+    }
+
+    impl Drop for SpawnedNode {
+        fn drop(&mut self) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+            let _ = fs::remove_file(&self.config_path);
+        }
+    }
+
+    // Starts the workspace's `nyxora-node` binary with `config` on a free
+    // port, building it first so the test runs against the current code,
+    // and waits until it answers `/health`
+    fn spawn_node(config: serde_json::Value) -> SpawnedNode {
+        let cargo = std::env::var("CARGO").unwrap_or_else(|_| "cargo".to_string());
+        let built = Command::new(cargo)
+            .args(["build", "--quiet", "-p", "nyxora-node", "--bin", "nyxora-node"])
+            .status()
+            .unwrap();
+        assert!(built.success(), "could not build nyxora-node");
+        // Test binaries live in `target/<profile>/deps`
+        let binary = std::env::current_exe().unwrap()
+            .parent().unwrap()
+            .parent().unwrap()
+            .join(format!("nyxora-node{}", std::env::consts::EXE_SUFFIX));
+
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let config_path = std::env::temp_dir().join(format!("nyxora-node-config-{}-{}.json", std::process::id(), port));
+        fs::write(&config_path, config.to_string()).unwrap();
+        let child = Command::new(binary)
+            .args(["--config", config_path.to_str().unwrap(), "--port", &port.to_string()])
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .unwrap();
+        let node = SpawnedNode { url: format!("http://127.0.0.1:{}", port), child, config_path };
+
+        for _ in 0..100 {
+            if get_from_node(&node.url, "/health").is_ok_and(|response| response.status().is_success()) {
+                return node;
+            }
+            std::thread::sleep(std::time::Duration::from_millis(100));
+        }
+        panic!("nyxora-node did not start on port {}", port);
+    }
+
+    #[test]
+    fn test_fetch_balance_from_node() {
+        let wallet = Wallet::new();
+        let node = spawn_node(serde_json::json!({
+            "address": "Q123456789012345678901234567890123456789",
+            "stake_amount": 1000,
+            "is_validator": false,
+            "quantum_enabled": false,
+            "peers": [],
+            "genesis_allocations": { wallet.address.clone(): 4200 },
+        }));
+
+        assert_eq!(fetch_balance(&node.url, &wallet.address), Ok(4200));
+        assert_eq!(fetch_balance(&format!("{}/", node.url), &wallet.address), Ok(4200));
+        assert_eq!(fetch_balance(&node.url, &Wallet::new().address), Ok(0));
+    }
+
+    // Answers from fixed values and counts the requests it receives
+    struct MockNode {
+        balance: u64,
+        nonce: u64,
+        requests: std::cell::Cell<usize>,
+    }
+
+    impl cache::NodeClient for MockNode {
+        fn node_url(&self) -> &str {
+            "http://mock"
+        }
+
+        fn fetch_balance(&self, _address: &str) -> Result<u64, String> {
+            self.requests.set(self.requests.get() + 1);
+            Ok(self.balance)
+        }
+
+        fn fetch_nonce(&self, _address: &str) -> Result<u64, String> {
+            self.requests.set(self.requests.get() + 1);
+            Ok(self.nonce)
+        }
+    }
+
+    #[test]
+    fn test_cached_account_is_reused_within_ttl() {
+        let path = temp_wallet_path("cache-fresh");
+        let node = MockNode { balance: 250, nonce: 3, requests: std::cell::Cell::new(0) };
+
+        let first = cache::account_state(&node, &path, "Qalice", 10, 1_000).unwrap();
+        assert_eq!((first.balance, first.nonce, first.fetched_at), (250, 3, 1_000));
+        assert_eq!(node.requests.get(), 2);
+
+        let second = cache::account_state(&node, &path, "Qalice", 10, 1_009).unwrap();
+        assert_eq!(second, first);
+        assert_eq!(node.requests.get(), 2);
+
+        // Another account isn't served from this entry
+        cache::account_state(&node, &path, "Qbob", 10, 1_009).unwrap();
+        assert_eq!(node.requests.get(), 4);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_expired_cache_entry_is_refetched() {
+        let path = temp_wallet_path("cache-expired");
+        let mut node = MockNode { balance: 250, nonce: 3, requests: std::cell::Cell::new(0) };
+        cache::account_state(&node, &path, "Qalice", 10, 1_000).unwrap();
+
+        node.balance = 180;
+        let refreshed = cache::account_state(&node, &path, "Qalice", 10, 1_010).unwrap();
+        assert_eq!((refreshed.balance, refreshed.fetched_at), (180, 1_010));
+        assert_eq!(node.requests.get(), 4);
+        assert_eq!(cache::AccountCache::load(&path), Some(refreshed));
+
+        // A TTL of 0, as with --no-cache, always asks the node
+        cache::account_state(&node, &path, "Qalice", 0, 1_010).unwrap();
+        assert_eq!(node.requests.get(), 6);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_stake_percent_of_balance_above_reserve() {
+        assert_eq!(stake_amount(None, Some(50.0), Some(1_001), 1), Ok(500.0));
+        assert_eq!(stake_amount(None, Some(100.0), Some(1_000), 10), Ok(990.0));
+        // Fractions of a token are left unstaked
+        assert_eq!(stake_amount(None, Some(33.0), Some(101), 1), Ok(33.0));
+        assert_eq!(stake_amount(Some(250.0), None, None, 1), Ok(250.0));
+
+        assert!(stake_amount(None, Some(50.0), Some(1), 1).is_err());
+        assert!(stake_amount(None, Some(150.0), Some(1_000), 1).is_err());
+        assert!(stake_amount(None, Some(0.0), Some(1_000), 1).is_err());
+        assert_eq!(stake_amount(None, Some(50.0), None, 1), Err("--percent needs --node to read the balance".to_string()));
+    }
+
+    #[test]
+    fn test_stake_amount_and_percent_are_exclusive() {
+        assert_eq!(
+            stake_amount(Some(100.0), Some(50.0), Some(1_000), 1),
+            Err("Give either an amount or --percent, not both".to_string())
+        );
+        assert_eq!(stake_amount(None, None, Some(1_000), 1), Err("Give an amount to stake or --percent".to_string()));
+
+        // The command line refuses the combination before anything runs
+        let err = Cli::try_parse_from(["nyxora-wallet", "stake", "100", "--percent", "50"]).err().unwrap();
+        assert_eq!(err.kind(), clap::error::ErrorKind::ArgumentConflict);
+        assert!(Cli::try_parse_from(["nyxora-wallet", "stake", "--percent", "50", "--node", "http://localhost:33333"]).is_ok());
+    }
+
+    #[test]
+    fn test_multisig_two_of_three() {
+        let wallets: Vec<Wallet> = (0..3).map(|_| Wallet::new()).collect();
+        let keys = wallets.iter().map(|w| w.multisig_public_key().unwrap()).collect::<Vec<_>>();
+
+        let multisig = MultisigWallet::new(keys.clone(), 2).unwrap();
+        // Key order does not change the address
+        let reversed = MultisigWallet::new(keys.into_iter().rev().collect(), 2).unwrap();
+        assert_eq!(multisig.address, reversed.address);
+        assert!(multisig.address.starts_with('Q'));
+
+        let message = "pay treasury 500";
+        let first = wallets[0].multisig_sign(message).unwrap();
+        let second = wallets[2].multisig_sign(message).unwrap();
+
+        let partial: PartialSignature = first.to_string().parse().unwrap();
+        assert_eq!(partial, first);
+
+        assert_eq!(multisig.combine(message, &[first.clone(), second]).unwrap().len(), 2);
+
+        // One signature, even when repeated, does not meet the threshold
+        let err = multisig.combine(message, &[first.clone(), first]).unwrap_err();
+        assert_eq!(err, "Only 1 of the required 2 valid signatures are present");
+    }
+
+    #[test]
+    fn test_multisig_rejects_outsiders_and_bad_threshold() {
+        let wallets: Vec<Wallet> = (0..3).map(|_| Wallet::new()).collect();
+        let keys = wallets[..2].iter().map(|w| w.multisig_public_key().unwrap()).collect::<Vec<_>>();
+        let multisig = MultisigWallet::new(keys.clone(), 1).unwrap();
+
+        let outsider = wallets[2].multisig_sign("message").unwrap();
+        assert!(multisig.combine("message", &[outsider]).is_err());
+
+        let member = wallets[0].multisig_sign("message").unwrap();
+        assert!(multisig.combine("tampered", &[member]).is_err());
+
+        assert!(MultisigWallet::new(keys.clone(), 3).is_err());
+        assert!(MultisigWallet::new(keys, 0).is_err());
+    }
+
+    fn temp_wallet_path(name: &str) -> String {
+        std::env::temp_dir()
+            .join(format!("nyxora-{}-{}.json", name, std::process::id()))
+            .to_string_lossy()
+            .to_string()
+    }
+
+    #[test]
+    fn test_encrypted_wallet_round_trip() {
+        let path = temp_wallet_path("encrypted");
+        let wallet = Wallet::new();
+        wallet.save(&path, "correct horse").unwrap();
+
+        // The private key is not written in the clear
+        let contents = fs::read_to_string(&path).unwrap();
+        assert!(!contents.contains(&wallet.private_key));
+        assert_eq!(WalletFile::read(&path).unwrap().address(), wallet.address);
+
+        let loaded = Wallet::load(&path, Some("correct horse")).unwrap();
+        assert_eq!(loaded.private_key, wallet.private_key);
+        assert_eq!(loaded.address, wallet.address);
+
+        let err = Wallet::load(&path, Some("wrong horse")).err().unwrap();
+        assert_eq!(err.to_string(), "Wrong passphrase or corrupted wallet file");
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_plaintext_wallet_still_loads() {
+        let path = temp_wallet_path("plaintext");
+        let wallet = Wallet::new();
+        fs::write(&path, serde_json::to_string_pretty(&wallet).unwrap()).unwrap();
+
+        let loaded = Wallet::load(&path, None).unwrap();
+        assert_eq!(loaded.private_key, wallet.private_key);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_send_appends_history_record() {
+        let path = temp_wallet_path("history");
+        let wallet = Wallet::new();
+        wallet.save(&path, "correct horse").unwrap();
+
+        let tx = Transaction {
+            from: wallet.address.clone(),
+            to: "Q3F2a9C1E8b7D6054A1c9e2f3B4d5a6c7e8f9012".to_string(),
+            amount: 5,
+            nonce: 0,
+            fee: 0,
+            valid_after: None,
+            public_key: String::new(),
+            signature: String::new(),
+        };
+        WalletFile::record_transaction(&path, TxRecord::new(&tx, 5.0)).unwrap();
+
+        let history = WalletFile::read(&path).unwrap().history().to_vec();
+        assert_eq!(history.len(), 1);
+        assert_eq!(history[0].to, tx.to);
+        assert_eq!(history[0].tx_hash, tx.hash());
+        assert_eq!(history[0].status, "sent");
+
+        // Decrypting and re-saving the wallet keeps its history
+        let loaded = Wallet::load(&path, Some("correct horse")).unwrap();
+        assert_eq!(loaded.history, history);
+        loaded.save(&path, "correct horse").unwrap();
+        assert_eq!(WalletFile::read(&path).unwrap().history(), history.as_slice());
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_history_table_format() {
+        assert_eq!(format_history(&[]), "No transactions yet\n");
+
+        let records = [
+            TxRecord {
+                to: "Q3F2a9C1E8b7D6054A1c9e2f3B4d5a6c7e8f9012".to_string(),
+                amount: 12.5,
+                timestamp: 1_700_000_000,
+                tx_hash: "0123456789abcdef0123456789abcdef".to_string(),
+                status: "confirmed".to_string(),
+            },
+            TxRecord {
+                to: "stake".to_string(),
+                amount: 100.0,
+                timestamp: 1_700_000_060,
+                tx_hash: "fedcba9876543210fedcba9876543210".to_string(),
+                status: "sent".to_string(),
+            },
+        ];
+        assert_eq!(
+            format_history(&records),
+            "TX HASH           TO                                              AMOUNT  STATUS      TIMESTAMP\n\
+             0123456789abcdef  Q3F2a9C1E8b7D6054A1c9e2f3B4d5a6c7e8f9012          12.5  confirmed   1700000000\n\
+             fedcba9876543210  stake                                              100  sent        1700000060\n"
+        );
+    }
+
+    // Test vector from the Web3 Secret Storage definition, passphrase "testpassword"
+    const PBKDF2_KEYSTORE: &str = r#"{
+        "crypto": {
+            "cipher": "aes-128-ctr",
+            "cipherparams": { "iv": "6087dab2f9fdbbfaddc31a909735c1e6" },
+            "ciphertext": "5318b4d5bcd28de64ee5559e671353e16f075ecae9f99c7a79a38af5f869aa46",
+            "kdf": "pbkdf2",
+            "kdfparams": {
+                "c": 262144,
+                "dklen": 32,
+                "prf": "hmac-sha256",
+                "salt": "ae3cd4e7013836a3df6bd7241b12db061dbe2c6785853cce422d148a624ce0bd"
+            },
+            "mac": "517ead924a9d0dc3124507e3393d175ce3ff7c1e96529c6c555ce9e51205e9b2"
+        },
+        "id": "3198bc9c-6672-5ab3-d995-4942343ae5b6",
+        "version": 3
+    }"#;
+
+    #[test]
+    fn test_import_known_keystore() {
+        let wallet = Wallet::import_keystore(PBKDF2_KEYSTORE, "testpassword").unwrap();
+        assert_eq!(wallet.private_key, "7a28b5ba57c53603b0b07b56bba752f7784bf506fa95edc395f5cf6c7514fe9d");
+
+        let expected = Wallet::from_private_key(&hex::decode(&wallet.private_key).unwrap());
+        assert_eq!(wallet.address, expected.address);
+        assert!(wallet.address.starts_with('Q') && wallet.address.len() == 40);
+    }
+
+    #[test]
+    fn test_address_checksum_catches_typos() {
+        let generated = Wallet::new();
+        assert!(Wallet::validate_address(&generated.address));
+
+        let address = Wallet::from_private_key(&[7u8; 32]).address;
+        assert!(Wallet::validate_address(&address));
+        assert!(address.chars().any(|c| c.is_ascii_uppercase()));
+
+        // Swap one character for another hex digit
+        let mut typo: Vec<char> = address.chars().collect();
+        typo[20] = if typo[20] == '0' { '1' } else { '0' };
+        assert!(!Wallet::validate_address(&typo.into_iter().collect::<String>()));
+
+        // Changing only the case of a letter breaks the checksum too
+        assert!(!Wallet::validate_address(&format!("Q{}", address[1..].to_lowercase())));
+        assert!(!Wallet::validate_address(&address[..39]));
+        assert!(!Wallet::validate_address("Qvalidator123"));
+    }
+
+    #[test]
+    fn test_import_keystore_rejects_bad_passphrase_and_mac() {
+        let err = Wallet::import_keystore(PBKDF2_KEYSTORE, "wrongpassword").err().unwrap();
+        assert!(err.starts_with("Keystore MAC mismatch"));
+
+        let tampered = PBKDF2_KEYSTORE.replace("517ead92", "00000000");
+        let err = Wallet::import_keystore(&tampered, "testpassword").err().unwrap();
+        assert!(err.starts_with("Keystore MAC mismatch"));
+    }
+
+    const TEST_MNEMONIC: &str = "abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon abandon about";
+
+    #[test]
+    fn test_mnemonic_derivation_is_deterministic() {
+        let wallet = Wallet::from_mnemonic(TEST_MNEMONIC, hd::DEFAULT_DERIVATION_PATH).unwrap();
+        let again = Wallet::from_mnemonic(&format!("  {} ", TEST_MNEMONIC), hd::DEFAULT_DERIVATION_PATH).unwrap();
+        assert_eq!(wallet.address, again.address);
+        assert_eq!(wallet.derivation_path.as_deref(), Some(hd::DEFAULT_DERIVATION_PATH));
+
+        // Another account on the same phrase gets its own key
+        let other = Wallet::from_mnemonic(TEST_MNEMONIC, "m/44'/33333'/1'/0'/0'").unwrap();
+        assert_ne!(other.address, wallet.address);
+
+        assert!(Wallet::from_mnemonic("abandon abandon abandon", hd::DEFAULT_DERIVATION_PATH).is_err());
+        assert!(Wallet::from_mnemonic(TEST_MNEMONIC, "m/44'/33333'/0").is_err());
+    }
+
+    #[test]
+    fn test_restore_reproduces_generated_wallet() {
+        for words in [12, 24] {
+            let phrase = hd::generate_mnemonic(words).unwrap().to_string();
+            assert_eq!(phrase.split_whitespace().count(), words);
+
+            let path = temp_wallet_path(&format!("mnemonic-{}", words));
+            let generated = Wallet::from_mnemonic(&phrase, hd::DEFAULT_DERIVATION_PATH).unwrap();
+            generated.save(&path, "backup").unwrap();
+
+            let loaded = Wallet::load(&path, Some("backup")).unwrap();
+            assert_eq!(loaded.derivation_path.as_deref(), Some(hd::DEFAULT_DERIVATION_PATH));
+
+            let restored = Wallet::from_mnemonic(&phrase, loaded.derivation_path.as_deref().unwrap()).unwrap();
+            assert_eq!(restored.address, generated.address);
+            assert_eq!(restored.private_key, generated.private_key);
+            fs::remove_file(&path).unwrap();
+        }
+        assert!(hd::generate_mnemonic(15).is_err());
+    }
+
+    #[test]
+    fn test_fetch_balance_unreachable_node() {
+        // Bind and immediately drop a listener so the port is closed
+        let port = TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+        let url = format!("http://127.0.0.1:{}", port);
+
+        let err = fetch_balance(&url, "Qalice").unwrap_err();
+        assert!(err.starts_with(&format!("Could not reach node at {}", url)));
+    }
+}
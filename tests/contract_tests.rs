@@ -92,7 +92,7 @@ mod contract_tests {
         let result = runtime.execute_contract(
             "quantum_nft",
             "mintNFT",
-            vec!["ipfs://nft-metadata".to_string(), "0xquantumproofhash".to_string()]
+            vec!["ipfs://nft-metadata".to_string(), "0x9e3779b97f4a7c15".to_string()]
         ).unwrap();
 
         assert!(result.success);